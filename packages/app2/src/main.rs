@@ -1,6 +1,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::sync::Arc;
+use std::path::PathBuf;
+use std::time::Duration;
 use std::collections::HashMap;
 use image::EncodableLayout;
 use eframe::{
@@ -12,8 +14,11 @@ use hero_core::{
 	Icon,
 	uuid::Uuid,
 	state::{ State, LoadingBarType },
+	instance::{ Instance, NewInstance, INSTANCE_ARCHIVE_EXTENSION, create_instance, delete_instance, duplicate_instance, export_instance, import_instance, move_instance, repair_instance, restore_instance, trashed_instances },
+	downloader::Checksum,
+	extension::{ Capability, ExtensionError, ExtensionRelease, ExtensionManifest, SettingKind, disable_extension, enable_extension, install_release, reload_extensions, shutdown_extensions, trust_key, trust_library, uninstall_extension },
 	parking_lot::RwLock,
-	user_interface::Element
+	user_interface::{ Element, PathSelectKind, Value }
 };
 use poll_promise::Promise;
 
@@ -27,7 +32,7 @@ mod blur;
 mod widget;
 //mod gif_loader;
 
-use widget::{ PathSelect, NavigationItem };
+use widget::{ PathSelect, LoadingBarItem, NavigationItem };
 
 #[tokio::main]
 async fn main() -> Result<(), eframe::Error> {
@@ -60,10 +65,46 @@ enum AppPage {
 	SelectGame(bool),
 	Home,
 	Library,
+	Settings,
+	Extensions,
 	Instance(Uuid),
 	Extension(String)
 }
 
+enum ExtensionAction {
+	Enable(String),
+	Disable(String),
+	Uninstall(String),
+	Trust(PathBuf),
+	TrustKey(String),
+	UntrustKey(String),
+	Grant(String, Vec<Capability>),
+	Revoke(String),
+	Reload
+}
+
+enum InstanceAction {
+	Rename(Uuid, String),
+	Duplicate(Uuid),
+	Move(Uuid, PathBuf),
+	Export(Uuid, PathBuf),
+	Delete(Uuid),
+	Repair(PathBuf),
+	Restore(PathBuf)
+}
+
+/// What the user has filled in so far in the "New instance" window.
+#[derive(Default)]
+struct NewInstanceWizard {
+	name: String,
+	icon: String,
+
+	/// Values of the game's creation options, by key.
+	options: HashMap<String, String>,
+	error: Option<String>,
+	creating: Option<Promise<Result<Uuid, String>>>
+}
+
 #[derive(serde::Deserialize)]
 struct WebExtension {
 	id: String,
@@ -82,14 +123,53 @@ struct WebExtensionCreator {
 	display_name: String
 }
 
+#[derive(serde::Deserialize)]
+struct WebExtensionRelease {
+	manifest: ExtensionManifest,
+	binaries: Vec<WebExtensionBinary>
+}
+
+#[derive(serde::Deserialize)]
+struct WebExtensionBinary {
+	/// One of the platform bits used by [`WebExtension::supported_platforms`].
+	platform: u8,
+	url: String,
+	size: Option<u64>,
+	sha256: String,
+	signature: Option<String>
+}
+
+const PLATFORM_WINDOWS: u8 = 1 << 0;
+const PLATFORM_MACOS: u8 = 1 << 1;
+const PLATFORM_LINUX: u8 = 1 << 2;
+
+fn current_platform() -> u8 {
+	if cfg!(target_os = "windows") {
+		PLATFORM_WINDOWS
+	} else if cfg!(target_os = "macos") {
+		PLATFORM_MACOS
+	} else {
+		PLATFORM_LINUX
+	}
+}
+
 struct HEROApp {
 	page: Arc<RwLock<AppPage>>,
 	images: HashMap<String, egui::Image<'static>>,
 	texture: Option<egui::TextureHandle>,
 	screenshot: Option<ColorImage>,
 	extensions: HashMap<String, Promise<WebExtension>>, 
+	extension_releases: HashMap<String, Promise<Option<WebExtensionRelease>>>,
+	extension_installs: HashMap<String, Promise<Result<(), String>>>,
 	instance_page: String,
-	instance_banners: HashMap<Uuid, egui::TextureHandle>
+	instance_banners: HashMap<Uuid, egui::TextureHandle>,
+	new_instance: Option<NewInstanceWizard>,
+
+	/// Instance being renamed, along with the name typed so far.
+	renaming: Option<(Uuid, String)>,
+
+	/// Public key of a publisher typed on the extensions page, but not trusted yet.
+	publisher_key: String
 }
 
 impl HEROApp {
@@ -128,14 +208,22 @@ impl HEROApp {
 		ctx.set_fonts(fonts);
 
 		let state = State::get();
-		state.extensions.write().load_libraries(state.path.join("extensions"));
+		state.extensions_mut().unwrap().load_libraries(state.path.join("extensions"));
 
 		hero_core::extension::load_extensions();
+		state.downloader().unwrap().resume_queue();
 
-		state.localisation.write().insert_data("en-AU", vec![
+		state.localisation_mut().unwrap().insert_data("en-AU", vec![
 			("loading.load_game.0", "Loading game information..."),
 			("loading.load_game.1", "Loading instances..."),
-			("loading.load_game.2", "\\^o^/")
+			("loading.load_game.2", "\\^o^/"),
+			("loading.download", "Downloading {}"),
+			("loading.extract", "Extracting {}"),
+			("loading.install_content", "Installing {}"),
+			("loading.create_instance", "Creating {}"),
+			("loading.paused", "{} (paused)"),
+			("loading.pause", "Pause"),
+			("loading.resume", "Resume")
 		]);
 
 		let page = Arc::new(RwLock::new(AppPage::SelectGame(false)));
@@ -163,8 +251,13 @@ impl HEROApp {
 			texture: None,
 			screenshot: None,
 			extensions: HashMap::new(),
+			extension_releases: HashMap::new(),
+			extension_installs: HashMap::new(),
 			instance_page: "global_instance_info".into(),
-			instance_banners: HashMap::new()
+			instance_banners: HashMap::new(),
+			new_instance: None,
+			renaming: None,
+			publisher_key: String::new()
 		}
 	}
 }
@@ -185,6 +278,10 @@ impl eframe::App for HEROApp {
 		if cfg!(target_os = "windows") { [0., 0., 0., 0.] } else { [0.05, 0.05, 0.05, 1.] }
 	}
 
+	fn on_exit(&mut self) {
+		shutdown_extensions(&State::get()).unwrap();
+	}
+
 	fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
 		let last_page = self.page.read().clone();
 		let frame_panel = egui::Frame {
@@ -204,7 +301,7 @@ impl eframe::App for HEROApp {
 			}
 		}
 
-		for instance in state.instances.read().items.values() {
+		for instance in state.instances().unwrap().items.values() {
 			let key = format!("instance_{}", instance.id());
 			if !self.images.contains_key(&key) {
 				if let Some(icon) = instance.icon() {
@@ -215,7 +312,7 @@ impl eframe::App for HEROApp {
 			}
 		}
 
-		for instance in state.instances.read().items.values() {
+		for instance in state.instances().unwrap().items.values() {
 			let id = instance.id();
 			if !self.instance_banners.contains_key(&id) {
 				let image = if let Some(icon) = instance.icon() {
@@ -277,8 +374,9 @@ impl eframe::App for HEROApp {
 						.tint(Color32::from_white_alpha(64))
 						.paint_at(&mut content_ui, Rect::from_center_size(content_rect.center(), Vec2::splat(content_rect.max.to_vec2().max_elem())));
 					
-					let loading_game = state.loading_bars.read().values().find_map(|x| match &x.bar_type {
-						LoadingBarType::LoadGame { game_id } => state.get_game(game_id.clone()).map(|g| (g, x.current.clone()))
+					let loading_game = state.loading_bars().values().find_map(|x| match &x.bar_type {
+						LoadingBarType::LoadGame { game_id } => state.get_game(game_id.clone()).map(|g| (g, x.current.clone())),
+						_ => None
 					});
 					if loading_game.is_none() {
 						title_bar_ui(ctx, &mut content_ui, frame, bar_rect, true);
@@ -371,6 +469,91 @@ impl eframe::App for HEROApp {
 
 							content_ui.painter()
 								.text(content_rect.left_top() + vec2(48., 200.), Align2::LEFT_TOP, extension.display_name.clone(), FontId::new(32., FontFamily::Name("inter-700".into())), Color32::WHITE);
+
+							let release = self.extension_releases.entry(id.clone()).or_insert_with(|| {
+								let id = id.clone();
+								Promise::spawn_thread("extension_release", move || {
+									reqwest::blocking::get(format!("https://hero-devs.com/api/v1/extension/{}/release", id))
+										.and_then(|x| x.error_for_status())
+										.and_then(|x| x.json::<WebExtensionRelease>())
+										.ok()
+								})
+							});
+
+							let rect = Rect::from_min_max(content_rect.left_top() + vec2(48., 248.), content_rect.max);
+							content_ui.allocate_ui_at_rect(rect, |ui| ui.vertical(|ui| {
+								ui.spacing_mut().item_spacing.y = 8.;
+								ui.label(RichText::new(format!("by {}", extension.creator.display_name)).family(FontFamily::Name("inter-400".into())));
+
+								if extension.supported_platforms & current_platform() == 0 {
+									ui.label(RichText::new("Not available for your platform").color(Color32::from_white_alpha(64)));
+									return;
+								}
+
+								let Some(release) = release.ready() else {
+									ui.add(Spinner::new());
+									return;
+								};
+								let Some(release) = release else {
+									ui.label(RichText::new("No release is available").color(Color32::from_white_alpha(64)));
+									return;
+								};
+								let Some(binary) = release.binaries.iter().find(|x| x.platform & current_platform() != 0) else {
+									ui.label(RichText::new("Not available for your platform").color(Color32::from_white_alpha(64)));
+									return;
+								};
+
+								let install = self.extension_installs.get(&id);
+								if install.is_some_and(|x| x.ready().is_none()) {
+									ui.horizontal(|ui| {
+										ui.add(Spinner::new());
+										ui.label("Installing...");
+									});
+									return;
+								}
+								if let Some(Err(err)) = install.and_then(|x| x.ready()) {
+									ui.label(RichText::new(err).color(Color32::from_rgb(255, 128, 128)));
+								}
+
+								if !release.manifest.capabilities.is_empty() {
+									ui.label(RichText::new("Installing this extension allows it to:").family(FontFamily::Name("inter-500".into())));
+									for capability in &release.manifest.capabilities {
+										ui.label(RichText::new(format!("• {}", capability)).family(FontFamily::Name("inter-400".into())));
+									}
+								}
+
+								let installed = state.extensions().unwrap().manifest(&release.manifest.id).map(|x| x.version.clone());
+								let mut start_install = false;
+								ui.horizontal(|ui| match &installed {
+									Some(version) => {
+										ui.label(format!("Version {} is installed", version));
+										if *version != release.manifest.version && ui.button(format!("Update to {}", release.manifest.version)).clicked() {
+											start_install = true;
+										}
+										if ui.button("Uninstall").clicked() {
+											if let Err(err) = uninstall_extension(&state, &release.manifest.id) {
+												println!("failed to uninstall extension! {}", err);
+											}
+										}
+									},
+									None => if ui.button(format!("Install {}", release.manifest.version)).clicked() {
+										start_install = true;
+									}
+								});
+
+								if start_install {
+									let release = ExtensionRelease {
+										manifest: release.manifest.clone(),
+										url: binary.url.clone(),
+										size: binary.size,
+										checksum: Checksum::Sha256(binary.sha256.clone()),
+										signature: binary.signature.clone()
+									};
+									self.extension_installs.insert(id.clone(), Promise::spawn_thread("extension_install", move || {
+										install_release(&release, &release.manifest.capabilities).map_err(|x| x.to_string())
+									}));
+								}
+							}));
 						}
 					} else {
 						self.extensions.insert(id.clone(), Promise::spawn_thread("extension_get", move || {
@@ -400,6 +583,51 @@ impl eframe::App for HEROApp {
 							if ui.add(NavigationItem::new("Library", egui::include_image!("icon/collection.svg"), matches!(page, AppPage::Library))).clicked() {
 								*self.page.write() = AppPage::Library;
 							}
+							if ui.add(NavigationItem::new("Extensions", egui::include_image!("icon/grid.svg"), matches!(page, AppPage::Extensions))).clicked() {
+								*self.page.write() = AppPage::Extensions;
+							}
+							if ui.add(NavigationItem::new("Settings", egui::include_image!("icon/gear.svg"), matches!(page, AppPage::Settings))).clicked() {
+								*self.page.write() = AppPage::Settings;
+							}
+
+							let loading_bars = state.loading_bars();
+							let mut loading_bars: Vec<_> = loading_bars.values().filter_map(|x| match &x.bar_type {
+								LoadingBarType::LoadGame { .. } => None,
+								LoadingBarType::Download { name, .. } => Some((state.t("loading.download".into()).replace("{}", name), x)),
+								LoadingBarType::Extract { name, .. } => Some((state.t("loading.extract".into()).replace("{}", name), x)),
+								LoadingBarType::InstallContent { item_id, .. } => Some((state.t("loading.install_content".into()).replace("{}", item_id), x)),
+								LoadingBarType::CreateInstance { name, .. } => Some((state.t("loading.create_instance".into()).replace("{}", name), x))
+							}).collect();
+							let paused: Vec<_> = state.downloader().unwrap().items().into_iter().filter(|x| x.is_paused()).collect();
+							if !loading_bars.is_empty() || !paused.is_empty() {
+								loading_bars.sort_by(|a, b| a.0.cmp(&b.0));
+
+								ui.add_space(24.);
+								ui.label(
+									RichText::new("Downloads")
+										.size(12.)
+										.color(Color32::from_white_alpha(32))
+										.family(FontFamily::Name("inter-600".into()))
+								);
+								ui.spacing_mut().item_spacing.y = 8.;
+								for (title, bar) in loading_bars {
+									ui.add(LoadingBarItem::new(title, bar));
+									if let LoadingBarType::Download { download_id, .. } = &bar.bar_type {
+										if ui.small_button(state.t("loading.pause".into())).clicked() {
+											if let Some(download) = state.downloader().unwrap().items().into_iter().find(|x| x.id() == *download_id) {
+												download.pause();
+											}
+										}
+									}
+								}
+								for download in paused {
+									ui.label(state.t("loading.paused".into()).replace("{}", &download.name()));
+									if ui.small_button(state.t("loading.resume".into())).clicked() {
+										download.resume();
+									}
+								}
+								ctx.request_repaint_after(Duration::from_millis(100));
+							}
 						});
 
 						let mut content_rect = content_rect;
@@ -418,11 +646,28 @@ impl eframe::App for HEROApp {
 										.color(Color32::WHITE)
 										.family(FontFamily::Name("inter-700".into()))
 								);
-								ui.add_space(24.);
+								ui.add_space(8.);
+								ui.horizontal(|ui| {
+									if ui.button("New instance").clicked() && self.new_instance.is_none() {
+										self.new_instance = Some(NewInstanceWizard::default());
+									}
+									if ui.button("Import instance").clicked() {
+										if let Some(path) = rfd::FileDialog::new().add_filter("HERO instance", &[INSTANCE_ARCHIVE_EXTENSION]).pick_file() {
+											std::thread::spawn(move || {
+												if let Err(err) = import_instance(&path) {
+													println!("failed to import instance from {:?}! {}", path, err);
+												}
+											});
+										}
+									}
+								});
+								ui.add_space(16.);
+
+								let mut action: Option<InstanceAction> = None;
 								ui.horizontal(|ui| {
 									ui.spacing_mut().item_spacing = Vec2::X * 40.;
 
-									for instance in state.instances.read().items.values() {
+									for instance in state.instances().unwrap().items.values() {
 										let (rect, response) = ui.allocate_exact_size(vec2(160., 210.), Sense::click().union(Sense::hover()));
 										let target = if response.hovered() {
 											ui.output_mut(|x| x.cursor_icon = CursorIcon::PointingHand);
@@ -461,12 +706,432 @@ impl eframe::App for HEROApp {
 										if response.clicked() {
 											*self.page.write() = AppPage::Instance(instance.id());
 										}
+										response.context_menu(|ui| {
+											let id = instance.id();
+											if ui.button("Rename").clicked() {
+												action = Some(InstanceAction::Rename(id, instance.display_name()));
+												ui.close_menu();
+											}
+											if ui.button("Duplicate").clicked() {
+												action = Some(InstanceAction::Duplicate(id));
+												ui.close_menu();
+											}
+											ui.menu_button("Move to", |ui| {
+												for directory in state.instance_directories() {
+													if !instance.path.starts_with(&directory) && ui.button(directory.to_string_lossy()).clicked() {
+														action = Some(InstanceAction::Move(id, directory));
+														ui.close_menu();
+													}
+												}
+												if ui.button("Another folder...").clicked() {
+													action = rfd::FileDialog::new().pick_folder().map(|x| InstanceAction::Move(id, x));
+													ui.close_menu();
+												}
+											});
+											if ui.button("Export...").clicked() {
+												action = rfd::FileDialog::new()
+													.add_filter("HERO instance", &[INSTANCE_ARCHIVE_EXTENSION])
+													.set_file_name(format!("{}.{}", instance.display_name(), INSTANCE_ARCHIVE_EXTENSION))
+													.save_file()
+													.map(|x| InstanceAction::Export(id, x));
+												ui.close_menu();
+											}
+											if ui.button("Move to trash").clicked() {
+												action = Some(InstanceAction::Delete(id));
+												ui.close_menu();
+											}
+										});
+									}
+								});
+
+								let broken: Vec<(PathBuf, String)> = state.instances().unwrap().broken.iter()
+									.map(|x| (x.path.clone(), x.error.to_string()))
+									.collect();
+								if !broken.is_empty() {
+									ui.add_space(24.);
+									ui.label(
+										RichText::new("Broken Instances")
+											.size(16.)
+											.color(Color32::WHITE)
+											.family(FontFamily::Name("inter-600".into()))
+									);
+									ui.add_space(8.);
+									for (path, error) in broken {
+										ui.label(RichText::new(path.to_string_lossy()).family(FontFamily::Name("inter-500".into())));
+										ui.label(RichText::new(error).color(Color32::from_rgb(255, 128, 128)).family(FontFamily::Name("inter-400".into())));
+										ui.horizontal(|ui| {
+											if ui.button("Repair").clicked() {
+												action = Some(InstanceAction::Repair(path.clone()));
+											}
+											if ui.button("Reveal folder").clicked() {
+												reveal(&path);
+											}
+										});
+									}
+								}
+
+								let trashed = state.current_game_id()
+									.map(|game_id| trashed_instances(&game_id).unwrap_or_default())
+									.unwrap_or_default();
+								if !trashed.is_empty() {
+									ui.add_space(24.);
+									ui.label(
+										RichText::new("Trash")
+											.size(16.)
+											.color(Color32::WHITE)
+											.family(FontFamily::Name("inter-600".into()))
+									);
+									ui.add_space(8.);
+									for path in trashed {
+										ui.horizontal(|ui| {
+											ui.label(RichText::new(path.file_name().unwrap_or_default().to_string_lossy()).family(FontFamily::Name("inter-500".into())));
+											if ui.button("Restore").clicked() {
+												action = Some(InstanceAction::Restore(path.clone()));
+											}
+										});
+									}
+								}
+
+								match action {
+									Some(InstanceAction::Rename(id, name)) => self.renaming = Some((id, name)),
+									Some(InstanceAction::Repair(path)) => {
+										std::thread::spawn(move || {
+											if let Err(err) = repair_instance(&path) {
+												println!("failed to repair instance {:?}! {}", path, err);
+											}
+										});
+									},
+									Some(InstanceAction::Duplicate(id)) => {
+										std::thread::spawn(move || {
+											if let Err(err) = duplicate_instance(&id) {
+												println!("failed to duplicate instance {}! {}", id, err);
+											}
+										});
+									},
+									Some(InstanceAction::Move(id, directory)) => {
+										if !state.instance_directories().contains(&directory) {
+											let mut settings = state.settings();
+											settings.instance_directories.push(directory.clone());
+											state.set_settings(settings).unwrap();
+										}
+										std::thread::spawn(move || {
+											if let Err(err) = move_instance(&id, &directory) {
+												println!("failed to move instance {}! {}", id, err);
+											}
+										});
+									},
+									Some(InstanceAction::Export(id, path)) => {
+										std::thread::spawn(move || {
+											if let Err(err) = export_instance(&id, &path) {
+												println!("failed to export instance {}! {}", id, err);
+											}
+										});
+									},
+									Some(InstanceAction::Delete(id)) => {
+										std::thread::spawn(move || {
+											match delete_instance(&id) {
+												Ok(path) => println!("moved instance {} to {:?}", id, path),
+												Err(err) => println!("failed to delete instance {}! {}", id, err)
+											}
+										});
+									},
+									Some(InstanceAction::Restore(path)) => {
+										std::thread::spawn(move || {
+											if let Err(err) = restore_instance(&path) {
+												println!("failed to restore instance {:?}! {}", path, err);
+											}
+										});
+									},
+									None => {}
+								}
+
+								let mut close = false;
+								if let Some((id, name)) = &mut self.renaming {
+									egui::Window::new("Rename instance")
+										.collapsible(false)
+										.resizable(false)
+										.anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+										.show(ctx, |ui| {
+											ui.text_edit_singleline(name);
+											ui.horizontal(|ui| {
+												if ui.add_enabled(!name.trim().is_empty(), egui::Button::new("Rename")).clicked() {
+													if let Err(err) = state.instances_mut().unwrap().rename(id, name.clone()) {
+														println!("failed to rename instance {}! {}", id, err);
+													}
+													close = true;
+												}
+												if ui.button("Cancel").clicked() {
+													close = true;
+												}
+											});
+										});
+								}
+								if close {
+									self.renaming = None;
+								}
+
+								let mut close = false;
+								if let (Some(wizard), Some(game)) = (&mut self.new_instance, state.get_current_game()) {
+									egui::Window::new("New instance")
+										.collapsible(false)
+										.resizable(false)
+										.anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+										.show(ctx, |ui| {
+											ui.label("Name");
+											ui.text_edit_singleline(&mut wizard.name);
+											ui.label("Icon");
+											ui.add(PathSelect::new(PathSelectKind::File, &mut wizard.icon));
+
+											let options = game.creation_options();
+											for option in &options {
+												let value = wizard.options.entry(option.key.clone())
+													.or_insert_with(|| option.kind.default_value().map(|x| x.to_string()).unwrap_or_default());
+												ui.label(state.t(option.name.clone()));
+												creation_option_ui(ui, &option.kind, value);
+											}
+
+											if let Some(error) = &wizard.error {
+												ui.colored_label(Color32::from_rgb(255, 128, 128), error);
+											}
+
+											let result = wizard.creating.as_ref().and_then(|x| x.ready().cloned());
+											match result {
+												Some(Ok(id)) => {
+													*self.page.write() = AppPage::Instance(id);
+													close = true;
+												},
+												Some(Err(error)) => {
+													wizard.error = Some(error);
+													wizard.creating = None;
+												},
+												None => {}
+											}
+
+											ui.horizontal(|ui| {
+												if wizard.creating.is_some() {
+													ui.add(Spinner::new());
+												} else if ui.add_enabled(!wizard.name.trim().is_empty(), egui::Button::new("Create")).clicked() {
+													let mut new = NewInstance::new(game.id(), wizard.name.clone());
+													if !wizard.icon.is_empty() {
+														new = new.icon(wizard.icon.clone());
+													}
+													for option in options {
+														if let Some(value) = wizard.options.get(&option.key) {
+															new = new.option(option.key, value.clone());
+														}
+													}
+													wizard.error = None;
+													wizard.creating = Some(Promise::spawn_thread("create_instance", move || {
+														create_instance(new).map_err(|x| x.to_string())
+													}));
+												}
+												if ui.button("Cancel").clicked() {
+													close = true;
+												}
+											});
+										});
+								}
+								if close {
+									self.new_instance = None;
+								}
+							},
+							AppPage::Settings => {
+								ui.add_space(48.);
+								ui.label(
+									RichText::new("Settings")
+										.size(32.)
+										.color(Color32::WHITE)
+										.family(FontFamily::Name("inter-700".into()))
+								);
+								ui.add_space(24.);
+								ui.label(
+									RichText::new("Downloads")
+										.size(16.)
+										.color(Color32::WHITE)
+										.family(FontFamily::Name("inter-600".into()))
+								);
+								ui.add_space(8.);
+
+								let mut settings = state.settings();
+								let mut speed_limit = settings.download_speed_limit / 1024;
+								let mut changed = false;
+								ui.horizontal(|ui| {
+									ui.label(RichText::new("Simultaneous downloads").family(FontFamily::Name("inter-400".into())));
+									changed |= ui.add(egui::Slider::new(&mut settings.max_concurrent_downloads, 1..=16)).changed();
+								});
+								ui.horizontal(|ui| {
+									ui.label(RichText::new("Speed limit (0 for unlimited)").family(FontFamily::Name("inter-400".into())));
+									if ui.add(egui::DragValue::new(&mut speed_limit).clamp_range(0..=1_000_000).suffix(" KB/s")).changed() {
+										settings.download_speed_limit = speed_limit * 1024;
+										changed = true;
+									}
+								});
+								if changed {
+									state.set_settings(settings).unwrap();
+								}
+
+								let extensions = state.extensions().unwrap();
+								let pages: Vec<(String, Vec<Element>)> = state.extension_settings().unwrap().iter()
+									.filter(|(_, x)| !x.schema.is_empty())
+									.map(|(id, x)| (extensions.manifest(id).map_or(id.clone(), |x| x.name.clone()), x.elements(&state, id)))
+									.collect();
+								drop(extensions);
+
+								for (name, elements) in pages {
+									ui.add_space(24.);
+									ui.label(
+										RichText::new(name)
+											.size(16.)
+											.color(Color32::WHITE)
+											.family(FontFamily::Name("inter-600".into()))
+									);
+									ui.add_space(8.);
+									for element in elements {
+										if let Some((value, new)) = element_ui(&mut ui, element, None) {
+											value.write(new, None);
+										}
+									}
+								}
+							},
+							AppPage::Extensions => {
+								ui.add_space(48.);
+								ui.label(
+									RichText::new("Extensions")
+										.size(32.)
+										.color(Color32::WHITE)
+										.family(FontFamily::Name("inter-700".into()))
+								);
+								ui.add_space(8.);
+
+								let mut action: Option<ExtensionAction> = None;
+								if ui.button("Reload").clicked() {
+									action = Some(ExtensionAction::Reload);
+								}
+								ui.add_space(16.);
+
+								let extensions = state.extensions().unwrap();
+								let disabled = extensions.disabled.iter().map(|x| (&x.manifest, true));
+								for (manifest, is_disabled) in extensions.manifests().into_iter().map(|x| (x, false)).chain(disabled) {
+									ui.label(
+										RichText::new(format!("{} {}", manifest.name, manifest.version))
+											.size(16.)
+											.color(if is_disabled { Color32::from_white_alpha(64) } else { Color32::WHITE })
+											.family(FontFamily::Name("inter-600".into()))
+									);
+									if !manifest.authors.is_empty() {
+										ui.label(RichText::new(format!("by {}", manifest.authors.join(", "))).family(FontFamily::Name("inter-400".into())));
+									}
+									if !manifest.dependencies.is_empty() {
+										ui.label(RichText::new(format!("requires {}", manifest.dependencies.join(", "))).family(FontFamily::Name("inter-400".into())));
+									}
+
+									let permissions = extensions.permissions(&manifest.id);
+									let granted = permissions.as_ref().map(|x| x.granted()).unwrap_or_default();
+									let ungranted = permissions.as_ref().map(|x| x.ungranted()).unwrap_or_default();
+									for capability in &manifest.capabilities {
+										let color = if granted.contains(capability) { Color32::from_white_alpha(128) } else { Color32::from_rgb(255, 192, 128) };
+										ui.label(RichText::new(format!("• {}", capability)).color(color).family(FontFamily::Name("inter-400".into())));
+									}
+									ui.horizontal(|ui| {
+										if !ungranted.is_empty() && ui.button("Grant permissions").clicked() {
+											action = Some(ExtensionAction::Grant(manifest.id.clone(), ungranted.clone()));
+										}
+										if !granted.is_empty() && ui.button("Revoke permissions").clicked() {
+											action = Some(ExtensionAction::Revoke(manifest.id.clone()));
+										}
+										if is_disabled {
+											if ui.button("Enable").clicked() {
+												action = Some(ExtensionAction::Enable(manifest.id.clone()));
+											}
+										} else if ui.button("Disable").clicked() {
+											action = Some(ExtensionAction::Disable(manifest.id.clone()));
+										}
+										if ui.button("Uninstall").clicked() {
+											action = Some(ExtensionAction::Uninstall(manifest.id.clone()));
+										}
+									});
+									ui.add_space(8.);
+								}
+
+								if !extensions.failed.is_empty() {
+									ui.add_space(16.);
+									ui.label(
+										RichText::new("Failed to load")
+											.size(16.)
+											.color(Color32::WHITE)
+											.family(FontFamily::Name("inter-600".into()))
+									);
+									ui.add_space(8.);
+									for failed in &extensions.failed {
+										ui.label(RichText::new(failed.file_name()).color(Color32::WHITE).family(FontFamily::Name("inter-500".into())));
+										ui.label(
+											RichText::new(failed.error.to_string())
+												.color(Color32::from_rgb(255, 128, 128))
+												.family(FontFamily::Name("inter-400".into()))
+										);
+										if matches!(failed.error, ExtensionError::Signature(_)) {
+											ui.label(RichText::new("Extensions run with full access to your computer, only trust ones you know are safe.").family(FontFamily::Name("inter-400".into())));
+											if ui.button("Trust anyway").clicked() {
+												action = Some(ExtensionAction::Trust(failed.path.clone()));
+											}
+										}
+										ui.add_space(8.);
+									}
+								}
+
+								ui.add_space(16.);
+								ui.label(
+									RichText::new("Trusted publishers")
+										.size(16.)
+										.color(Color32::WHITE)
+										.family(FontFamily::Name("inter-600".into()))
+								);
+								ui.add_space(8.);
+								ui.label(RichText::new("Extensions signed by these publishers load without asking, only add keys you got from the publisher themselves.").family(FontFamily::Name("inter-400".into())));
+								for key in &extensions.trust.keys {
+									ui.horizontal(|ui| {
+										ui.label(RichText::new(key).color(Color32::WHITE).family(FontFamily::Name("inter-500".into())));
+										if ui.button("Remove").clicked() {
+											action = Some(ExtensionAction::UntrustKey(key.clone()));
+										}
+									});
+								}
+								ui.horizontal(|ui| {
+									ui.add(egui::TextEdit::singleline(&mut self.publisher_key).hint_text("Public key"));
+									if ui.button("Trust").clicked() && !self.publisher_key.trim().is_empty() {
+										action = Some(ExtensionAction::TrustKey(std::mem::take(&mut self.publisher_key).trim().into()));
 									}
 								});
+								drop(extensions);
+
+								if let Some(action) = action {
+									let result = match action {
+										ExtensionAction::Enable(id) => enable_extension(&state, &id).map_err(|x| x.to_string()),
+										ExtensionAction::Disable(id) => disable_extension(&state, &id).map_err(|x| x.to_string()),
+										ExtensionAction::Uninstall(id) => uninstall_extension(&state, &id).map_err(|x| x.to_string()),
+										ExtensionAction::Trust(path) => trust_library(&state, &path).map_err(|x| x.to_string()),
+										ExtensionAction::TrustKey(key) => trust_key(&state, &key).map_err(|x| x.to_string()),
+										ExtensionAction::UntrustKey(key) => state.extensions_mut().unwrap().untrust_key(&key).map_err(|x| x.to_string()),
+										ExtensionAction::Grant(id, capabilities) => state.extensions_mut().unwrap().grant(&id, &capabilities).map_err(|x| x.to_string()),
+										ExtensionAction::Revoke(id) => state.extensions_mut().unwrap().revoke(&id).map_err(|x| x.to_string()),
+										ExtensionAction::Reload => reload_extensions(&state).map_err(|x| x.to_string())
+									};
+									if let Err(err) = result {
+										println!("extension action failed! {}", err);
+									}
+									if state.current_game_id().is_none() {
+										*self.page.write() = AppPage::SelectGame(false);
+									}
+								}
 							},
 							AppPage::Instance(id) => {
-								let instances = state.instances.read();
-								let instance = instances.items.get(&id).unwrap();
+								let instances = state.instances().unwrap();
+								let Some(instance) = instances.items.get(&id) else {
+									// it was deleted, moved or failed to load again since the page was opened
+									*self.page.write() = AppPage::Library;
+									ctx.request_repaint();
+									return;
+								};
 								let mut rect = content_rect.clone();
 								rect.max.y = rect.min.y + 192.;
 
@@ -514,7 +1179,8 @@ impl eframe::App for HEROApp {
 									});
 								}
 
-								let pages = state.ui_containers.read();
+								let pages = state.ui_containers();
+								let mut writes = vec![];
 
 								let mut rect = content_rect.shrink2(vec2(32., 0.));
 								rect.min.y += 184.;
@@ -536,24 +1202,15 @@ impl eframe::App for HEROApp {
 									ui.spacing_mut().item_spacing = Vec2::Y * 16.;
 
 									for element in (page.render)() {
-										match element {
-											Element::Text(text) => {
-												ui.label(
-													RichText::new(text.read(Some(&instance)).unwrap())
-														.size(16.)
-														.color(Color32::WHITE)
-														.family(FontFamily::Name("inter-400".into()))	
-												);
-											},
-											Element::PathSelect { kind, value } => {
-												let mut path = value.read(Some(&instance)).unwrap_or("path not set".into());
-												if ui.add(PathSelect::new(kind, &mut path)).changed() {
-													value.write(path, Some(instance.id()));
-												}
-											}
-										};
+										writes.extend(element_ui(&mut ui, element, Some(&instance)));
 									}
 								}
+								drop(pages);
+								drop(instances);
+
+								for (value, new) in writes {
+									value.write(new, Some(id));
+								}
 							},
 							_ => {
 								ui.label(RichText::new("this page is completely empty... how strange!").family(FontFamily::Name("inter-400".into())));
@@ -593,6 +1250,111 @@ impl eframe::App for HEROApp {
 	}
 }
 
+/// Shows an element, returning the value the user changed along with what they changed it to.
+/// Write it with [`Value::write`] once the instance isn't borrowed from the state anymore.
+fn element_ui(ui: &mut egui::Ui, element: Element, instance: Option<&Instance>) -> Option<(Value<String>, String)> {
+	let read = |value: &Value<String>| value.read(instance);
+	let mut changed = None;
+	let mut write = |value: Value<String>, new: String| changed = Some((value, new));
+	match element {
+		Element::Text(text) => {
+			ui.label(
+				RichText::new(read(&text).unwrap_or_default())
+					.size(16.)
+					.color(Color32::WHITE)
+					.family(FontFamily::Name("inter-400".into()))	
+			);
+		},
+		Element::PathSelect { kind, value } => {
+			let mut path = read(&value).unwrap_or("path not set".into());
+			if ui.add(PathSelect::new(kind, &mut path)).changed() {
+				write(value, path);
+			}
+		},
+		Element::TextInput { value } => {
+			let mut text = read(&value).unwrap_or_default();
+			if ui.text_edit_singleline(&mut text).changed() {
+				write(value, text);
+			}
+		},
+		Element::Checkbox { value } => {
+			let mut checked = read(&value).is_some_and(|x| x == "true");
+			if ui.checkbox(&mut checked, "").changed() {
+				write(value, checked.to_string());
+			}
+		},
+		Element::Dropdown { options, value } => {
+			let current = read(&value).unwrap_or_default();
+			let mut selected = current.clone();
+			egui::ComboBox::from_id_source(ui.next_auto_id())
+				.selected_text(&current)
+				.show_ui(ui, |ui| {
+					for option in options {
+						ui.selectable_value(&mut selected, option.clone(), option);
+					}
+				});
+			if selected != current {
+				write(value, selected);
+			}
+		},
+		Element::NumberInput { min, max, value } => {
+			let mut number: f64 = read(&value).and_then(|x| x.parse().ok()).unwrap_or_default();
+			let range = min.unwrap_or(f64::MIN)..=max.unwrap_or(f64::MAX);
+			if ui.add(egui::DragValue::new(&mut number).clamp_range(range)).changed() {
+				write(value, number.to_string());
+			}
+		}
+	}
+	changed
+}
+
+/// Opens a directory in the platform's file manager.
+fn reveal(path: &std::path::Path) {
+	let program = if cfg!(target_os = "windows") {
+		"explorer"
+	} else if cfg!(target_os = "macos") {
+		"open"
+	} else {
+		"xdg-open"
+	};
+	if let Err(err) = std::process::Command::new(program).arg(path).spawn() {
+		println!("failed to reveal {:?}! {}", path, err);
+	}
+}
+
+fn creation_option_ui(ui: &mut egui::Ui, kind: &SettingKind, value: &mut String) {
+	match kind {
+		SettingKind::String { .. } => {
+			ui.text_edit_singleline(value);
+		},
+		SettingKind::Path { directory, .. } => {
+			ui.add(PathSelect::new(if *directory { PathSelectKind::Directory } else { PathSelectKind::File }, value));
+		},
+		SettingKind::Boolean { .. } => {
+			let mut checked = value == "true";
+			if ui.checkbox(&mut checked, "").changed() {
+				*value = checked.to_string();
+			}
+		},
+		SettingKind::Enum { options, .. } => {
+			egui::ComboBox::from_id_source(ui.next_auto_id())
+				.selected_text(value.as_str())
+				.show_ui(ui, |ui| {
+					for option in options {
+						ui.selectable_value(value, option.clone(), option);
+					}
+				});
+		},
+		SettingKind::Number { min, max, .. } => {
+			let mut number: f64 = value.parse().unwrap_or_default();
+			let range = min.unwrap_or(f64::MIN)..=max.unwrap_or(f64::MAX);
+			if ui.add(egui::DragValue::new(&mut number).clamp_range(range)).changed() {
+				*value = number.to_string();
+			}
+		}
+	}
+}
+
 fn title_bar_ui(
 	ctx: &egui::Context,
     ui: &mut egui::Ui,
@@ -0,0 +1,86 @@
+use std::time::Duration;
+use eframe::{
+	egui::{ Ui, Response, Sense, Widget },
+	emath::Align2,
+	epaint::{ Vec2, Rect, Rounding, Color32, FontId, FontFamily }
+};
+use hero_core::state::{ LoadingBar, LoadingBarType };
+
+pub struct LoadingBarItem<'a> {
+	title: String,
+	bar: &'a LoadingBar
+}
+
+impl<'a> LoadingBarItem<'a> {
+	pub fn new(title: impl ToString, bar: &'a LoadingBar) -> Self {
+		Self {
+			title: title.to_string(),
+			bar
+		}
+	}
+
+	fn details(&self) -> String {
+		let is_bytes = matches!(self.bar.bar_type, LoadingBarType::Download { .. });
+		let amount = |value: f64| if is_bytes { format_bytes(value) } else { format!("{}", value.floor()) };
+
+		let mut details = if self.bar.total > 0. {
+			format!("{} of {}", amount(self.bar.current), amount(self.bar.total))
+		} else { amount(self.bar.current) };
+		if is_bytes && self.bar.speed > 0. {
+			details += &format!(" • {}/s", format_bytes(self.bar.speed));
+		}
+		if let Some(eta) = self.bar.eta() {
+			details += &format!(" • {} left", format_duration(eta));
+		}
+		details
+	}
+}
+
+impl<'a> Widget for LoadingBarItem<'a> {
+	fn ui(self, ui: &mut Ui) -> Response {
+		let (rect, response) = ui.allocate_at_least(Vec2::new(ui.available_width(), 48.), Sense::hover());
+		if ui.is_rect_visible(rect) {
+			let painter = ui.painter();
+			painter.text(rect.left_top(), Align2::LEFT_TOP, &self.title, FontId::new(13., FontFamily::Name("inter-500".into())), Color32::WHITE);
+
+			let mut bar_rect = rect;
+			bar_rect.min.y += 20.;
+			bar_rect.max.y = bar_rect.min.y + 4.;
+			painter.rect_filled(bar_rect, Rounding::same(2.), Color32::from_white_alpha(4));
+
+			let fraction = self.bar.fraction().unwrap_or(0.) as f32;
+			if fraction > 0. {
+				let mut fill_rect = bar_rect;
+				fill_rect.max.x = fill_rect.min.x + bar_rect.width() * fraction;
+				painter.rect_filled(fill_rect, Rounding::same(2.), Color32::WHITE);
+			}
+
+			let details_rect = Rect::from_min_max(bar_rect.left_bottom() + Vec2::DOWN * 6., rect.max);
+			painter.text(details_rect.left_top(), Align2::LEFT_TOP, self.details(), FontId::new(11., FontFamily::Name("inter-400".into())), Color32::from_white_alpha(32));
+		}
+
+		response
+	}
+}
+
+fn format_bytes(bytes: f64) -> String {
+	let mut value = bytes;
+	for unit in ["B", "KB", "MB", "GB"] {
+		if value < 1024. {
+			return format!("{:.1} {}", value, unit);
+		}
+		value /= 1024.;
+	}
+	format!("{:.1} TB", value)
+}
+
+fn format_duration(duration: Duration) -> String {
+	let seconds = duration.as_secs();
+	if seconds >= 3600 {
+		format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+	} else if seconds >= 60 {
+		format!("{}m {}s", seconds / 60, seconds % 60)
+	} else {
+		format!("{}s", seconds)
+	}
+}
\ No newline at end of file
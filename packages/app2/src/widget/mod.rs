@@ -1,5 +1,7 @@
+mod loading_bar;
 mod path_select;
 mod navigation_item;
 
+pub use loading_bar::*;
 pub use path_select::*;
 pub use navigation_item::*;
\ No newline at end of file
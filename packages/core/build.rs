@@ -0,0 +1,74 @@
+use std::env;
+use std::path::{ Path, PathBuf };
+use std::process::Command;
+
+fn main() {
+	// extensions built by a different compiler can't safely share Rust types with us,
+	// so the version is baked in and compared when an extension is loaded.
+	let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+	let version = Command::new(rustc)
+		.arg("--version")
+		.output()
+		.map(|x| String::from_utf8_lossy(&x.stdout).trim().to_string())
+		.unwrap_or_default();
+	println!("cargo:rustc-env=HERO_RUSTC_VERSION={}", version);
+	println!("cargo:rustc-env=HERO_TARGET={}", env::var("TARGET").unwrap_or_default());
+
+	// features change the shape of shared types, and so can the source itself or flags like debug assertions,
+	// so all of them are hashed together with every source file and compared as well
+	let mut features: Vec<String> = env::vars()
+		.filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(|x| x.to_ascii_lowercase()))
+		.collect();
+	features.sort();
+	let features = features.join(",");
+	println!("cargo:rustc-env=HERO_FEATURES={}", features);
+
+	let mut fingerprint = Fingerprint::default();
+	fingerprint.write(env::var("CARGO_PKG_VERSION").unwrap_or_default().as_bytes());
+	fingerprint.write(features.as_bytes());
+	fingerprint.write(env::var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default().as_bytes());
+	fingerprint.write(&[env::var_os("CARGO_CFG_DEBUG_ASSERTIONS").is_some() as u8]);
+	fingerprint.write(&std::fs::read("Cargo.toml").unwrap_or_default());
+	for path in source_files(Path::new("src")) {
+		fingerprint.write(path.to_string_lossy().replace('\\', "/").as_bytes());
+		fingerprint.write(&std::fs::read(&path).unwrap_or_default());
+	}
+	println!("cargo:rustc-env=HERO_BUILD_FINGERPRINT={:016x}", fingerprint.0);
+
+	println!("cargo:rerun-if-changed=build.rs");
+	println!("cargo:rerun-if-changed=Cargo.toml");
+	println!("cargo:rerun-if-changed=src");
+}
+
+/// Every file below a directory, in the same order on every platform.
+fn source_files(directory: &Path) -> Vec<PathBuf> {
+	let mut files = vec![];
+	if let Ok(entries) = std::fs::read_dir(directory) {
+		for path in entries.filter_map(|x| x.ok()).map(|x| x.path()) {
+			if path.is_dir() {
+				files.extend(source_files(&path));
+			} else {
+				files.push(path);
+			}
+		}
+	}
+	files.sort();
+	files
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hasher is guaranteed to give the same result in every build.
+struct Fingerprint(u64);
+
+impl Default for Fingerprint {
+	fn default() -> Self {
+		Self(0xcbf29ce484222325)
+	}
+}
+
+impl Fingerprint {
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes.iter().chain(&[0xff]) {
+			self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+		}
+	}
+}
\ No newline at end of file
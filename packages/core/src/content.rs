@@ -1,14 +1,20 @@
 use std::any::Any;
+use serde::{ Serialize, Deserialize };
 use crate::instance::Instance;
 
-#[derive(Clone, Debug)]
+#[cfg(feature = "core")]
+use uuid::Uuid;
+#[cfg(feature = "core")]
+use crate::state::{ State, LoadingBarType };
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ContentFile {
 	pub name: String,
 	pub icon: Option<Vec<u8>>,
 	pub version: Option<String>
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ContentProviderItem {
 	/// Unique identifier.
 	pub id: String,
@@ -29,7 +35,7 @@ pub struct ContentProviderItem {
 	pub can_be_installed: bool
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ContentProviderItemAuthor {
 	pub id: String,
 	pub name: String,
@@ -49,7 +55,7 @@ pub trait ContentProvider: Any + Send + Sync {
 	/// The unique identifier for this content provider.
 	fn name(&self) -> &'static str;
 	fn search(&mut self, query: String, sort_type: ContentProviderSearchSortType, descending: bool) -> Vec<ContentProviderItem>;
-	fn install(&mut self, instance: &Instance, item_id: &String);
+	fn install(&mut self, instance: &Instance, item_id: &str);
 
 	/// Defines if and how long search queries should be cached for.
 	fn cache_duration(&self) -> Option<std::time::Duration> {
@@ -67,4 +73,21 @@ pub trait ContentPage: Any + Send + Sync {
 	fn items(&self, _instance: &Instance) -> Vec<ContentFile> {
 		Vec::new()
 	}
+}
+
+/// Installs an item into an instance with a loading bar, then tells extensions about it.
+/// Take providers from [`crate::extension::ExtensionHooks::content_providers`], so they install with their extension's permissions.
+#[cfg(feature = "core")]
+pub fn install_content(provider: &mut dyn ContentProvider, instance_id: Uuid, item_id: &str) {
+	let state = State::get();
+	let Some(instance) = state.instances.read().items.get(&instance_id).cloned() else {
+		return;
+	};
+
+	let bar = state.init_loading(LoadingBarType::InstallContent { instance_id, item_id: item_id.into() }, 1.);
+	provider.install(&instance, item_id);
+	state.finish_loading(&bar);
+
+	let hooks = state.extensions.read().hooks();
+	hooks.dispatch("on_content_installed", |x| x.on_content_installed(&instance, item_id));
 }
\ No newline at end of file
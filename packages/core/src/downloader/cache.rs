@@ -0,0 +1,180 @@
+use std::fs;
+use std::io::Result;
+use std::path::{ Path, PathBuf };
+use std::time::SystemTime;
+
+use super::Checksum;
+
+/// How large the download cache may grow before old entries are removed, in bytes.
+pub const DEFAULT_CACHE_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Downloaded files stored by their checksum, so the same file
+/// can be handed to multiple instances without fetching it again.
+#[derive(Clone, Debug)]
+pub struct DownloadCache {
+	path: PathBuf,
+	max_size: u64
+}
+
+impl DownloadCache {
+	pub fn new(path: impl Into<PathBuf>, max_size: u64) -> Self {
+		Self {
+			path: path.into(),
+			max_size
+		}
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	pub fn max_size(&self) -> u64 {
+		self.max_size
+	}
+
+	/// Where the file with the given checksum is, or would be, stored.
+	/// Returns nothing for checksums that aren't valid, as they could lead outside of the cache.
+	pub fn entry_path(&self, checksum: &Checksum) -> Option<PathBuf> {
+		if !checksum.is_valid() {
+			return None;
+		}
+
+		let hex = checksum.hex().to_ascii_lowercase();
+		let algorithm = match checksum {
+			Checksum::Sha1(_) => "sha1",
+			Checksum::Sha256(_) => "sha256"
+		};
+		Some(self.path.join(algorithm).join(&hex[..2]).join(hex))
+	}
+
+	/// Returns the path of a cached file, marking it as recently used.
+	pub fn get(&self, checksum: &Checksum) -> Option<PathBuf> {
+		let path = self.entry_path(checksum)?;
+		if path.is_file() {
+			let _ = filetime::set_file_mtime(&path, filetime::FileTime::now());
+			return Some(path);
+		}
+		None
+	}
+
+	/// Copies a verified file into the cache, then removes old entries if it has grown too large.
+	pub fn insert(&self, checksum: &Checksum, file: &Path) -> Result<()> {
+		let path = self.entry_path(checksum)
+			.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} is not a valid checksum", checksum)))?;
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		let temp_path = path.with_extension("tmp");
+		fs::copy(file, &temp_path)?;
+		fs::rename(&temp_path, &path)?;
+
+		self.collect_garbage()?;
+		Ok(())
+	}
+
+	/// Total size of every cached file, in bytes.
+	pub fn size(&self) -> Result<u64> {
+		Ok(self.entries()?.iter().map(|x| x.1).sum())
+	}
+
+	/// Removes the least recently used files until the cache fits within its maximum size,
+	/// returning how many bytes were freed.
+	pub fn collect_garbage(&self) -> Result<u64> {
+		let mut entries = self.entries()?;
+		let mut size: u64 = entries.iter().map(|x| x.1).sum();
+		let mut freed = 0;
+
+		entries.sort_by_key(|x| x.2);
+		for (path, length, _) in entries {
+			if size <= self.max_size {
+				break;
+			}
+			fs::remove_file(path)?;
+			size -= length;
+			freed += length;
+		}
+
+		Ok(freed)
+	}
+
+	/// Removes every cached file.
+	pub fn clear(&self) -> Result<()> {
+		if self.path.exists() {
+			fs::remove_dir_all(&self.path)?;
+		}
+		Ok(())
+	}
+
+	fn entries(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>> {
+		let mut entries = vec![];
+		if !self.path.exists() {
+			return Ok(entries);
+		}
+
+		let mut directories = vec![self.path.clone()];
+		while let Some(directory) = directories.pop() {
+			for entry in fs::read_dir(directory)?.filter_map(|x| x.ok()) {
+				let metadata = entry.metadata()?;
+				if metadata.is_dir() {
+					directories.push(entry.path());
+				} else {
+					entries.push((entry.path(), metadata.len(), metadata.modified()?));
+				}
+			}
+		}
+
+		Ok(entries)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing::temp_dir;
+
+	const SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+	#[test]
+	fn stores_entries_by_checksum() {
+		let dir = temp_dir("cache");
+		let cache = DownloadCache::new(dir.join("cache"), DEFAULT_CACHE_SIZE);
+		let checksum = Checksum::Sha256(SHA256.to_ascii_uppercase());
+		fs::write(dir.join("file"), b"").unwrap();
+
+		cache.insert(&checksum, &dir.join("file")).unwrap();
+		let path = cache.get(&checksum).unwrap();
+		assert_eq!(path, dir.join("cache/sha256/e3").join(SHA256));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_checksums_leading_outside_of_the_cache() {
+		let dir = temp_dir("cache");
+		let cache = DownloadCache::new(dir.join("cache"), DEFAULT_CACHE_SIZE);
+		let checksum = Checksum::Sha256("../../secret.txt".into());
+		fs::write(dir.join("secret.txt"), b"secret").unwrap();
+
+		assert_eq!(cache.entry_path(&checksum), None);
+		assert_eq!(cache.get(&checksum), None);
+		assert!(cache.insert(&checksum, &dir.join("secret.txt")).is_err());
+		assert!(!dir.join("cache").exists());
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn removes_least_recently_used_entries() {
+		let dir = temp_dir("cache");
+		let cache = DownloadCache::new(dir.join("cache"), 8);
+		let old = Checksum::Sha1("0".repeat(40));
+		let new = Checksum::Sha1("1".repeat(40));
+		fs::write(dir.join("file"), b"12345").unwrap();
+
+		cache.insert(&old, &dir.join("file")).unwrap();
+		filetime::set_file_mtime(cache.entry_path(&old).unwrap(), filetime::FileTime::from_unix_time(0, 0)).unwrap();
+		cache.insert(&new, &dir.join("file")).unwrap();
+		assert_eq!(cache.get(&old), None);
+		assert!(cache.get(&new).is_some());
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
\ No newline at end of file
@@ -0,0 +1,110 @@
+use std::fmt;
+use std::fmt::Write;
+use sha1::Digest;
+use serde::{ Serialize, Deserialize };
+
+/// An expected hash of a downloaded file, written as hexadecimal.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Checksum {
+	Sha1(String),
+	Sha256(String)
+}
+
+impl Checksum {
+	pub fn hex(&self) -> &str {
+		match self {
+			Self::Sha1(hex) | Self::Sha256(hex) => hex
+		}
+	}
+
+	/// Whether or not this is as long as the algorithm's digests and made of nothing but hexadecimal digits.
+	/// Checksums name files in the [`super::DownloadCache`], so anything else is refused.
+	pub fn is_valid(&self) -> bool {
+		let length = match self {
+			Self::Sha1(_) => 40,
+			Self::Sha256(_) => 64
+		};
+		self.hex().len() == length && self.hex().bytes().all(|x| x.is_ascii_hexdigit())
+	}
+
+	/// Whether or not the given hexadecimal digest is the one we expect.
+	pub fn matches(&self, digest: &str) -> bool {
+		self.hex().eq_ignore_ascii_case(digest)
+	}
+
+	pub(crate) fn hasher(&self) -> ChecksumHasher {
+		match self {
+			Self::Sha1(_) => ChecksumHasher::Sha1(sha1::Sha1::new()),
+			Self::Sha256(_) => ChecksumHasher::Sha256(sha2::Sha256::new())
+		}
+	}
+}
+
+impl fmt::Display for Checksum {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Sha1(hex) => write!(f, "sha1:{}", hex),
+			Self::Sha256(hex) => write!(f, "sha256:{}", hex)
+		}
+	}
+}
+
+pub(crate) enum ChecksumHasher {
+	Sha1(sha1::Sha1),
+	Sha256(sha2::Sha256)
+}
+
+impl ChecksumHasher {
+	pub fn update(&mut self, data: &[u8]) {
+		match self {
+			Self::Sha1(hasher) => hasher.update(data),
+			Self::Sha256(hasher) => hasher.update(data)
+		}
+	}
+
+	/// Consumes the hasher, returning the digest as lowercase hexadecimal.
+	pub fn finish(self) -> String {
+		let bytes = match self {
+			Self::Sha1(hasher) => hasher.finalize().to_vec(),
+			Self::Sha256(hasher) => hasher.finalize().to_vec()
+		};
+		bytes.iter().fold(String::new(), |mut hex, x| {
+			let _ = write!(hex, "{:02x}", x);
+			hex
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SHA1: &str = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+	const SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+	#[test]
+	fn accepts_digests_of_the_right_length() {
+		assert!(Checksum::Sha1(SHA1.into()).is_valid());
+		assert!(Checksum::Sha256(SHA256.into()).is_valid());
+		assert!(Checksum::Sha256(SHA256.to_ascii_uppercase()).is_valid());
+	}
+
+	#[test]
+	fn refuses_malformed_digests() {
+		assert!(!Checksum::Sha1(SHA256.into()).is_valid());
+		assert!(!Checksum::Sha256(SHA1.into()).is_valid());
+		assert!(!Checksum::Sha256(String::new()).is_valid());
+		assert!(!Checksum::Sha256("../secret.txt".into()).is_valid());
+		assert!(!Checksum::Sha256(format!("{}/..", &SHA256[..61])).is_valid());
+		assert!(!Checksum::Sha1(SHA1.replace('a', "g")).is_valid());
+	}
+
+	#[test]
+	fn hashes_match_case_insensitively() {
+		let mut hasher = Checksum::Sha256(SHA256.into()).hasher();
+		hasher.update(b"");
+		assert_eq!(hasher.finish(), SHA256);
+		assert!(Checksum::Sha256(SHA256.to_ascii_uppercase()).matches(SHA256));
+		assert!(!Checksum::Sha1(SHA1.into()).matches(SHA256));
+	}
+}
\ No newline at end of file
@@ -0,0 +1,427 @@
+use std::fmt;
+use std::sync::Arc;
+use std::fs::{ self, File };
+use std::io::{ self, Read, BufReader };
+use std::path::{ Path, PathBuf, Component };
+use serde::{ Serialize, Deserialize };
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+	Zip,
+	TarGz,
+	TarXz
+}
+
+impl ArchiveFormat {
+	/// Guesses the format of an archive from its file name.
+	pub fn from_path(path: &Path) -> Option<Self> {
+		let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+		if name.ends_with(".zip") || name.ends_with(".jar") {
+			Some(Self::Zip)
+		} else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+			Some(Self::TarGz)
+		} else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+			Some(Self::TarXz)
+		} else {
+			None
+		}
+	}
+}
+
+/// Describes how a downloaded archive should be extracted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Extraction {
+	pub destination: PathBuf,
+
+	/// Format of the archive, guessed from the file name if not set.
+	pub format: Option<ArchiveFormat>,
+
+	/// How many leading path components to remove from every entry,
+	/// entries that have no components left are skipped.
+	pub strip_components: usize,
+
+	/// Whether or not to delete the archive once it has been extracted.
+	pub remove_archive: bool
+}
+
+impl Extraction {
+	pub fn new(destination: impl Into<PathBuf>) -> Self {
+		Self {
+			destination: destination.into(),
+			format: None,
+			strip_components: 0,
+			remove_archive: false
+		}
+	}
+
+	pub fn format(mut self, format: ArchiveFormat) -> Self {
+		self.format = Some(format);
+		self
+	}
+
+	pub fn strip_components(mut self, count: usize) -> Self {
+		self.strip_components = count;
+		self
+	}
+
+	pub fn remove_archive(mut self) -> Self {
+		self.remove_archive = true;
+		self
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum ExtractError {
+	Io(Arc<io::Error>),
+
+	/// The archive contains an entry that would be written outside of the destination.
+	UnsafePath(PathBuf),
+	UnknownFormat(PathBuf),
+
+	/// Extraction was told to stop before reaching the end of the archive.
+	Cancelled
+}
+
+impl fmt::Display for ExtractError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(err) => write!(f, "io error: {}", err),
+			Self::UnsafePath(path) => write!(f, "archive entry {:?} leaves the destination", path),
+			Self::UnknownFormat(path) => write!(f, "{:?} is not a supported archive", path),
+			Self::Cancelled => write!(f, "extraction was cancelled")
+		}
+	}
+}
+
+impl std::error::Error for ExtractError {}
+
+impl From<io::Error> for ExtractError {
+	fn from(value: io::Error) -> Self {
+		Self::Io(Arc::new(value))
+	}
+}
+
+impl From<zip::result::ZipError> for ExtractError {
+	fn from(value: zip::result::ZipError) -> Self {
+		Self::Io(Arc::new(value.into()))
+	}
+}
+
+/// Extracts an archive, calling `progress` with how far along it is and the total amount.
+/// Zip archives report progress in entries, tar archives in compressed bytes.
+pub fn extract_archive(archive: &Path, extraction: &Extraction, progress: impl FnMut(u64, u64)) -> Result<(), ExtractError> {
+	extract_archive_cancellable(archive, extraction, progress, || false)
+}
+
+/// Same as [`extract_archive`], but checks `is_cancelled` before every entry and stops with [`ExtractError::Cancelled`] once it returns true.
+/// Entries already written are left in place, and the archive is kept.
+pub fn extract_archive_cancellable(archive: &Path, extraction: &Extraction, mut progress: impl FnMut(u64, u64), is_cancelled: impl Fn() -> bool) -> Result<(), ExtractError> {
+	let format = extraction.format
+		.or_else(|| ArchiveFormat::from_path(archive))
+		.ok_or_else(|| ExtractError::UnknownFormat(archive.to_path_buf()))?;
+	fs::create_dir_all(&extraction.destination)?;
+
+	match format {
+		ArchiveFormat::Zip => extract_zip(archive, extraction, &mut progress, &is_cancelled)?,
+		ArchiveFormat::TarGz | ArchiveFormat::TarXz => {
+			let file = File::open(archive)?;
+			let total = file.metadata()?.len();
+			let reader = ProgressReader {
+				inner: BufReader::new(file),
+				read: 0,
+				total,
+				progress: &mut progress
+			};
+			if format == ArchiveFormat::TarGz {
+				extract_tar(flate2::read::GzDecoder::new(reader), extraction, &is_cancelled)?;
+			} else {
+				extract_tar(xz2::read::XzDecoder::new(reader), extraction, &is_cancelled)?;
+			}
+			progress(total, total);
+		}
+	}
+
+	if extraction.remove_archive {
+		fs::remove_file(archive)?;
+	}
+	Ok(())
+}
+
+fn extract_zip(archive: &Path, extraction: &Extraction, progress: &mut impl FnMut(u64, u64), is_cancelled: &impl Fn() -> bool) -> Result<(), ExtractError> {
+	let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+	let root = fs::canonicalize(&extraction.destination)?;
+	let total = zip.len() as u64;
+	for index in 0..zip.len() {
+		if is_cancelled() {
+			return Err(ExtractError::Cancelled);
+		}
+		let mut file = zip.by_index(index)?;
+		let name = file.enclosed_name()
+			.map(Path::to_path_buf)
+			.ok_or_else(|| ExtractError::UnsafePath(PathBuf::from(file.name())))?;
+
+		if let Some(relative) = entry_path(&name, extraction.strip_components)? {
+			let path = root.join(relative);
+			if file.is_dir() {
+				create_dir_inside(&root, &path)?;
+			} else {
+				if let Some(parent) = path.parent() {
+					create_dir_inside(&root, parent)?;
+				}
+				io::copy(&mut file, &mut File::create(&path)?)?;
+
+				#[cfg(unix)]
+				if let Some(mode) = file.unix_mode() {
+					use std::os::unix::fs::PermissionsExt;
+					fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+				}
+			}
+		}
+		progress(index as u64 + 1, total);
+	}
+
+	Ok(())
+}
+
+fn extract_tar(reader: impl Read, extraction: &Extraction, is_cancelled: &impl Fn() -> bool) -> Result<(), ExtractError> {
+	let mut archive = tar::Archive::new(reader);
+	let root = fs::canonicalize(&extraction.destination)?;
+	for entry in archive.entries()? {
+		if is_cancelled() {
+			return Err(ExtractError::Cancelled);
+		}
+		let mut entry = entry?;
+		let name = entry.path()?.to_path_buf();
+		let Some(relative) = entry_path(&name, extraction.strip_components)? else {
+			continue;
+		};
+
+		let path = root.join(&relative);
+		if let Some(parent) = path.parent() {
+			create_dir_inside(&root, parent)?;
+		}
+
+		let kind = entry.header().entry_type();
+		if kind.is_symlink() || kind.is_hard_link() {
+			let target = entry.link_name()?
+				.map(|x| x.to_path_buf())
+				.ok_or_else(|| ExtractError::UnsafePath(name.clone()))?;
+			if kind.is_hard_link() {
+				// hard link targets are relative to the root of the archive, so they get stripped too
+				let target = entry_path(&target, extraction.strip_components)?
+					.ok_or_else(|| ExtractError::UnsafePath(name.clone()))?;
+				let target = fs::canonicalize(root.join(target))?;
+				if !target.starts_with(&root) {
+					return Err(ExtractError::UnsafePath(name));
+				}
+				let _ = fs::remove_file(&path);
+				fs::hard_link(target, &path)?;
+				continue;
+			}
+
+			let base = relative.parent().map(Path::to_path_buf).unwrap_or_default();
+			if normalise(&base.join(target)).is_none() {
+				return Err(ExtractError::UnsafePath(name));
+			}
+		}
+		entry.unpack(&path)?;
+	}
+
+	Ok(())
+}
+
+/// Creates a directory and its parents, refusing to if an existing parent is a symbolic link leading out of `root`.
+/// Earlier entries of an archive can be links that look harmless on their own but escape when chained together,
+/// so this goes by where the directory really is on disk rather than by its path.
+fn create_dir_inside(root: &Path, path: &Path) -> Result<(), ExtractError> {
+	let mut existing = path;
+	while fs::symlink_metadata(existing).is_err() {
+		existing = existing.parent().ok_or_else(|| ExtractError::UnsafePath(path.to_path_buf()))?;
+	}
+	if !fs::canonicalize(existing)?.starts_with(root) {
+		return Err(ExtractError::UnsafePath(path.to_path_buf()));
+	}
+	Ok(fs::create_dir_all(path)?)
+}
+
+/// Strips leading components from an entry's path, refusing anything that could escape the destination.
+fn entry_path(name: &Path, strip_components: usize) -> Result<Option<PathBuf>, ExtractError> {
+	let path = normalise(name).ok_or_else(|| ExtractError::UnsafePath(name.to_path_buf()))?;
+	let relative: PathBuf = path.components().skip(strip_components).collect();
+	Ok(if relative.as_os_str().is_empty() { None } else { Some(relative) })
+}
+
+/// Lexically resolves a relative path, returning nothing if it is absolute or climbs above its root.
+fn normalise(path: &Path) -> Option<PathBuf> {
+	let mut result = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::Normal(part) => result.push(part),
+			Component::CurDir => {},
+			Component::ParentDir => if !result.pop() {
+				return None;
+			},
+			Component::RootDir | Component::Prefix(_) => return None
+		}
+	}
+	Some(result)
+}
+
+struct ProgressReader<'a, R: Read, F: FnMut(u64, u64)> {
+	inner: R,
+	read: u64,
+	total: u64,
+	progress: &'a mut F
+}
+
+impl<'a, R: Read, F: FnMut(u64, u64)> Read for ProgressReader<'a, R, F> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let read = self.inner.read(buf)?;
+		self.read += read as u64;
+		(self.progress)(self.read, self.total);
+		Ok(read)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Write;
+	use super::*;
+	use crate::testing::temp_dir;
+
+	fn tar_gz(path: &Path, build: impl FnOnce(&mut tar::Builder<flate2::write::GzEncoder<File>>)) {
+		let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(File::create(path).unwrap(), flate2::Compression::fast()));
+		build(&mut builder);
+		builder.into_inner().unwrap().finish().unwrap();
+	}
+
+	fn header(kind: tar::EntryType, name: &str, link: Option<&str>, size: u64) -> tar::Header {
+		let mut header = tar::Header::new_gnu();
+		header.set_entry_type(kind);
+		header.set_mode(0o755);
+		header.set_size(size);
+		// written by hand as set_path refuses the paths these tests are about
+		header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+		if let Some(link) = link {
+			header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+		}
+		header.set_cksum();
+		header
+	}
+
+	fn symlink(builder: &mut tar::Builder<impl Write>, name: &str, target: &str) {
+		builder.append(&header(tar::EntryType::Symlink, name, Some(target), 0), io::empty()).unwrap();
+	}
+
+	fn file(builder: &mut tar::Builder<impl Write>, name: &str, data: &[u8]) {
+		builder.append(&header(tar::EntryType::Regular, name, None, data.len() as u64), data).unwrap();
+	}
+
+	#[test]
+	fn extracts_tar_with_strip_components() {
+		let dir = temp_dir("extract");
+		let archive = dir.join("archive.tar.gz");
+		tar_gz(&archive, |builder| {
+			file(builder, "top/a/file.txt", b"hello");
+			symlink(builder, "top/link", "a/file.txt");
+		});
+
+		extract_archive(&archive, &Extraction::new(dir.join("out")).strip_components(1), |_, _| {}).unwrap();
+		assert_eq!(fs::read(dir.join("out/a/file.txt")).unwrap(), b"hello");
+		assert_eq!(fs::read(dir.join("out/link")).unwrap(), b"hello");
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_parent_directory_entries() {
+		let dir = temp_dir("extract");
+		let archive = dir.join("archive.tar.gz");
+		tar_gz(&archive, |builder| file(builder, "../escaped.txt", b"nope"));
+
+		let result = extract_archive(&archive, &Extraction::new(dir.join("out")), |_, _| {});
+		assert!(matches!(result, Err(ExtractError::UnsafePath(_))));
+		assert!(!dir.join("escaped.txt").exists());
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_symlinks_leaving_the_destination() {
+		let dir = temp_dir("extract");
+		let archive = dir.join("archive.tar.gz");
+		tar_gz(&archive, |builder| symlink(builder, "link", "../.."));
+
+		let result = extract_archive(&archive, &Extraction::new(dir.join("out")), |_, _| {});
+		assert!(matches!(result, Err(ExtractError::UnsafePath(_))));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_chained_symlinks() {
+		let dir = temp_dir("extract");
+		let archive = dir.join("archive.tar.gz");
+		tar_gz(&archive, |builder| {
+			symlink(builder, "a/b", "..");
+			symlink(builder, "l", "a/b/..");
+			file(builder, "l/escaped.txt", b"nope");
+		});
+
+		let result = extract_archive(&archive, &Extraction::new(dir.join("out")), |_, _| {});
+		assert!(matches!(result, Err(ExtractError::UnsafePath(_))));
+		assert!(!dir.join("escaped.txt").exists());
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_hard_links_through_symlinks() {
+		let dir = temp_dir("extract");
+		fs::write(dir.join("secret.txt"), b"secret").unwrap();
+		let archive = dir.join("archive.tar.gz");
+		tar_gz(&archive, |builder| {
+			symlink(builder, "a/b", "..");
+			symlink(builder, "l", "a/b/..");
+			builder.append(&header(tar::EntryType::Link, "copy.txt", Some("l/secret.txt"), 0), io::empty()).unwrap();
+		});
+
+		let result = extract_archive(&archive, &Extraction::new(dir.join("out")), |_, _| {});
+		assert!(matches!(result, Err(ExtractError::UnsafePath(_))));
+		assert!(!dir.join("out/copy.txt").exists());
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_zip_entries_leaving_the_destination() {
+		let dir = temp_dir("extract");
+		let archive = dir.join("archive.zip");
+		let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+		zip.start_file("../escaped.txt", zip::write::FileOptions::default()).unwrap();
+		zip.write_all(b"nope").unwrap();
+		zip.finish().unwrap();
+
+		let result = extract_archive(&archive, &Extraction::new(dir.join("out")), |_, _| {});
+		assert!(matches!(result, Err(ExtractError::UnsafePath(_))));
+		assert!(!dir.join("escaped.txt").exists());
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn stops_between_entries_once_cancelled() {
+		let dir = temp_dir("extract");
+		let archive = dir.join("archive.tar.gz");
+		tar_gz(&archive, |builder| {
+			file(builder, "first.txt", b"first");
+			file(builder, "second.txt", b"second");
+		});
+
+		let checks = std::cell::Cell::new(0);
+		let extraction = Extraction::new(dir.join("out")).remove_archive();
+		let result = extract_archive_cancellable(&archive, &extraction, |_, _| {}, || {
+			checks.set(checks.get() + 1);
+			checks.get() > 1
+		});
+		assert!(matches!(result, Err(ExtractError::Cancelled)));
+		assert!(dir.join("out/first.txt").exists());
+		assert!(!dir.join("out/second.txt").exists());
+		assert!(archive.exists());
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
\ No newline at end of file
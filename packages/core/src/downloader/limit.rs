@@ -0,0 +1,69 @@
+use std::time::{ Duration, Instant };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use parking_lot::Mutex;
+
+/// Shares a bytes-per-second budget between every running download.
+pub(crate) struct BandwidthLimiter {
+	bytes_per_second: AtomicU64,
+	bucket: Mutex<(Instant, f64)>
+}
+
+impl BandwidthLimiter {
+	pub fn new(bytes_per_second: u64) -> Self {
+		Self {
+			bytes_per_second: AtomicU64::new(bytes_per_second),
+			bucket: Mutex::new((Instant::now(), 0.))
+		}
+	}
+
+	pub fn limit(&self) -> u64 {
+		self.bytes_per_second.load(Ordering::Relaxed)
+	}
+
+	pub fn set_limit(&self, bytes_per_second: u64) {
+		self.bytes_per_second.store(bytes_per_second, Ordering::Relaxed);
+	}
+
+	/// Waits until `bytes` more may be received, a limit of zero never waits.
+	pub async fn acquire(&self, bytes: u64) {
+		let limit = self.limit() as f64;
+		if limit == 0. {
+			return;
+		}
+
+		let delay = {
+			let mut bucket = self.bucket.lock();
+			let now = Instant::now();
+			bucket.1 = (bucket.1 + now.duration_since(bucket.0).as_secs_f64() * limit).min(limit) - bytes as f64;
+			bucket.0 = now;
+
+			if bucket.1 < 0. { Duration::from_secs_f64(-bucket.1 / limit) } else { Duration::ZERO }
+		};
+		if !delay.is_zero() {
+			tokio::time::sleep(delay).await;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn spreads_bytes_over_the_limit() {
+		let limiter = BandwidthLimiter::new(10_000);
+		let started = Instant::now();
+		limiter.acquire(2_000).await;
+		limiter.acquire(2_000).await;
+		let elapsed = started.elapsed().as_secs_f64();
+		assert!((0.35..1.).contains(&elapsed), "{}", elapsed);
+	}
+
+	#[tokio::test]
+	async fn never_waits_without_a_limit() {
+		let limiter = BandwidthLimiter::new(0);
+		let started = Instant::now();
+		limiter.acquire(u64::MAX).await;
+		assert!(started.elapsed() < Duration::from_millis(100));
+	}
+}
\ No newline at end of file
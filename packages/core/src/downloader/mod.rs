@@ -0,0 +1,1172 @@
+use std::fmt;
+use std::sync::{ Arc, OnceLock };
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, Instant };
+use std::sync::atomic::{ AtomicBool, AtomicU64, AtomicUsize, Ordering };
+use uuid::Uuid;
+use parking_lot::RwLock;
+use reqwest::{ header, StatusCode };
+use serde::{ Serialize, Deserialize };
+use tokio::io::{ AsyncReadExt, AsyncWriteExt };
+use tokio::runtime::{ Handle, Runtime, RuntimeFlavor };
+use tokio::sync::{ watch, Mutex, Semaphore };
+
+use crate::extension::{ Capability, CapabilityError, CapabilityGuard };
+
+#[cfg(feature = "core")]
+use crate::state::{ State, LoadingBarType };
+
+mod cache;
+mod limit;
+mod retry;
+mod extract;
+mod checksum;
+
+pub use cache::*;
+pub use retry::*;
+pub use extract::*;
+pub use checksum::*;
+use limit::BandwidthLimiter;
+
+#[cfg(feature = "core")]
+mod queue;
+#[cfg(feature = "core")]
+use queue::DownloadQueue;
+
+/// How many downloads may run at once when no limit is given.
+pub const DEFAULT_MAX_CONCURRENT: usize = 4;
+
+/// How often a running download updates its loading bar.
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+static FALLBACK_RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Returns the runtime downloads should be spawned on, which is the caller's
+/// if there is one, so extensions can start downloads from synchronous code.
+/// Single threaded runtimes are skipped, as waiting for a download would block the only thread that could run it.
+pub(crate) fn runtime_handle() -> Handle {
+	match Handle::try_current() {
+		Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => handle,
+		_ => fallback_handle()
+	}
+}
+
+fn fallback_handle() -> Handle {
+	FALLBACK_RUNTIME.get_or_init(|| {
+		tokio::runtime::Builder::new_multi_thread()
+			.thread_name("hero-downloader")
+			.enable_all()
+			.build()
+			.expect("failed to build downloader runtime")
+	}).handle().clone()
+}
+
+pub struct Downloader {
+	items: Arc<RwLock<Vec<Download>>>,
+	cache: RwLock<Option<DownloadCache>>,
+	client: reqwest::Client,
+	permits: Arc<Semaphore>,
+	max_concurrent: AtomicUsize,
+	retry_policy: RwLock<RetryPolicy>,
+	limiter: Arc<BandwidthLimiter>,
+	guard: RwLock<Option<CapabilityGuard>>,
+
+	#[cfg(feature = "core")]
+	queue: RwLock<Option<Arc<DownloadQueue>>>
+}
+
+impl Downloader {
+	pub fn new() -> Self {
+		Self::with_max_concurrent(DEFAULT_MAX_CONCURRENT)
+	}
+
+	pub fn with_max_concurrent(max_concurrent: usize) -> Self {
+		Self {
+			items: Arc::new(RwLock::new(vec![])),
+			cache: RwLock::new(None),
+			client: reqwest::Client::new(),
+			permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+			max_concurrent: AtomicUsize::new(max_concurrent.max(1)),
+			retry_policy: RwLock::new(RetryPolicy::default()),
+			limiter: Arc::new(BandwidthLimiter::new(0)),
+			guard: RwLock::new(None),
+
+			#[cfg(feature = "core")]
+			queue: RwLock::new(None)
+		}
+	}
+
+	pub fn max_concurrent(&self) -> usize {
+		self.max_concurrent.load(Ordering::Relaxed)
+	}
+
+	/// Changes how many downloads may run at once, running downloads are
+	/// never interrupted, new ones just wait until there is room for them.
+	pub fn set_max_concurrent(&self, max_concurrent: usize) {
+		let max_concurrent = max_concurrent.max(1);
+		let previous = self.max_concurrent.swap(max_concurrent, Ordering::Relaxed);
+		match max_concurrent.cmp(&previous) {
+			std::cmp::Ordering::Greater => self.permits.add_permits(max_concurrent - previous),
+			std::cmp::Ordering::Less => {
+				let permits = self.permits.clone();
+				runtime_handle().spawn(async move {
+					if let Ok(permit) = permits.acquire_many_owned((previous - max_concurrent) as u32).await {
+						permit.forget();
+					}
+				});
+			},
+			std::cmp::Ordering::Equal => {}
+		}
+	}
+
+	/// The combined download speed limit in bytes per second, zero if unlimited.
+	pub fn speed_limit(&self) -> u64 {
+		self.limiter.limit()
+	}
+
+	pub fn set_speed_limit(&self, bytes_per_second: u64) {
+		self.limiter.set_limit(bytes_per_second);
+	}
+
+	pub fn retry_policy(&self) -> RetryPolicy {
+		self.retry_policy.read().clone()
+	}
+
+	pub fn set_retry_policy(&self, retry_policy: RetryPolicy) {
+		*self.retry_policy.write() = retry_policy;
+	}
+
+	/// Every download started through this downloader, including finished ones.
+	pub fn items(&self) -> Vec<Download> {
+		self.items.read().clone()
+	}
+
+	pub fn cache(&self) -> Option<DownloadCache> {
+		self.cache.read().clone()
+	}
+
+	/// Sets where downloads with a checksum are cached, future downloads of
+	/// the same file will be copied from here instead of being fetched again.
+	pub fn set_cache(&self, cache: Option<DownloadCache>) {
+		*self.cache.write() = cache;
+	}
+
+	/// Refuses downloads an acting extension hasn't been granted the hosts and directories for.
+	pub fn set_capability_guard(&self, guard: Option<CapabilityGuard>) {
+		*self.guard.write() = guard;
+	}
+
+	/// Refuses requests with malformed checksums, and those the extension that made them isn't allowed to make.
+	/// New downloads are checked against the acting extension, downloads restored from the queue against
+	/// the extension recorded with them, which has to still be loaded and granted what it needs.
+	fn validate(&self, download: &Download, queued: bool) -> Result<(), DownloadError> {
+		let request = download.request();
+		if let Some(checksum) = request.checksum.as_ref().filter(|x| !x.is_valid()) {
+			return Err(DownloadError::InvalidChecksum(checksum.clone()));
+		}
+
+		let Some(guard) = *self.guard.read() else {
+			return Ok(());
+		};
+		let result = match download.extension() {
+			Some(id) if queued => {
+				let permissions = guard.permissions(id);
+				check_request(request, |needed| match &permissions {
+					Some(permissions) => permissions.check(needed),
+					None => Err(CapabilityError { extension: id.into(), capability: needed.clone() })
+				})
+			},
+			_ => check_request(request, |needed| guard.check(needed))
+		};
+		result.map_err(DownloadError::Forbidden)
+	}
+
+	/// Forgets about downloads that are no longer running.
+	pub fn clear_finished(&self) {
+		self.items.write().retain(|x| !x.status().is_done());
+	}
+
+	/// Saves unfinished downloads to the given file as they change,
+	/// so they can be picked up again with [`Downloader::resume_queue`].
+	#[cfg(feature = "core")]
+	pub fn set_queue_path(&self, path: Option<PathBuf>) {
+		*self.queue.write() = path.map(|x| Arc::new(DownloadQueue::new(x, self.items.clone())));
+	}
+
+	/// Restarts every download that was unfinished when the queue was last saved,
+	/// downloads that were paused stay paused.
+	#[cfg(feature = "core")]
+	pub fn resume_queue(&self) -> Vec<Download> {
+		let entries = match self.queue.read().as_ref() {
+			Some(queue) => queue.load(),
+			None => return vec![]
+		};
+		entries.into_iter().map(|x| self.submit(Download::new(x.request, x.paused, x.extension), true)).collect()
+	}
+
+	pub fn download_file_over_http(&self, request: DownloadRequest) -> Download {
+		let extension = self.guard.read().and_then(|x| x.acting()).map(|x| x.id.clone());
+		self.submit(Download::new(request, false, extension), false)
+	}
+
+	/// Starts a download, unless it is refused by [`Downloader::validate`].
+	fn submit(&self, download: Download, queued: bool) -> Download {
+		if let Err(err) = self.validate(&download, queued) {
+			println!("refused to download {}: {}", download.url(), err);
+			download.inner.status.send_replace(DownloadStatus::Failed(err));
+			self.items.write().push(download.clone());
+			return download;
+		}
+		self.start(download)
+	}
+
+	fn start(&self, download: Download) -> Download {
+		self.items.write().push(download.clone());
+
+		let task = download.clone();
+		let context = DownloadContext {
+			cache: self.cache(),
+			client: self.client.clone(),
+			permits: self.permits.clone(),
+			limiter: self.limiter.clone(),
+			retry_policy: self.retry_policy(),
+
+			#[cfg(feature = "core")]
+			queue: self.queue.read().clone()
+		};
+		context.save_queue();
+
+		runtime_handle().spawn(async move {
+			let mut control = task.inner.control.subscribe();
+			let result = loop {
+				let current = control.wait_for(|x| *x != DownloadControl::Pause).await.map_or(DownloadControl::Stop, |x| *x);
+				if current == DownloadControl::Stop {
+					break Err(DownloadError::Stopped);
+				}
+
+				task.inner.status.send_replace(DownloadStatus::Queued);
+				context.save_queue();
+
+				let result = tokio::select! {
+					biased;
+					_ = control.wait_for(|x| *x != DownloadControl::Run) => None,
+					result = task.run(&context) => Some(result)
+				};
+				match result {
+					Some(result) => break result,
+					None => if *control.borrow() == DownloadControl::Pause {
+						task.inner.status.send_replace(DownloadStatus::Paused);
+						task.finish_loading_bar();
+						context.save_queue();
+					}
+				}
+			};
+
+			task.finish(result).await;
+			context.save_queue();
+		});
+
+		download
+	}
+
+	pub fn download_files_over_http(&self, requests: Vec<DownloadRequest>) -> DownloadGroup {
+		DownloadGroup {
+			items: requests.into_iter().map(|x| self.download_file_over_http(x)).collect()
+		}
+	}
+}
+
+impl Default for Downloader {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Everything a running download needs from its [`Downloader`].
+struct DownloadContext {
+	cache: Option<DownloadCache>,
+	client: reqwest::Client,
+	permits: Arc<Semaphore>,
+	limiter: Arc<BandwidthLimiter>,
+	retry_policy: RetryPolicy,
+
+	#[cfg(feature = "core")]
+	queue: Option<Arc<DownloadQueue>>
+}
+
+impl DownloadContext {
+	fn save_queue(&self) {
+		#[cfg(feature = "core")]
+		if let Some(queue) = &self.queue {
+			queue.save();
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DownloadRequest {
+	pub url: String,
+
+	/// Other places the same file can be downloaded from, tried in order when `url` fails.
+	pub mirrors: Vec<String>,
+
+	/// Where the file should end up once it has been fully downloaded.
+	pub path: PathBuf,
+
+	/// Expected size in bytes, the download fails if it doesn't match.
+	pub size: Option<u64>,
+
+	/// Expected hash, the download fails if it doesn't match.
+	pub checksum: Option<Checksum>,
+
+	/// Treats the file as an archive and extracts it once downloaded.
+	pub extract: Option<Extraction>
+}
+
+impl DownloadRequest {
+	pub fn new(url: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+		Self {
+			url: url.into(),
+			mirrors: vec![],
+			path: path.into(),
+			size: None,
+			checksum: None,
+			extract: None
+		}
+	}
+
+	pub fn mirror(mut self, url: impl Into<String>) -> Self {
+		self.mirrors.push(url.into());
+		self
+	}
+
+	pub fn size(mut self, size: u64) -> Self {
+		self.size = Some(size);
+		self
+	}
+
+	pub fn sha1(mut self, hex: impl Into<String>) -> Self {
+		self.checksum = Some(Checksum::Sha1(hex.into()));
+		self
+	}
+
+	pub fn sha256(mut self, hex: impl Into<String>) -> Self {
+		self.checksum = Some(Checksum::Sha256(hex.into()));
+		self
+	}
+
+	pub fn extract(mut self, extraction: Extraction) -> Self {
+		self.extract = Some(extraction);
+		self
+	}
+
+	/// The file data is written to while the download is in progress.
+	pub fn part_path(&self) -> PathBuf {
+		let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+		name.push(".part");
+		self.path.with_file_name(name)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum DownloadStatus {
+	Queued,
+	Downloading,
+	Paused,
+	Extracting,
+	Finished,
+	Stopped,
+	Failed(DownloadError)
+}
+
+impl DownloadStatus {
+	/// Whether or not the download has stopped running, for any reason.
+	pub fn is_done(&self) -> bool {
+		matches!(self, Self::Finished | Self::Stopped | Self::Failed(_))
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DownloadProgress {
+	pub downloaded: u64,
+
+	/// Total size in bytes, if the server told us.
+	pub total: Option<u64>
+}
+
+impl DownloadProgress {
+	pub fn fraction(&self) -> Option<f64> {
+		self.total.filter(|x| *x > 0).map(|x| self.downloaded as f64 / x as f64)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum DownloadError {
+	Http(Arc<reqwest::Error>),
+	Io(Arc<std::io::Error>),
+	SizeMismatch {
+		expected: u64,
+		actual: u64
+	},
+	ChecksumMismatch {
+		expected: Checksum,
+		actual: String
+	},
+	Extract(ExtractError),
+
+	/// The request's checksum isn't a digest of the right length, see [`Checksum::is_valid`].
+	InvalidChecksum(Checksum),
+
+	/// The extension that asked for the download wasn't granted permission to make it.
+	Forbidden(CapabilityError),
+	Stopped
+}
+
+impl fmt::Display for DownloadError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Http(err) => write!(f, "http error: {}", err),
+			Self::Io(err) => write!(f, "io error: {}", err),
+			Self::SizeMismatch { expected, actual } => write!(f, "expected {} bytes but received {}", expected, actual),
+			Self::ChecksumMismatch { expected, actual } => write!(f, "expected {} but received {}", expected, actual),
+			Self::Extract(err) => write!(f, "failed to extract: {}", err),
+			Self::InvalidChecksum(checksum) => write!(f, "{} is not a valid checksum", checksum),
+			Self::Forbidden(err) => err.fmt(f),
+			Self::Stopped => write!(f, "download was stopped")
+		}
+	}
+}
+
+impl std::error::Error for DownloadError {}
+
+impl DownloadError {
+	/// Whether or not trying again later might succeed.
+	pub fn is_transient(&self) -> bool {
+		match self {
+			Self::Http(err) => err.status().map_or(true, |x| {
+				x.is_server_error() || x == StatusCode::REQUEST_TIMEOUT || x == StatusCode::TOO_MANY_REQUESTS
+			}),
+			Self::SizeMismatch { .. } | Self::ChecksumMismatch { .. } => true,
+			Self::Io(_) | Self::Extract(_) | Self::InvalidChecksum(_) | Self::Forbidden(_) | Self::Stopped => false
+		}
+	}
+}
+
+impl From<reqwest::Error> for DownloadError {
+	fn from(value: reqwest::Error) -> Self {
+		Self::Http(Arc::new(value))
+	}
+}
+
+impl From<std::io::Error> for DownloadError {
+	fn from(value: std::io::Error) -> Self {
+		Self::Io(Arc::new(value))
+	}
+}
+
+impl From<ExtractError> for DownloadError {
+	fn from(value: ExtractError) -> Self {
+		match value {
+			ExtractError::Cancelled => Self::Stopped,
+			value => Self::Extract(value)
+		}
+	}
+}
+
+/// A handle to a single download, cheap to clone.
+#[derive(Clone)]
+pub struct Download {
+	inner: Arc<DownloadInner>
+}
+
+struct DownloadInner {
+	id: Uuid,
+	request: DownloadRequest,
+	downloaded: AtomicU64,
+	total: AtomicU64,
+	extracted: AtomicU64,
+	extract_total: AtomicU64,
+	status: watch::Sender<DownloadStatus>,
+	control: watch::Sender<DownloadControl>,
+
+	/// Whether the file is already in place, so resuming a paused extraction doesn't download it again.
+	fetched: AtomicBool,
+
+	/// Held for as long as the archive is being extracted.
+	/// Pausing only stops an extraction at its next entry, so a resumed one waits on this for it to finish.
+	extracting: Arc<Mutex<()>>,
+
+	/// The extension that started the download, if it wasn't HERO itself.
+	extension: Option<String>,
+
+	#[cfg(feature = "core")]
+	loading_bar: parking_lot::Mutex<Option<Uuid>>
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DownloadControl {
+	Run,
+	Pause,
+	Stop
+}
+
+impl Download {
+	fn new(request: DownloadRequest, paused: bool, extension: Option<String>) -> Self {
+		Self {
+			inner: Arc::new(DownloadInner {
+				id: Uuid::new_v4(),
+				request,
+				downloaded: AtomicU64::new(0),
+				total: AtomicU64::new(0),
+				extracted: AtomicU64::new(0),
+				extract_total: AtomicU64::new(0),
+				status: watch::channel(if paused { DownloadStatus::Paused } else { DownloadStatus::Queued }).0,
+				control: watch::channel(if paused { DownloadControl::Pause } else { DownloadControl::Run }).0,
+				fetched: AtomicBool::new(false),
+				extracting: Arc::new(Mutex::new(())),
+				extension,
+
+				#[cfg(feature = "core")]
+				loading_bar: parking_lot::Mutex::new(None)
+			})
+		}
+	}
+
+	pub fn id(&self) -> Uuid {
+		self.inner.id
+	}
+
+	pub fn request(&self) -> &DownloadRequest {
+		&self.inner.request
+	}
+
+	pub fn url(&self) -> &str {
+		&self.inner.request.url
+	}
+
+	/// Identifier of the extension that started the download, [`None`] if HERO did.
+	pub fn extension(&self) -> Option<&str> {
+		self.inner.extension.as_deref()
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.inner.request.path
+	}
+
+	/// The name of the file being downloaded.
+	pub fn name(&self) -> String {
+		self.path().file_name().map_or_else(|| self.url().to_string(), |x| x.to_string_lossy().to_string())
+	}
+
+	pub fn status(&self) -> DownloadStatus {
+		self.inner.status.borrow().clone()
+	}
+
+	pub fn progress(&self) -> DownloadProgress {
+		let total = self.inner.total.load(Ordering::Relaxed);
+		DownloadProgress {
+			downloaded: self.inner.downloaded.load(Ordering::Relaxed),
+			total: if total > 0 { Some(total) } else { None }
+		}
+	}
+
+	/// How far along extracting the archive is, from zero to one, once it has started.
+	pub fn extraction_progress(&self) -> Option<f64> {
+		let total = self.inner.extract_total.load(Ordering::Relaxed);
+		if total > 0 {
+			return Some(self.inner.extracted.load(Ordering::Relaxed) as f64 / total as f64);
+		}
+		None
+	}
+
+	/// Cancels the download, removing anything that was written so far.
+	/// Downloads that fail for other reasons keep their partial file so they can be resumed.
+	pub fn stop(&self) {
+		self.inner.control.send_replace(DownloadControl::Stop);
+	}
+
+	/// Interrupts the download, keeping what was written so far so it can continue with [`Download::resume`].
+	pub fn pause(&self) {
+		self.inner.control.send_if_modified(|x| {
+			let is_running = *x == DownloadControl::Run;
+			if is_running {
+				*x = DownloadControl::Pause;
+			}
+			is_running
+		});
+	}
+
+	pub fn resume(&self) {
+		self.inner.control.send_if_modified(|x| {
+			let is_paused = *x == DownloadControl::Pause;
+			if is_paused {
+				*x = DownloadControl::Run;
+			}
+			is_paused
+		});
+	}
+
+	pub fn is_paused(&self) -> bool {
+		*self.inner.control.borrow() == DownloadControl::Pause
+	}
+
+	/// Waits for the download to finish, fail or be stopped.
+	pub async fn wait(&self) -> Result<(), DownloadError> {
+		let mut status = self.inner.status.subscribe();
+		let status = status.wait_for(|x| x.is_done()).await
+			.map(|x| x.clone())
+			.unwrap_or(DownloadStatus::Stopped);
+		match status {
+			DownloadStatus::Failed(err) => Err(err),
+			DownloadStatus::Stopped => Err(DownloadError::Stopped),
+			_ => Ok(())
+		}
+	}
+
+	/// Blocking version of [`Download::wait`], for use outside of async code.
+	pub fn wait_blocking(&self) -> Result<(), DownloadError> {
+		block_on(self.wait())
+	}
+
+	async fn run(&self, context: &DownloadContext) -> Result<(), DownloadError> {
+		let request = &self.inner.request;
+		if let Some(parent) = request.path.parent() {
+			tokio::fs::create_dir_all(parent).await?;
+		}
+
+		let is_fetched = self.inner.fetched.load(Ordering::Relaxed) && tokio::fs::try_exists(&request.path).await?;
+		if !is_fetched {
+			let is_cached = match &context.cache {
+				Some(cache) => self.copy_from_cache(cache).await?,
+				None => false
+			};
+			if !is_cached {
+				self.download(context).await?;
+			}
+			self.inner.fetched.store(true, Ordering::Relaxed);
+		}
+
+		if let Some(extraction) = request.extract.clone() {
+			self.extract(extraction).await?;
+		}
+
+		Ok(())
+	}
+
+	/// Downloads the file, trying every url until one works or we run out of attempts.
+	async fn download(&self, context: &DownloadContext) -> Result<(), DownloadError> {
+		let request = &self.inner.request;
+		let _permit = context.permits.acquire().await.map_err(|_| DownloadError::Stopped)?;
+		self.inner.status.send_replace(DownloadStatus::Downloading);
+
+		let urls: Vec<&String> = std::iter::once(&request.url).chain(request.mirrors.iter()).collect();
+		let mut attempt = 0;
+		loop {
+			let url = urls[attempt as usize % urls.len()];
+			match self.fetch(url, context).await {
+				Ok(_) => break,
+				Err(err) => {
+					// a missing file is worth looking for on the other mirrors, but not asking for again
+					let can_retry = err.is_transient() || (matches!(err, DownloadError::Http(_)) && (attempt as usize + 1) < urls.len());
+					if !can_retry || attempt + 1 >= context.retry_policy.max_attempts {
+						return Err(err);
+					}
+
+					let delay = context.retry_policy.delay(attempt);
+					println!("download from {} failed, retrying in {:?}: {}", url, delay, err);
+					tokio::time::sleep(delay).await;
+					attempt += 1;
+				}
+			}
+		}
+
+		if let Some((cache, checksum)) = context.cache.clone().zip(request.checksum.clone()) {
+			let path = request.path.clone();
+			match tokio::task::spawn_blocking(move || cache.insert(&checksum, &path)).await {
+				Ok(Err(err)) => println!("failed to cache {}: {}", self.url(), err),
+				Err(err) => println!("failed to cache {}: {}", self.url(), err),
+				_ => {}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Downloads the file from a single url, continuing from any partial file already on disk.
+	async fn fetch(&self, url: &str, context: &DownloadContext) -> Result<(), DownloadError> {
+		let request = &self.inner.request;
+		let part_path = request.part_path();
+		let mut offset = tokio::fs::metadata(&part_path).await.map_or(0, |x| x.len());
+
+		let mut response = send_request(&context.client, url, offset).await?;
+		if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+			offset = 0;
+			response = send_request(&context.client, url, 0).await?;
+		}
+
+		let mut response = response.error_for_status()?;
+		if offset > 0 && !is_resumed_response(&response, offset) {
+			offset = 0;
+		}
+
+		if let Some(total) = response.content_length().map(|x| x + offset).or(request.size) {
+			self.inner.total.store(total, Ordering::Relaxed);
+		}
+		self.inner.downloaded.store(offset, Ordering::Relaxed);
+
+		let mut hasher = request.checksum.as_ref().map(Checksum::hasher);
+		let mut file = if offset > 0 {
+			if let Some(hasher) = hasher.as_mut() {
+				hash_existing(&part_path, hasher).await?;
+			}
+			tokio::fs::OpenOptions::new().append(true).open(&part_path).await?
+		} else {
+			tokio::fs::File::create(&part_path).await?
+		};
+
+		let mut size = offset;
+		let mut reported_at = Instant::now();
+		self.report_progress();
+
+		while let Some(chunk) = response.chunk().await? {
+			context.limiter.acquire(chunk.len() as u64).await;
+			file.write_all(&chunk).await?;
+			if let Some(hasher) = hasher.as_mut() {
+				hasher.update(&chunk);
+			}
+
+			size += chunk.len() as u64;
+			self.inner.downloaded.store(size, Ordering::Relaxed);
+
+			if reported_at.elapsed() >= REPORT_INTERVAL {
+				self.report_progress();
+				reported_at = Instant::now();
+			}
+		}
+		file.flush().await?;
+		drop(file);
+		self.report_progress();
+
+		if let Err(err) = verify(request, size, hasher) {
+			tokio::fs::remove_file(&part_path).await?;
+			return Err(err);
+		}
+
+		tokio::fs::rename(&part_path, &request.path).await?;
+		Ok(())
+	}
+
+	async fn extract(&self, extraction: Extraction) -> Result<(), DownloadError> {
+		self.finish_loading_bar();
+		self.inner.status.send_replace(DownloadStatus::Extracting);
+
+		let extracting = self.inner.extracting.clone().lock_owned().await;
+		let control = self.inner.control.subscribe();
+		let download = self.clone();
+		let archive = self.inner.request.path.clone();
+		let mut reported_at = Instant::now();
+		tokio::task::spawn_blocking(move || {
+			let _extracting = extracting;
+			extract_archive_cancellable(&archive, &extraction, |current, total| {
+				download.inner.extracted.store(current, Ordering::Relaxed);
+				download.inner.extract_total.store(total, Ordering::Relaxed);
+				if current == total || reported_at.elapsed() >= REPORT_INTERVAL {
+					download.report_extraction();
+					reported_at = Instant::now();
+				}
+			}, || *control.borrow() != DownloadControl::Run)
+		})
+			.await
+			.map_err(|x| std::io::Error::new(std::io::ErrorKind::Other, x))??;
+
+		self.finish_loading_bar();
+		Ok(())
+	}
+
+	/// Copies the file from the cache if we already have it, returning whether or not we did.
+	async fn copy_from_cache(&self, cache: &DownloadCache) -> Result<bool, DownloadError> {
+		let request = &self.inner.request;
+		let Some((checksum, source)) = request.checksum.as_ref().and_then(|x| Some((x, cache.get(x)?))) else {
+			return Ok(false);
+		};
+
+		let size = tokio::fs::metadata(&source).await?.len();
+		if request.size.map_or(false, |x| x != size) {
+			return Ok(false);
+		}
+
+		// the cache lives on disk where anything could have changed it, so it isn't trusted blindly
+		let mut hasher = checksum.hasher();
+		hash_existing(&source, &mut hasher).await?;
+		if !checksum.matches(&hasher.finish()) {
+			println!("cached copy of {} is corrupt, downloading it again", self.url());
+			let _ = tokio::fs::remove_file(&source).await;
+			return Ok(false);
+		}
+
+		tokio::fs::copy(&source, &request.path).await?;
+		self.inner.total.store(size, Ordering::Relaxed);
+		self.inner.downloaded.store(size, Ordering::Relaxed);
+
+		Ok(true)
+	}
+
+	async fn finish(&self, result: Result<(), DownloadError>) {
+		let status = match result {
+			Ok(_) => DownloadStatus::Finished,
+			Err(DownloadError::Stopped) => {
+				let _ = tokio::fs::remove_file(self.inner.request.part_path()).await;
+				DownloadStatus::Stopped
+			},
+			Err(err) => {
+				println!("download of {} failed: {}", self.url(), err);
+				DownloadStatus::Failed(err)
+			}
+		};
+		self.inner.status.send_replace(status);
+		self.finish_loading_bar();
+	}
+
+	/// Mirrors our download progress onto a loading bar, so the frontend can display it.
+	fn report_progress(&self) {
+		#[cfg(feature = "core")] {
+			let progress = self.progress();
+			self.update_loading_bar(|| LoadingBarType::Download {
+				download_id: self.id(),
+				name: self.name()
+			}, progress.downloaded, progress.total);
+		}
+	}
+
+	fn report_extraction(&self) {
+		#[cfg(feature = "core")] {
+			let total = self.inner.extract_total.load(Ordering::Relaxed);
+			self.update_loading_bar(|| LoadingBarType::Extract {
+				download_id: self.id(),
+				name: self.name()
+			}, self.inner.extracted.load(Ordering::Relaxed), Some(total));
+		}
+	}
+
+	#[cfg(feature = "core")]
+	fn update_loading_bar(&self, bar_type: impl FnOnce() -> LoadingBarType, current: u64, total: Option<u64>) {
+		let state = State::get();
+		let mut loading_bar = self.inner.loading_bar.lock();
+		let bar_id = *loading_bar.get_or_insert_with(|| state.init_loading(bar_type(), 0.));
+		state.set_loading(&bar_id, current as f64, total.map(|x| x as f64));
+	}
+
+	fn finish_loading_bar(&self) {
+		#[cfg(feature = "core")]
+		if let Some(bar_id) = self.inner.loading_bar.lock().take() {
+			State::get().finish_loading(&bar_id);
+		}
+	}
+}
+
+pub struct DownloadGroup {
+	items: Vec<Download>
+}
+
+impl DownloadGroup {
+	pub fn items(&self) -> &[Download] {
+		&self.items
+	}
+
+	/// Combined progress of every download in this group.
+	pub fn progress(&self) -> DownloadProgress {
+		let mut total = Some(0);
+		let mut downloaded = 0;
+		for download in self.items.iter() {
+			let progress = download.progress();
+			downloaded += progress.downloaded;
+			total = total.zip(progress.total).map(|(a, b)| a + b);
+		}
+
+		DownloadProgress { downloaded, total }
+	}
+
+	pub fn stop_all(&self) {
+		for download in self.items.iter() {
+			download.stop();
+		}
+	}
+
+	/// Waits for every download, returning the first error encountered.
+	pub async fn wait(&self) -> Result<(), DownloadError> {
+		let mut result = Ok(());
+		for download in self.items.iter() {
+			if let Err(err) = download.wait().await {
+				if result.is_ok() {
+					result = Err(err);
+				}
+			}
+		}
+		result
+	}
+
+	/// Blocking version of [`DownloadGroup::wait`], for use outside of async code.
+	pub fn wait_blocking(&self) -> Result<(), DownloadError> {
+		block_on(self.wait())
+	}
+}
+
+async fn send_request(client: &reqwest::Client, url: &str, offset: u64) -> Result<reqwest::Response, DownloadError> {
+	let mut builder = client.get(url);
+	if offset > 0 {
+		builder = builder.header(header::RANGE, format!("bytes={}-", offset));
+	}
+	Ok(builder.send().await?)
+}
+
+/// Whether or not the server is sending us the rest of the file starting at `offset`,
+/// rather than ignoring our range and sending the whole thing.
+fn is_resumed_response(response: &reqwest::Response, offset: u64) -> bool {
+	response.status() == StatusCode::PARTIAL_CONTENT && response.headers()
+		.get(header::CONTENT_RANGE)
+		.and_then(|x| x.to_str().ok())
+		.map_or(false, |x| x.starts_with(&format!("bytes {}-", offset)))
+}
+
+async fn hash_existing(path: &Path, hasher: &mut ChecksumHasher) -> std::io::Result<()> {
+	let mut file = tokio::fs::File::open(path).await?;
+	let mut buffer = vec![0; 64 * 1024];
+	loop {
+		let read = file.read(&mut buffer).await?;
+		if read == 0 {
+			return Ok(());
+		}
+		hasher.update(&buffer[..read]);
+	}
+}
+
+/// Checks every host and directory a request touches.
+fn check_request(request: &DownloadRequest, check: impl Fn(&Capability) -> Result<(), CapabilityError>) -> Result<(), CapabilityError> {
+	for url in std::iter::once(&request.url).chain(request.mirrors.iter()) {
+		let host = reqwest::Url::parse(url).ok().and_then(|x| x.host_str().map(str::to_string)).unwrap_or_default();
+		check(&Capability::Network(host))?;
+	}
+	check(&Capability::Filesystem(request.path.clone()))?;
+	if let Some(extraction) = &request.extract {
+		check(&Capability::Filesystem(extraction.destination.clone()))?;
+	}
+	Ok(())
+}
+
+fn verify(request: &DownloadRequest, size: u64, hasher: Option<ChecksumHasher>) -> Result<(), DownloadError> {
+	if let Some(expected) = request.size.filter(|x| *x != size) {
+		return Err(DownloadError::SizeMismatch { expected, actual: size });
+	}
+	if let Some((expected, hasher)) = request.checksum.clone().zip(hasher) {
+		let actual = hasher.finish();
+		if !expected.matches(&actual) {
+			return Err(DownloadError::ChecksumMismatch { expected, actual });
+		}
+	}
+	Ok(())
+}
+
+/// Runs a future to completion from synchronous code, even if that code is itself running on a runtime.
+/// [`tokio::task::block_in_place`] panics on single threaded runtimes, so there the future is run on the fallback runtime from another thread instead.
+fn block_on<F: std::future::Future + Send>(future: F) -> F::Output where F::Output: Send {
+	match Handle::try_current() {
+		Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => tokio::task::block_in_place(|| handle.block_on(future)),
+		Ok(_) => std::thread::scope(|scope| {
+			scope.spawn(|| fallback_handle().block_on(future))
+				.join()
+				.unwrap_or_else(|err| std::panic::resume_unwind(err))
+		}),
+		Err(_) => fallback_handle().block_on(future)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing::temp_dir;
+
+	const SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+	/// A downloader whose downloads fail straight away, unless they are served from its cache.
+	fn offline_downloader(cache: &Path) -> Downloader {
+		let downloader = Downloader::new();
+		downloader.set_retry_policy(RetryPolicy::none());
+		downloader.set_cache(Some(DownloadCache::new(cache, DEFAULT_CACHE_SIZE)));
+		downloader
+	}
+
+	/// Serves `body` over http on a port of its own, answering ranged requests with the rest of it.
+	/// Returns the url along with the offsets it was asked to start from, one per request.
+	fn serve(body: &'static [u8]) -> (String, Arc<parking_lot::Mutex<Vec<Option<usize>>>>) {
+		use std::io::{ BufRead, Write };
+
+		let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}/file", listener.local_addr().unwrap());
+		let offsets = Arc::new(parking_lot::Mutex::new(vec![]));
+		let requested = offsets.clone();
+		std::thread::spawn(move || for stream in listener.incoming() {
+			let mut stream = stream.unwrap();
+			let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+			let mut offset = None;
+			loop {
+				let mut line = String::new();
+				if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+					break;
+				}
+				if let Some(range) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+					offset = range.trim().trim_end_matches('-').parse::<usize>().ok();
+				}
+			}
+			requested.lock().push(offset);
+
+			let start = offset.unwrap_or(0);
+			let status = match offset {
+				Some(start) => format!("206 Partial Content\r\nContent-Range: bytes {}-{}/{}", start, body.len() - 1, body.len()),
+				None => "200 OK".into()
+			};
+			let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len() - start);
+			let _ = stream.write_all(&body[start..]);
+		});
+		(url, offsets)
+	}
+
+	#[test]
+	fn resumes_partial_downloads_from_where_they_stopped() {
+		let dir = temp_dir("downloader");
+		let downloader = offline_downloader(&dir.join("cache"));
+		let (url, offsets) = serve(b"hello");
+		let request = DownloadRequest::new(url, dir.join("file")).sha256(SHA256);
+		std::fs::write(request.part_path(), b"hel").unwrap();
+
+		downloader.download_file_over_http(request).wait_blocking().unwrap();
+		assert_eq!(std::fs::read(dir.join("file")).unwrap(), b"hello");
+		assert_eq!(*offsets.lock(), [Some(3)]);
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn discards_downloads_not_matching_their_checksum() {
+		let dir = temp_dir("downloader");
+		let downloader = offline_downloader(&dir.join("cache"));
+		let (url, _) = serve(b"HELLO");
+		let request = DownloadRequest::new(url, dir.join("file")).sha256(SHA256);
+		let part_path = request.part_path();
+
+		let result = downloader.download_file_over_http(request).wait_blocking();
+		assert!(matches!(result, Err(DownloadError::ChecksumMismatch { .. })));
+		assert!(!dir.join("file").exists() && !part_path.exists());
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn falls_back_to_mirrors() {
+		let dir = temp_dir("downloader");
+		let downloader = offline_downloader(&dir.join("cache"));
+		downloader.set_retry_policy(RetryPolicy {
+			max_attempts: 2,
+			initial_delay: Duration::from_millis(10),
+			max_delay: Duration::from_millis(10)
+		});
+		let (mirror, offsets) = serve(b"hello");
+		let request = DownloadRequest::new("http://127.0.0.1:1/file", dir.join("file")).mirror(mirror).sha256(SHA256);
+
+		downloader.download_file_over_http(request).wait_blocking().unwrap();
+		assert_eq!(std::fs::read(dir.join("file")).unwrap(), b"hello");
+		assert_eq!(offsets.lock().len(), 1);
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_malformed_checksums() {
+		let dir = temp_dir("downloader");
+		let downloader = offline_downloader(&dir.join("cache"));
+		let download = downloader.download_file_over_http(DownloadRequest::new("http://127.0.0.1:1/file", dir.join("file")).sha256("../secret.txt"));
+		assert!(matches!(download.status(), DownloadStatus::Failed(DownloadError::InvalidChecksum(_))));
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn copies_verified_files_from_the_cache() {
+		let dir = temp_dir("downloader");
+		let downloader = offline_downloader(&dir.join("cache"));
+		std::fs::write(dir.join("source"), b"hello").unwrap();
+		downloader.cache().unwrap().insert(&Checksum::Sha256(SHA256.into()), &dir.join("source")).unwrap();
+
+		let download = downloader.download_file_over_http(DownloadRequest::new("http://127.0.0.1:1/file", dir.join("file")).sha256(SHA256));
+		download.wait_blocking().unwrap();
+		assert_eq!(std::fs::read(dir.join("file")).unwrap(), b"hello");
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[tokio::test(flavor = "current_thread")]
+	async fn waits_for_downloads_on_single_threaded_runtimes() {
+		let dir = temp_dir("downloader");
+		let downloader = offline_downloader(&dir.join("cache"));
+		std::fs::write(dir.join("source"), b"hello").unwrap();
+		downloader.cache().unwrap().insert(&Checksum::Sha256(SHA256.into()), &dir.join("source")).unwrap();
+
+		let download = downloader.download_file_over_http(DownloadRequest::new("http://127.0.0.1:1/file", dir.join("file")).sha256(SHA256));
+		download.wait_blocking().unwrap();
+		DownloadGroup { items: vec![download] }.wait_blocking().unwrap();
+		assert_eq!(std::fs::read(dir.join("file")).unwrap(), b"hello");
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn ignores_corrupt_cache_entries() {
+		let dir = temp_dir("downloader");
+		let downloader = offline_downloader(&dir.join("cache"));
+		let checksum = Checksum::Sha256(SHA256.into());
+		std::fs::write(dir.join("source"), b"HELLO").unwrap();
+		downloader.cache().unwrap().insert(&checksum, &dir.join("source")).unwrap();
+
+		let download = downloader.download_file_over_http(DownloadRequest::new("http://127.0.0.1:1/file", dir.join("file")).sha256(SHA256));
+		assert!(matches!(download.wait_blocking(), Err(DownloadError::Http(_))));
+		assert!(!dir.join("file").exists());
+		assert_eq!(downloader.cache().unwrap().get(&checksum), None);
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	/// Resumes a paused download that the extension `test` left in the queue, with `permissions` standing in for loaded extensions.
+	#[cfg(feature = "core")]
+	fn resume_from_test(permissions: fn(&str) -> Option<Arc<crate::extension::ExtensionPermissions>>) -> DownloadStatus {
+		let dir = temp_dir("downloader");
+		let entry = queue::QueueEntry {
+			request: DownloadRequest::new("http://127.0.0.1:1/file", dir.join("file")),
+			paused: true,
+			extension: Some("test".into())
+		};
+		std::fs::write(dir.join("queue.json"), serde_json::to_string(&[entry]).unwrap()).unwrap();
+
+		let downloader = offline_downloader(&dir.join("cache"));
+		downloader.set_capability_guard(Some(crate::extension::capability::tests::guard(permissions)));
+		downloader.set_queue_path(Some(dir.join("queue.json")));
+		let downloads = downloader.resume_queue();
+		assert_eq!(downloads.len(), 1);
+		assert_eq!(downloads[0].extension(), Some("test"));
+
+		let status = downloads[0].status();
+		downloads[0].stop();
+		// stopping saves the queue again in the background, which may still be writing to it
+		let _ = std::fs::remove_dir_all(dir);
+		status
+	}
+
+	#[test]
+	#[cfg(feature = "core")]
+	fn checks_queued_downloads_against_their_extension() {
+		use crate::extension::capability::tests::permissions;
+
+		let status = resume_from_test(|_| None);
+		assert!(matches!(status, DownloadStatus::Failed(DownloadError::Forbidden(x)) if x.extension == "test"));
+
+		let status = resume_from_test(|_| Some(Arc::new(permissions(vec![], vec![]))));
+		assert!(matches!(status, DownloadStatus::Failed(DownloadError::Forbidden(_))));
+
+		let status = resume_from_test(|_| {
+			let everything = vec![Capability::Network("*".into()), Capability::Filesystem("/".into())];
+			Some(Arc::new(permissions(everything.clone(), everything)))
+		});
+		assert!(matches!(status, DownloadStatus::Paused));
+	}
+}
\ No newline at end of file
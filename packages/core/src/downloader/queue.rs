@@ -0,0 +1,74 @@
+use std::fs;
+use std::sync::Arc;
+use std::path::PathBuf;
+use parking_lot::{ Mutex, RwLock };
+use serde::{ Serialize, Deserialize };
+
+use super::{ Download, DownloadStatus, DownloadRequest };
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct QueueEntry {
+	pub request: DownloadRequest,
+	pub paused: bool,
+
+	/// The extension that started the download, its capabilities are checked again before it is resumed.
+	#[serde(default)]
+	pub extension: Option<String>
+}
+
+/// Keeps a file on disk listing every unfinished download, so they can be picked up after a restart.
+pub(crate) struct DownloadQueue {
+	path: PathBuf,
+	items: Arc<RwLock<Vec<Download>>>,
+	lock: Mutex<()>
+}
+
+impl DownloadQueue {
+	pub fn new(path: PathBuf, items: Arc<RwLock<Vec<Download>>>) -> Self {
+		Self {
+			path,
+			items,
+			lock: Mutex::new(())
+		}
+	}
+
+	pub fn save(&self) {
+		let _lock = self.lock.lock();
+		let entries: Vec<QueueEntry> = self.items.read().iter().filter_map(|x| {
+			let status = x.status();
+			if status.is_done() {
+				return None;
+			}
+			Some(QueueEntry {
+				request: x.request().clone(),
+				paused: matches!(status, DownloadStatus::Paused),
+				extension: x.extension().map(str::to_string)
+			})
+		}).collect();
+
+		if let Err(err) = self.write(&entries) {
+			println!("failed to save download queue! {}", err);
+		}
+	}
+
+	pub fn load(&self) -> Vec<QueueEntry> {
+		let _lock = self.lock.lock();
+		match fs::read_to_string(&self.path) {
+			Ok(data) => serde_json::from_str(&data).unwrap_or_else(|err| {
+				println!("failed to read download queue! {}", err);
+				vec![]
+			}),
+			Err(_) => vec![]
+		}
+	}
+
+	fn write(&self, entries: &[QueueEntry]) -> std::io::Result<()> {
+		if let Some(parent) = self.path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		let temp_path = self.path.with_extension("tmp");
+		fs::write(&temp_path, serde_json::to_string(entries)?)?;
+		fs::rename(temp_path, &self.path)
+	}
+}
\ No newline at end of file
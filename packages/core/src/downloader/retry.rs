@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+/// How a download should be retried after failing, each attempt moves on to the next mirror.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+	/// Total number of attempts, including the first one.
+	pub max_attempts: u32,
+	pub initial_delay: Duration,
+	pub max_delay: Duration
+}
+
+impl RetryPolicy {
+	/// Never retry, failing on the first error.
+	pub fn none() -> Self {
+		Self {
+			max_attempts: 1,
+			..Default::default()
+		}
+	}
+
+	/// How long to wait before the attempt after `attempt`, doubling every time.
+	pub fn delay(&self, attempt: u32) -> Duration {
+		self.initial_delay
+			.saturating_mul(2u32.saturating_pow(attempt))
+			.min(self.max_delay)
+	}
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_attempts: 5,
+			initial_delay: Duration::from_millis(500),
+			max_delay: Duration::from_secs(30)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn doubles_delays_up_to_the_maximum() {
+		let policy = RetryPolicy {
+			max_attempts: 10,
+			initial_delay: Duration::from_millis(500),
+			max_delay: Duration::from_secs(3)
+		};
+		let delays: Vec<u128> = (0..5).map(|x| policy.delay(x).as_millis()).collect();
+		assert_eq!(delays, [500, 1000, 2000, 3000, 3000]);
+		assert_eq!(policy.delay(u32::MAX), Duration::from_secs(3));
+	}
+}
\ No newline at end of file
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use super::{ act_as, ExtensionPermissions, SettingDefinition };
+use crate::{ Icon, IconData };
+use crate::game::Game;
+use crate::instance::Instance;
+use crate::content::{ ContentFile, ContentPage, ContentProvider, ContentProviderItem, ContentProviderSearchSortType };
+
+/// A game that runs everything it is asked to do with the permissions of the extension that provided it.
+pub struct ActingGame {
+	permissions: Arc<ExtensionPermissions>,
+	game: Arc<Box<dyn Game>>
+}
+
+impl ActingGame {
+	pub fn new(permissions: Arc<ExtensionPermissions>, game: Arc<Box<dyn Game>>) -> Self {
+		Self { permissions, game }
+	}
+
+	fn act<T>(&self, callback: impl FnOnce(&dyn Game) -> T) -> T {
+		act_as(Some(self.permissions.clone()), || callback(self.game.as_ref().as_ref()))
+	}
+}
+
+impl Icon for ActingGame {
+	fn icon(&self) -> Option<IconData> {
+		self.act(|x| x.icon())
+	}
+}
+
+impl Game for ActingGame {
+	fn id(&self) -> &'static str {
+		self.game.id()
+	}
+
+	fn launch(&self, instance: &Instance) -> Option<std::process::Child> {
+		self.act(|x| x.launch(instance))
+	}
+
+	fn pre_load(&self) {
+		self.act(|x| x.pre_load())
+	}
+
+	fn creation_options(&self) -> Vec<SettingDefinition> {
+		self.act(|x| x.creation_options())
+	}
+
+	fn on_instance_created(&self, instance: &Instance) {
+		self.act(|x| x.on_instance_created(instance))
+	}
+
+	fn content_pages(&self) -> Vec<Box<dyn ContentPage>> {
+		self.act(|x| x.content_pages()).into_iter().map(|page| Box::new(ActingContentPage {
+			permissions: self.permissions.clone(),
+			page
+		}) as Box<dyn ContentPage>).collect()
+	}
+}
+
+/// A content page that reads its items with the permissions of the extension that provided it.
+pub struct ActingContentPage {
+	permissions: Arc<ExtensionPermissions>,
+	page: Box<dyn ContentPage>
+}
+
+impl ContentPage for ActingContentPage {
+	fn name(&self) -> &'static str {
+		self.page.name()
+	}
+
+	fn items(&self, instance: &Instance) -> Vec<ContentFile> {
+		act_as(Some(self.permissions.clone()), || self.page.items(instance))
+	}
+}
+
+/// A content provider that searches and installs with the permissions of the extension that provided it.
+pub struct ActingContentProvider {
+	permissions: Arc<ExtensionPermissions>,
+	provider: Box<dyn ContentProvider>
+}
+
+impl ActingContentProvider {
+	pub fn new(permissions: Arc<ExtensionPermissions>, provider: Box<dyn ContentProvider>) -> Self {
+		Self { permissions, provider }
+	}
+}
+
+impl ContentProvider for ActingContentProvider {
+	fn name(&self) -> &'static str {
+		self.provider.name()
+	}
+
+	fn search(&mut self, query: String, sort_type: ContentProviderSearchSortType, descending: bool) -> Vec<ContentProviderItem> {
+		act_as(Some(self.permissions.clone()), || self.provider.search(query, sort_type, descending))
+	}
+
+	fn install(&mut self, instance: &Instance, item_id: &str) {
+		act_as(Some(self.permissions.clone()), || self.provider.install(instance, item_id))
+	}
+
+	fn cache_duration(&self) -> Option<std::time::Duration> {
+		self.provider.cache_duration()
+	}
+
+	fn supported_types(&self) -> Vec<&'static str> {
+		self.provider.supported_types()
+	}
+}
+
+/// Makes a container render, and filter the instances it is shown for, with the permissions of the extension that provided it.
+#[cfg(feature = "ui")]
+pub fn acting_container(permissions: Arc<ExtensionPermissions>, mut container: crate::user_interface::Container) -> crate::user_interface::Container {
+	use crate::user_interface::ContainerKind;
+
+	let render = container.render;
+	let render_permissions = permissions.clone();
+	container.render = Box::new(move || act_as(Some(render_permissions.clone()), &render));
+	container.kind = match container.kind {
+		ContainerKind::InstancePage(Some(filter)) => ContainerKind::InstancePage(Some(Box::new(move |instance, game| {
+			act_as(Some(permissions.clone()), || filter(instance, game))
+		}))),
+		other => other
+	};
+	container
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::{ acting_extension, Capability };
+	use super::super::capability::tests::permissions;
+
+	struct CheckingGame;
+
+	impl Icon for CheckingGame {
+		fn icon(&self) -> Option<IconData> {
+			None
+		}
+	}
+
+	impl Game for CheckingGame {
+		fn id(&self) -> &'static str {
+			"game"
+		}
+
+		fn launch(&self, _instance: &Instance) -> Option<std::process::Child> {
+			None
+		}
+
+		fn pre_load(&self) {
+			let acting = acting_extension().expect("pre_load ran as HERO");
+			assert!(acting.check(&Capability::Process).is_err());
+		}
+
+		fn creation_options(&self) -> Vec<SettingDefinition> {
+			assert_eq!(acting_extension().map(|x| x.id.clone()), Some("test".into()));
+			vec![]
+		}
+	}
+
+	#[test]
+	fn games_run_with_their_extensions_permissions() {
+		let game = ActingGame::new(Arc::new(permissions(vec![], vec![])), Arc::new(Box::new(CheckingGame)));
+		game.pre_load();
+		game.creation_options();
+		assert!(acting_extension().is_none());
+	}
+}
\ No newline at end of file
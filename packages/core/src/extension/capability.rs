@@ -0,0 +1,440 @@
+use std::fmt;
+use std::sync::Arc;
+use std::cell::RefCell;
+use std::path::{ Path, PathBuf, Component };
+use parking_lot::RwLock;
+use serde::{ Serialize, Deserialize };
+
+use super::ExtensionManifest;
+
+#[cfg(feature = "core")]
+use std::collections::HashMap;
+
+/// Keeps track of which capabilities the user has granted to each extension, inside of the extensions directory.
+#[cfg(feature = "core")]
+pub const GRANTS_FILE: &str = "grants.json";
+
+/// Something an extension has to declare in its manifest, and the user has to grant, before core will do it on the extension's behalf.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+	/// Downloading from a host, `*.example.com` also covers every subdomain and `*` covers everything.
+	Network(String),
+
+	/// Reading and writing files below a directory, relative to HERO's data directory unless absolute.
+	Filesystem(PathBuf),
+
+	/// Starting other programs, including the game itself.
+	Process,
+
+	/// Seeing instances of games provided by other extensions.
+	OtherInstances
+}
+
+impl Capability {
+	/// Whether or not being granted this capability allows what `needed` asks for.
+	fn covers(&self, needed: &Capability, base: &Path) -> bool {
+		match (self, needed) {
+			(Self::Network(pattern), Self::Network(host)) => {
+				let host = host.to_ascii_lowercase();
+				let pattern = pattern.to_ascii_lowercase();
+				match pattern.strip_prefix("*.") {
+					Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+					None => pattern == "*" || pattern == host
+				}
+			},
+			(Self::Filesystem(root), Self::Filesystem(path)) => normalise(&base.join(path)).starts_with(normalise(&base.join(root))),
+			(Self::Process, Self::Process) | (Self::OtherInstances, Self::OtherInstances) => true,
+			_ => false
+		}
+	}
+}
+
+impl fmt::Display for Capability {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Network(host) => write!(f, "download from {}", host),
+			Self::Filesystem(path) => write!(f, "access files in {:?}", path),
+			Self::Process => write!(f, "start other programs"),
+			Self::OtherInstances => write!(f, "see instances of other extensions' games")
+		}
+	}
+}
+
+/// Lexically resolves `.` and `..`, so a path can't climb out of a root it appears to be inside of.
+fn normalise(path: &Path) -> PathBuf {
+	let mut result = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::ParentDir => {
+				result.pop();
+			},
+			Component::CurDir => {},
+			other => result.push(other)
+		}
+	}
+	result
+}
+
+#[derive(Clone, Debug)]
+pub struct CapabilityError {
+	pub extension: String,
+	pub capability: Capability
+}
+
+impl fmt::Display for CapabilityError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "extension {} is not allowed to {}", self.extension, self.capability)
+	}
+}
+
+impl std::error::Error for CapabilityError {}
+
+/// An extension tried to do something only HERO itself may, such as granting capabilities or changing settings.
+#[derive(Clone, Debug)]
+pub struct HostOnlyError {
+	pub extension: String,
+	pub action: &'static str
+}
+
+impl fmt::Display for HostOnlyError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "extension {} is not allowed to {}, only HERO is", self.extension, self.action)
+	}
+}
+
+impl std::error::Error for HostOnlyError {}
+
+impl From<HostOnlyError> for std::io::Error {
+	fn from(err: HostOnlyError) -> Self {
+		std::io::Error::new(std::io::ErrorKind::PermissionDenied, err)
+	}
+}
+
+/// What an extension asked for in its manifest and what the user has granted it.
+pub struct ExtensionPermissions {
+	pub id: String,
+
+	/// Games the extension provides, it can always see their instances.
+	pub games: Vec<String>,
+
+	/// Extensions whose services it may use.
+	pub dependencies: Vec<String>,
+	requested: Vec<Capability>,
+	granted: RwLock<Vec<Capability>>,
+	base: PathBuf
+}
+
+impl ExtensionPermissions {
+	pub fn new(manifest: &ExtensionManifest, granted: Vec<Capability>, base: impl Into<PathBuf>) -> Self {
+		Self {
+			id: manifest.id.clone(),
+			games: manifest.games.clone(),
+			dependencies: manifest.dependencies.clone(),
+			requested: manifest.capabilities.clone(),
+			granted: RwLock::new(granted),
+			base: base.into()
+		}
+	}
+
+	pub fn requested(&self) -> &[Capability] {
+		&self.requested
+	}
+
+	pub fn granted(&self) -> Vec<Capability> {
+		self.granted.read().clone()
+	}
+
+	/// Requested capabilities the user hasn't granted yet.
+	pub fn ungranted(&self) -> Vec<Capability> {
+		let granted = self.granted.read();
+		self.requested.iter().filter(|x| !granted.contains(x)).cloned().collect()
+	}
+
+	pub fn set_granted(&self, granted: Vec<Capability>) {
+		*self.granted.write() = granted;
+	}
+
+	/// Only capabilities that were both requested and granted count.
+	pub fn check(&self, needed: &Capability) -> Result<(), CapabilityError> {
+		let granted = self.granted.read();
+		if self.requested.iter().any(|x| granted.contains(x) && x.covers(needed, &self.base)) {
+			return Ok(());
+		}
+		Err(CapabilityError {
+			extension: self.id.clone(),
+			capability: needed.clone()
+		})
+	}
+
+	pub fn can_see_game(&self, game_id: &str) -> bool {
+		self.games.iter().any(|x| x == game_id) || self.check(&Capability::OtherInstances).is_ok()
+	}
+}
+
+thread_local! {
+	static ACTING: RefCell<Option<Arc<ExtensionPermissions>>> = RefCell::new(None);
+}
+
+/// Restores whoever was acting before, even if the callback panics.
+struct ActingGuard(Option<Arc<ExtensionPermissions>>);
+
+impl Drop for ActingGuard {
+	fn drop(&mut self) {
+		ACTING.with(|x| *x.borrow_mut() = self.0.take());
+	}
+}
+
+/// Runs code on behalf of an extension, so anything it asks core to do in the meantime is checked against its permissions.
+/// Code running outside of this is HERO itself, which may do anything.
+pub fn act_as<T>(permissions: Option<Arc<ExtensionPermissions>>, callback: impl FnOnce() -> T) -> T {
+	let _guard = ActingGuard(ACTING.with(|x| x.replace(permissions)));
+	callback()
+}
+
+/// The extension core is currently running code for, if any.
+pub fn acting_extension() -> Option<Arc<ExtensionPermissions>> {
+	ACTING.with(|x| x.borrow().clone())
+}
+
+/// Refuses whatever only HERO itself may do while an extension is acting.
+pub fn check_host(action: &'static str) -> Result<(), HostOnlyError> {
+	match acting_extension() {
+		Some(acting) => Err(HostOnlyError { extension: acting.id.clone(), action }),
+		None => Ok(())
+	}
+}
+
+fn act_as_dyn(permissions: Option<Arc<ExtensionPermissions>>, callback: &mut dyn FnMut()) {
+	act_as(permissions, callback)
+}
+
+fn check_acting(needed: &Capability) -> Result<(), CapabilityError> {
+	acting_extension().map_or(Ok(()), |x| x.check(needed))
+}
+
+fn can_see_game(game_id: &str) -> bool {
+	acting_extension().map_or(true, |x| x.can_see_game(game_id))
+}
+
+fn loaded_permissions(id: &str) -> Option<Arc<ExtensionPermissions>> {
+	#[cfg(feature = "core")]
+	return crate::state::State::get().extensions.read().permissions(id);
+
+	#[cfg(not(feature = "core"))]
+	{
+		let _ = id;
+		None
+	}
+}
+
+/// Checks capabilities against whichever extension is acting.
+///
+/// Native extensions carry their own copy of this crate, so rather than their copy looking at its own
+/// (always empty) record of who is acting, [`crate::state::State`] and [`crate::downloader::Downloader`]
+/// hold one of these pointing back into HERO.
+#[derive(Clone, Copy)]
+pub struct CapabilityGuard {
+	check: fn(&Capability) -> Result<(), CapabilityError>,
+	can_see_game: fn(&str) -> bool,
+	acting: fn() -> Option<Arc<ExtensionPermissions>>,
+	permissions: fn(&str) -> Option<Arc<ExtensionPermissions>>,
+	host: fn(&'static str) -> Result<(), HostOnlyError>,
+	act_as: fn(Option<Arc<ExtensionPermissions>>, &mut dyn FnMut())
+}
+
+impl CapabilityGuard {
+	pub fn new() -> Self {
+		Self {
+			check: check_acting,
+			can_see_game,
+			acting: acting_extension,
+			permissions: loaded_permissions,
+			host: check_host,
+			act_as: act_as_dyn
+		}
+	}
+
+	pub fn acting(&self) -> Option<Arc<ExtensionPermissions>> {
+		(self.acting)()
+	}
+
+	/// Permissions of a loaded extension, this locks the extensions so don't call it while holding a guard on them.
+	pub fn permissions(&self, id: &str) -> Option<Arc<ExtensionPermissions>> {
+		(self.permissions)(id)
+	}
+
+	pub fn check(&self, needed: &Capability) -> Result<(), CapabilityError> {
+		(self.check)(needed)
+	}
+
+	/// Whether or not the acting extension may see instances of the given game.
+	pub fn can_see_game(&self, game_id: &str) -> bool {
+		(self.can_see_game)(game_id)
+	}
+
+	/// Refuses whatever only HERO itself may do while an extension is acting.
+	pub fn check_host(&self, action: &'static str) -> Result<(), HostOnlyError> {
+		(self.host)(action)
+	}
+
+	/// Runs code on behalf of an extension, see [`act_as`].
+	pub fn act_as<T>(&self, permissions: Option<Arc<ExtensionPermissions>>, callback: impl FnOnce() -> T) -> T {
+		let mut callback = Some(callback);
+		let mut result = None;
+		(self.act_as)(permissions, &mut || result = callback.take().map(|x| x()));
+		result.expect("the callback was not run")
+	}
+}
+
+impl Default for CapabilityGuard {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The capabilities the user has granted to each extension.
+#[cfg(feature = "core")]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GrantStore {
+	#[serde(default)]
+	pub grants: HashMap<String, Vec<Capability>>,
+
+	#[serde(skip)]
+	path: Option<PathBuf>
+}
+
+#[cfg(feature = "core")]
+impl GrantStore {
+	pub fn load(path: PathBuf) -> Self {
+		let mut store: Self = std::fs::read_to_string(&path)
+			.ok()
+			.and_then(|x| serde_json::from_str(&x).ok())
+			.unwrap_or_default();
+		store.path = Some(path);
+		store
+	}
+
+	pub fn save(&self) -> std::io::Result<()> {
+		if let Some(path) = &self.path {
+			if let Some(parent) = path.parent() {
+				std::fs::create_dir_all(parent)?;
+			}
+			std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+		}
+		Ok(())
+	}
+
+	pub fn granted(&self, id: &str) -> Vec<Capability> {
+		self.grants.get(id).cloned().unwrap_or_default()
+	}
+
+	pub fn grant(&mut self, id: &str, capabilities: &[Capability]) {
+		let granted = self.grants.entry(id.to_string()).or_default();
+		for capability in capabilities {
+			if !granted.contains(capability) {
+				granted.push(capability.clone());
+			}
+		}
+	}
+
+	pub fn revoke(&mut self, id: &str) {
+		self.grants.remove(id);
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+
+	/// A guard looking loaded extensions up with `permissions` rather than in the global state.
+	#[cfg(feature = "core")]
+	pub(crate) fn guard(permissions: fn(&str) -> Option<Arc<ExtensionPermissions>>) -> CapabilityGuard {
+		CapabilityGuard { permissions, ..CapabilityGuard::new() }
+	}
+
+	pub(crate) fn permissions(capabilities: Vec<Capability>, granted: Vec<Capability>) -> ExtensionPermissions {
+		let manifest = ExtensionManifest {
+			id: "test".into(),
+			name: "Test".into(),
+			version: "1.0.0".into(),
+			authors: vec![],
+			api_version: super::super::API_VERSION,
+			platforms: vec![],
+			games: vec!["game".into()],
+			capabilities,
+			dependencies: vec![]
+		};
+		ExtensionPermissions::new(&manifest, granted, "/data")
+	}
+
+	fn filesystem(path: &str) -> Capability {
+		Capability::Filesystem(PathBuf::from(path))
+	}
+
+	#[test]
+	fn filesystem_covers_paths_below_its_root() {
+		let base = Path::new("/data");
+		assert!(filesystem("mods").covers(&filesystem("mods"), base));
+		assert!(filesystem("mods").covers(&filesystem("mods/a/b.jar"), base));
+		assert!(filesystem("mods").covers(&filesystem("/data/mods/b.jar"), base));
+		assert!(filesystem("/elsewhere").covers(&filesystem("/elsewhere/file"), base));
+	}
+
+	#[test]
+	fn filesystem_does_not_cover_siblings_or_parents() {
+		let base = Path::new("/data");
+		assert!(!filesystem("mods").covers(&filesystem("mods2/file"), base));
+		assert!(!filesystem("mods").covers(&filesystem("modsfile"), base));
+		assert!(!filesystem("mods").covers(&filesystem("."), base));
+		assert!(!filesystem("mods").covers(&filesystem("/data"), base));
+		assert!(!filesystem("mods").covers(&filesystem("mods/../secret"), base));
+		assert!(!filesystem("mods").covers(&filesystem("mods/a/../../secret"), base));
+		assert!(!filesystem("mods").covers(&filesystem("/etc/passwd"), base));
+	}
+
+	#[test]
+	fn network_patterns() {
+		let base = Path::new("/data");
+		let network = |x: &str| Capability::Network(x.into());
+		assert!(network("example.com").covers(&network("EXAMPLE.com"), base));
+		assert!(!network("example.com").covers(&network("cdn.example.com"), base));
+		assert!(network("*.example.com").covers(&network("example.com"), base));
+		assert!(network("*.example.com").covers(&network("cdn.example.com"), base));
+		assert!(!network("*.example.com").covers(&network("badexample.com"), base));
+		assert!(!network("*.example.com").covers(&network("example.com.evil.net"), base));
+		assert!(network("*").covers(&network("anything.net"), base));
+		assert!(!network("example.com").covers(&Capability::Process, base));
+	}
+
+	#[test]
+	fn only_requested_and_granted_capabilities_count() {
+		let requested_only = permissions(vec![Capability::Process], vec![]);
+		assert!(requested_only.check(&Capability::Process).is_err());
+
+		let granted_only = permissions(vec![], vec![Capability::Process]);
+		assert!(granted_only.check(&Capability::Process).is_err());
+
+		let both = permissions(vec![Capability::Process], vec![Capability::Process]);
+		assert!(both.check(&Capability::Process).is_ok());
+		assert!(both.check(&Capability::OtherInstances).is_err());
+		assert!(both.can_see_game("game"));
+		assert!(!both.can_see_game("other"));
+	}
+
+	#[test]
+	fn act_as_restores_whoever_was_acting() {
+		let outer = Arc::new(permissions(vec![], vec![]));
+		assert!(acting_extension().is_none());
+		act_as(Some(outer.clone()), || {
+			assert!(check_acting(&Capability::Process).is_err());
+			act_as(None, || assert!(check_acting(&Capability::Process).is_ok()));
+			assert!(acting_extension().is_some_and(|x| Arc::ptr_eq(&x, &outer)));
+
+			let result = std::panic::catch_unwind(|| act_as(None, || panic!("oops")));
+			assert!(result.is_err());
+			assert!(acting_extension().is_some_and(|x| Arc::ptr_eq(&x, &outer)));
+		});
+		assert!(acting_extension().is_none());
+	}
+}
\ No newline at end of file
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use super::{ Capability, ExtensionError, ExtensionManifest, SignatureError, decode_hex, install_extension };
+use crate::state::State;
+use crate::downloader::{ Checksum, DownloadRequest };
+
+/// A build of an extension for this platform that can be downloaded and installed.
+#[derive(Clone, Debug)]
+pub struct ExtensionRelease {
+	pub manifest: ExtensionManifest,
+	pub url: String,
+	pub size: Option<u64>,
+	pub checksum: Checksum,
+
+	/// Hexadecimal ed25519 signature of the library.
+	pub signature: Option<String>
+}
+
+impl ExtensionRelease {
+	/// Where the library or module is downloaded to before being moved into the extensions directory,
+	/// which names it by what it turned out to be, see [`super::extension_file_name`].
+	pub fn download_path(&self, state: &State) -> PathBuf {
+		state.path.join("downloads").join(format!("{}.download", self.manifest.id))
+	}
+}
+
+/// Downloads a release, verifies it against its checksum and manifest, then installs and loads it,
+/// replacing any version that is already installed. Blocks until everything is done.
+/// `capabilities` are only granted once the release has been downloaded, and taken back if it fails to load, see [`install_extension`].
+pub fn install_release(release: &ExtensionRelease, capabilities: &[Capability]) -> Result<(), ExtensionError> {
+	release.manifest.validate()?;
+	let signature = match &release.signature {
+		Some(signature) => Some(decode_hex(signature).ok_or(SignatureError::Malformed)?),
+		None => None
+	};
+
+	let state = State::get();
+	state.capabilities.check_host("install extensions").map_err(std::io::Error::from)?;
+	let path = release.download_path(&state);
+	let mut request = DownloadRequest::new(&release.url, &path);
+	request.size = release.size;
+	request.checksum = Some(release.checksum.clone());
+
+	state.downloader.download_file_over_http(request).wait_blocking()?;
+	install_extension(&state, &path, &release.manifest, signature.as_deref(), capabilities)
+}
\ No newline at end of file
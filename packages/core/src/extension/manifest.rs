@@ -0,0 +1,124 @@
+use std::fmt;
+#[cfg(feature = "core")]
+use std::path::Path;
+use serde::{ Serialize, Deserialize };
+
+use super::{ API_VERSION, Capability };
+
+/// File extension of the manifest that sits next to every extension library,
+/// `libexample.so` is described by `libexample.json`.
+pub const MANIFEST_EXTENSION: &str = "json";
+
+/// Platform listed by extensions that are compiled to WebAssembly.
+pub const WASM_PLATFORM: &str = "wasm";
+
+/// Names of the files HERO keeps in the extensions directory, which the manifest of an extension with one of these identifiers would overwrite.
+pub(crate) const RESERVED_IDS: [&str; 3] = ["disabled", "grants", "trust"];
+
+/// Describes an extension without having to load it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExtensionManifest {
+	/// Must match [`super::Extension::id`].
+	pub id: String,
+	pub name: String,
+	pub version: String,
+
+	#[serde(default)]
+	pub authors: Vec<String>,
+
+	/// The [`API_VERSION`] the extension was written for.
+	pub api_version: u32,
+
+	/// Operating systems the extension can run on, either alone (`linux`) or with an architecture (`linux-x86_64`).
+	/// WebAssembly extensions run everywhere and list `wasm` instead.
+	pub platforms: Vec<String>,
+
+	/// Identifiers of the games this extension provides.
+	#[serde(default)]
+	pub games: Vec<String>,
+
+	/// What the extension needs to be allowed to do, the user is asked to grant these when installing it.
+	#[serde(default)]
+	pub capabilities: Vec<Capability>,
+
+	/// Identifiers of extensions that have to be loaded first, whose services this one uses.
+	#[serde(default)]
+	pub dependencies: Vec<String>
+}
+
+impl ExtensionManifest {
+	#[cfg(feature = "core")]
+	pub fn read(path: &Path) -> Result<Self, ManifestError> {
+		let data = std::fs::read_to_string(path).map_err(|err| ManifestError::Read(err.to_string()))?;
+		let manifest: Self = serde_json::from_str(&data).map_err(|err| ManifestError::Parse(err.to_string()))?;
+		manifest.validate()?;
+
+		Ok(manifest)
+	}
+
+	/// Checks that the manifest is complete and describes an extension this build of HERO can load.
+	pub fn validate(&self) -> Result<(), ManifestError> {
+		if !is_valid_id(&self.id) || RESERVED_IDS.contains(&self.id.as_str()) {
+			return Err(ManifestError::InvalidId(self.id.clone()));
+		}
+		if self.name.trim().is_empty() {
+			return Err(ManifestError::MissingField("name"));
+		}
+		if self.version.trim().is_empty() {
+			return Err(ManifestError::MissingField("version"));
+		}
+		if self.api_version != API_VERSION {
+			return Err(ManifestError::ApiVersion { expected: API_VERSION, found: self.api_version });
+		}
+		if !self.supports_current_platform() {
+			return Err(ManifestError::UnsupportedPlatform(self.platforms.clone()));
+		}
+		if let Some(game) = self.games.iter().find(|x| !is_valid_id(x)) {
+			return Err(ManifestError::InvalidId(game.clone()));
+		}
+		if let Some(dependency) = self.dependencies.iter().find(|x| !is_valid_id(x) || **x == self.id) {
+			return Err(ManifestError::InvalidId(dependency.clone()));
+		}
+
+		Ok(())
+	}
+
+	pub fn supports_current_platform(&self) -> bool {
+		let os = std::env::consts::OS;
+		let full = format!("{}-{}", os, std::env::consts::ARCH);
+		self.platforms.iter().any(|x| x == os || *x == full || x == WASM_PLATFORM)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum ManifestError {
+	/// There is no manifest next to the library.
+	Missing,
+	Read(String),
+	Parse(String),
+	MissingField(&'static str),
+	InvalidId(String),
+	ApiVersion { expected: u32, found: u32 },
+	UnsupportedPlatform(Vec<String>)
+}
+
+impl fmt::Display for ManifestError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Missing => write!(f, "no manifest was found"),
+			Self::Read(err) => write!(f, "manifest could not be read: {}", err),
+			Self::Parse(err) => write!(f, "manifest is invalid: {}", err),
+			Self::MissingField(field) => write!(f, "manifest is missing a {}", field),
+			Self::InvalidId(id) => write!(f, "{:?} is not a valid identifier", id),
+			Self::ApiVersion { expected, found } => write!(f, "requires API version {}, expected {}", found, expected),
+			Self::UnsupportedPlatform(platforms) => write!(f, "only supports {}, not {}-{}", platforms.join(", "), std::env::consts::OS, std::env::consts::ARCH)
+		}
+	}
+}
+
+impl std::error::Error for ManifestError {}
+
+/// Identifiers may only contain lowercase letters, digits, dashes, underscores and dots.
+fn is_valid_id(id: &str) -> bool {
+	!id.is_empty() && id.chars().all(|x| x.is_ascii_lowercase() || x.is_ascii_digit() || matches!(x, '-' | '_' | '.'))
+}
\ No newline at end of file
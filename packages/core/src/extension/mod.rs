@@ -0,0 +1,1612 @@
+use std::fmt;
+use std::sync::Arc;
+use std::ffi::{ c_char, c_void };
+use std::panic::AssertUnwindSafe;
+
+#[cfg(feature = "core")]
+use std::ffi::CStr;
+#[cfg(feature = "core")]
+use std::path::{ Path, PathBuf };
+use uuid::Uuid;
+use crate::Icon;
+use crate::game::Game;
+use crate::instance::Instance;
+use crate::downloader::{ Downloader, DownloadError };
+
+use crate::state::State;
+
+#[cfg(feature = "ui")]
+use crate::user_interface::Container;
+
+use crate::content::ContentProvider;
+
+mod manifest;
+pub use manifest::*;
+
+mod service;
+pub use service::*;
+
+pub(crate) mod capability;
+pub use capability::*;
+
+mod acting;
+pub use acting::*;
+
+mod settings;
+pub use settings::*;
+
+#[cfg(feature = "core")]
+mod install;
+#[cfg(feature = "core")]
+pub use install::*;
+
+#[cfg(feature = "core")]
+mod signature;
+#[cfg(feature = "core")]
+pub use signature::*;
+
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
+
+pub trait Extension: Icon + Sync + Send {
+	/// The unique identifier for your extension.
+	fn id(&self) -> &'static str;
+
+	/// Runs after the extension has been fully loaded & registered.
+	fn setup(&self) {}
+
+	/// Runs when the user picks a game, before its instances are loaded.
+	fn on_game_selected(&self, _game_id: &str) {}
+
+	/// Runs once the instances of the selected game have been loaded.
+	fn on_instances_loaded(&self, _game_id: &str, _instances: &[Uuid]) {}
+
+	fn before_launch(&self, _instance: &Instance) {}
+
+	/// Runs once the game has been told to launch an instance, which may still be starting.
+	fn after_launch(&self, _instance: &Instance) {}
+
+	/// Runs when the process returned by [`Game::launch`] exits, with its exit code if it has one.
+	fn on_process_exited(&self, _instance: &Instance, _exit_code: Option<i32>) {}
+
+	fn on_content_installed(&self, _instance: &Instance, _item_id: &str) {}
+
+	/// Runs once before HERO closes, while every extension is still loaded.
+	fn on_shutdown(&self) {}
+
+	/// Options shown on the extension's settings page, read them back with [`State::extension_setting`].
+	fn settings(&self) -> Vec<SettingDefinition> {
+		vec![]
+	}
+
+	/// Runs after the user changes one of the extension's settings.
+	fn on_setting_changed(&self, _key: &str, _value: &SettingValue) {}
+
+	fn games(&self) -> Option<Vec<Arc<Box<dyn Game>>>> {
+		None
+	}
+
+	fn downloader(&self) -> Option<Arc<Downloader>> {
+		None
+	}
+
+	fn localisation(&self) -> Vec<(&'static str, Vec<(&'static str, &'static str)>)> {
+		vec![]
+	}
+
+	#[cfg(feature = "ui")]
+	fn ui_containers(&self) -> Vec<Container> {
+		vec![]
+	}
+
+	fn content_providers(&self) -> Vec<Box<dyn ContentProvider>> {
+		vec![]
+	}
+}
+
+/// Version of the interface between HERO and its extensions,
+/// bumped whenever [`Extension`] or anything it exposes changes.
+pub const API_VERSION: u32 = 5;
+
+/// The compiler that built this copy of `hero-core`, extensions must be built with the same one.
+pub const RUSTC_VERSION: &str = concat!(env!("HERO_RUSTC_VERSION"), "\0");
+pub const CORE_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// The platform this copy of `hero-core` was built for, such as `x86_64-unknown-linux-gnu`.
+pub const TARGET: &str = concat!(env!("HERO_TARGET"), "\0");
+
+/// Every feature this copy of `hero-core` was built with, separated by commas.
+pub const FEATURES: &str = concat!(env!("HERO_FEATURES"), "\0");
+
+/// A hash of the features, flags and every source file this copy of `hero-core` was built from,
+/// as any of them can change the layout of the types shared with native extensions.
+pub const BUILD_FINGERPRINT: &str = concat!(env!("HERO_BUILD_FINGERPRINT"), "\0");
+
+pub const API_VERSION_SYMBOL: &[u8] = b"HERO_API_VERSION\0";
+pub const DECLARATION_SYMBOL: &[u8] = b"HERO_EXTENSION\0";
+
+/// Builds the extension, receiving a `*const State` and returning a `*mut Box<dyn Extension>`,
+/// or null if it panicked, in which case the message is written to the given `*mut Option<String>`.
+pub type BuildExtension = unsafe extern "C" fn(*const c_void, *mut c_void) -> *mut c_void;
+
+/// Runs a `*mut &mut dyn FnMut()` inside of the extension, so that panics are caught by the standard library that raised them.
+/// Returns false if it panicked, writing the message to the given `*mut Option<String>`.
+pub type CatchUnwind = unsafe extern "C" fn(*mut c_void, *mut c_void) -> bool;
+
+/// Exported by every extension library, describing how it was built and how to create it.
+/// Use [`crate::declare_extension`] rather than creating this yourself.
+///
+/// Only this declaration is C-ABI, everything after [`ExtensionDeclaration::build`] passes Rust types such as
+/// `&State` and `Box<dyn Extension>` across the library boundary, and those have no stable layout.
+/// There is no C-ABI vtable for [`Extension`] yet, so native extensions are only supported when built by the same compiler,
+/// for the same target, against the same `hero-core` source with the same features and flags,
+/// all of which [`check_abi`] refuses before calling anything, see [`BUILD_FINGERPRINT`].
+/// Extensions that have to work with any build of HERO should be WebAssembly modules instead, whose interface is stable.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ExtensionDeclaration {
+	pub api_version: u32,
+	pub rustc_version: *const c_char,
+	pub core_version: *const c_char,
+	pub target: *const c_char,
+	pub features: *const c_char,
+	pub fingerprint: *const c_char,
+	pub build: BuildExtension,
+	pub catch_unwind: CatchUnwind
+}
+
+unsafe impl Send for ExtensionDeclaration {}
+unsafe impl Sync for ExtensionDeclaration {}
+
+#[cfg(feature = "core")]
+impl ExtensionDeclaration {
+	pub fn build(&self, state: &State) -> Result<Box<dyn Extension>, ExtensionError> {
+		let mut panic: Option<String> = None;
+		let extension = unsafe { (self.build)(state as *const State as *const c_void, &mut panic as *mut _ as *mut c_void) };
+		if extension.is_null() {
+			return Err(ExtensionError::Panicked { entry_point: "build", message: panic.unwrap_or_default() });
+		}
+
+		Ok(unsafe { *Box::from_raw(extension as *mut Box<dyn Extension>) })
+	}
+
+	/// Runs code belonging to the extension, turning a panic inside of it into an error.
+	pub fn call<T>(&self, entry_point: &'static str, callback: impl FnOnce() -> T) -> Result<T, ExtensionError> {
+		let mut callback = Some(callback);
+		let mut result = None;
+		let mut callback = || result = callback.take().map(|x| x());
+		let mut callback: &mut dyn FnMut() = &mut callback;
+
+		let mut panic: Option<String> = None;
+		unsafe { (self.catch_unwind)(&mut callback as *mut &mut dyn FnMut() as *mut c_void, &mut panic as *mut _ as *mut c_void) };
+		result.ok_or_else(|| ExtensionError::Panicked { entry_point, message: panic.unwrap_or_default() })
+	}
+}
+
+#[doc(hidden)]
+pub unsafe fn build_extension(build: fn(&State) -> Box<dyn Extension>, state: *const c_void, panic: *mut c_void) -> *mut c_void {
+	let mut extension = None;
+	let mut callback = || extension = Some(build(&*(state as *const State)));
+	let mut callback: &mut dyn FnMut() = &mut callback;
+	catch_unwind(&mut callback as *mut &mut dyn FnMut() as *mut c_void, panic);
+
+	extension.map_or(std::ptr::null_mut(), |x| Box::into_raw(Box::new(x)) as *mut c_void)
+}
+
+#[doc(hidden)]
+pub unsafe extern "C" fn catch_unwind(callback: *mut c_void, panic: *mut c_void) -> bool {
+	let callback = &mut *(callback as *mut &mut dyn FnMut());
+	match std::panic::catch_unwind(AssertUnwindSafe(callback)) {
+		Ok(_) => true,
+		Err(payload) => {
+			let message = payload.downcast_ref::<&str>()
+				.map(|x| x.to_string())
+				.or_else(|| payload.downcast_ref::<String>().cloned())
+				.unwrap_or_else(|| "unknown panic".into());
+			*(panic as *mut Option<String>) = Some(message);
+			false
+		}
+	}
+}
+
+/// Exports an extension from a library, given a function that takes `&State` and returns `Box<dyn Extension>`.
+#[macro_export]
+macro_rules! declare_extension {
+	($build: path) => {
+		#[no_mangle]
+		pub static HERO_API_VERSION: u32 = $crate::extension::API_VERSION;
+
+		#[no_mangle]
+		pub static HERO_EXTENSION: $crate::extension::ExtensionDeclaration = $crate::extension::ExtensionDeclaration {
+			api_version: $crate::extension::API_VERSION,
+			rustc_version: $crate::extension::RUSTC_VERSION.as_ptr() as *const _,
+			core_version: $crate::extension::CORE_VERSION.as_ptr() as *const _,
+			target: $crate::extension::TARGET.as_ptr() as *const _,
+			features: $crate::extension::FEATURES.as_ptr() as *const _,
+			fingerprint: $crate::extension::BUILD_FINGERPRINT.as_ptr() as *const _,
+			build: {
+				unsafe extern "C" fn __hero_build_extension(state: *const ::std::ffi::c_void, panic: *mut ::std::ffi::c_void) -> *mut ::std::ffi::c_void {
+					$crate::extension::build_extension($build, state, panic)
+				}
+				__hero_build_extension
+			},
+			catch_unwind: $crate::extension::catch_unwind
+		};
+	};
+}
+
+/// Why an extension library was refused before anything inside it was called.
+#[derive(Clone, Debug)]
+pub enum AbiError {
+	/// The library doesn't export an API version, it is either not an extension or was built for an older HERO.
+	MissingApiVersion,
+	ApiVersion { expected: u32, found: u32 },
+	MissingDeclaration,
+	Toolchain { expected: String, found: String },
+	CoreVersion { expected: String, found: String },
+	Target { expected: String, found: String },
+	Features { expected: String, found: String },
+
+	/// Built from different `hero-core` source or with different flags, see [`BUILD_FINGERPRINT`].
+	Fingerprint { expected: String, found: String }
+}
+
+impl fmt::Display for AbiError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::MissingApiVersion => write!(f, "library does not export an API version"),
+			Self::ApiVersion { expected, found } => write!(f, "built for API version {}, expected {}", found, expected),
+			Self::MissingDeclaration => write!(f, "library does not declare an extension"),
+			Self::Toolchain { expected, found } => write!(f, "built with {}, expected {}", found, expected),
+			Self::CoreVersion { expected, found } => write!(f, "built against hero-core {}, expected {}", found, expected),
+			Self::Target { expected, found } => write!(f, "built for {}, expected {}", found, expected),
+			Self::Features { expected, found } => write!(f, "built with hero-core features [{}], expected [{}]", found, expected),
+			Self::Fingerprint { expected, found } => write!(f, "built from a different hero-core (build {}, expected {})", found, expected)
+		}
+	}
+}
+
+impl std::error::Error for AbiError {}
+
+#[derive(Clone, Debug)]
+pub enum ExtensionError {
+	/// The library itself could not be opened.
+	Library(String),
+	Manifest(ManifestError),
+	Abi(AbiError),
+
+	/// Another extension with the same identifier has already been loaded.
+	Duplicate(String),
+	IdMismatch { expected: String, found: String },
+	Panicked { entry_point: &'static str, message: String },
+
+	/// The extension provides a game its manifest doesn't list.
+	UndeclaredGame(String),
+
+	/// An extension this one depends on isn't installed, or failed to load.
+	MissingDependency(String),
+
+	/// These extensions depend on each other, so none of them can be loaded first.
+	DependencyCycle(Vec<String>),
+
+	#[cfg(feature = "core")]
+	Signature(SignatureError),
+
+	/// A WebAssembly module could not be loaded, or trapped.
+	Wasm(String),
+	Download(DownloadError),
+	Io(Arc<std::io::Error>)
+}
+
+impl fmt::Display for ExtensionError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Library(err) => write!(f, "library could not be opened: {}", err),
+			Self::Manifest(err) => err.fmt(f),
+			Self::Abi(err) => err.fmt(f),
+			Self::Duplicate(id) => write!(f, "{} is already loaded", id),
+			Self::IdMismatch { expected, found } => write!(f, "identifies itself as {}, expected {}", found, expected),
+			Self::Panicked { entry_point, message } => write!(f, "panicked in {}: {}", entry_point, message),
+			Self::UndeclaredGame(id) => write!(f, "provides {}, which is not listed in its manifest", id),
+			Self::MissingDependency(id) => write!(f, "depends on {}, which is not loaded", id),
+			Self::DependencyCycle(ids) => write!(f, "dependency cycle between {}", ids.join(", ")),
+
+			#[cfg(feature = "core")]
+			Self::Signature(err) => err.fmt(f),
+			Self::Wasm(err) => write!(f, "wasm error: {}", err),
+			Self::Download(err) => write!(f, "download failed: {}", err),
+			Self::Io(err) => write!(f, "io error: {}", err)
+		}
+	}
+}
+
+impl std::error::Error for ExtensionError {}
+
+impl From<ManifestError> for ExtensionError {
+	fn from(value: ManifestError) -> Self {
+		Self::Manifest(value)
+	}
+}
+
+impl From<AbiError> for ExtensionError {
+	fn from(value: AbiError) -> Self {
+		Self::Abi(value)
+	}
+}
+
+#[cfg(feature = "core")]
+impl From<SignatureError> for ExtensionError {
+	fn from(value: SignatureError) -> Self {
+		Self::Signature(value)
+	}
+}
+
+#[cfg(feature = "wasm")]
+impl From<WasmError> for ExtensionError {
+	fn from(value: WasmError) -> Self {
+		Self::Wasm(value.0)
+	}
+}
+
+impl From<DownloadError> for ExtensionError {
+	fn from(value: DownloadError) -> Self {
+		Self::Download(value)
+	}
+}
+
+impl From<std::io::Error> for ExtensionError {
+	fn from(value: std::io::Error) -> Self {
+		Self::Io(Arc::new(value))
+	}
+}
+
+/// An extension that could not be loaded, and why.
+#[cfg(feature = "core")]
+#[derive(Clone, Debug)]
+pub struct FailedExtension {
+	pub path: PathBuf,
+	pub error: ExtensionError
+}
+
+#[cfg(feature = "core")]
+impl FailedExtension {
+	pub fn file_name(&self) -> String {
+		self.path.file_name().map(|x| x.to_string_lossy().into_owned()).unwrap_or_default()
+	}
+}
+
+/// Checks that a library was built for this exact version of HERO, only reading its exported statics.
+/// This is what keeps the Rust types shared with native extensions sound, see [`ExtensionDeclaration`].
+#[cfg(feature = "core")]
+pub fn check_abi(library: &libloading::Library) -> Result<&ExtensionDeclaration, AbiError> {
+	let api_version: libloading::Symbol<*const u32> = unsafe { library.get(API_VERSION_SYMBOL) }
+		.map_err(|_| AbiError::MissingApiVersion)?;
+	let api_version = unsafe { **api_version };
+	if api_version != API_VERSION {
+		return Err(AbiError::ApiVersion { expected: API_VERSION, found: api_version });
+	}
+
+	let declaration: libloading::Symbol<*const ExtensionDeclaration> = unsafe { library.get(DECLARATION_SYMBOL) }
+		.map_err(|_| AbiError::MissingDeclaration)?;
+	let declaration = unsafe { &**declaration };
+
+	let read = |value: *const c_char| if value.is_null() {
+		String::new()
+	} else {
+		unsafe { CStr::from_ptr(value) }.to_string_lossy().into_owned()
+	};
+	let expected = RUSTC_VERSION.trim_end_matches('\0');
+	let found = read(declaration.rustc_version);
+	if found != expected {
+		return Err(AbiError::Toolchain { expected: expected.into(), found });
+	}
+
+	let expected = CORE_VERSION.trim_end_matches('\0');
+	let found = read(declaration.core_version);
+	if found != expected {
+		return Err(AbiError::CoreVersion { expected: expected.into(), found });
+	}
+
+	let expected = TARGET.trim_end_matches('\0');
+	let found = read(declaration.target);
+	if found != expected {
+		return Err(AbiError::Target { expected: expected.into(), found });
+	}
+
+	let expected = FEATURES.trim_end_matches('\0');
+	let found = read(declaration.features);
+	if found != expected {
+		return Err(AbiError::Features { expected: expected.into(), found });
+	}
+
+	let expected = BUILD_FINGERPRINT.trim_end_matches('\0');
+	let found = read(declaration.fingerprint);
+	if found != expected {
+		return Err(AbiError::Fingerprint { expected: expected.into(), found });
+	}
+
+	Ok(declaration)
+}
+
+/// File extensions of dynamic libraries on every platform we support.
+#[cfg(feature = "core")]
+const LIBRARY_EXTENSIONS: [&str; 3] = ["dll", "dylib", "so"];
+
+/// File extension of WebAssembly extensions, which run on every platform.
+#[cfg(feature = "core")]
+const WASM_EXTENSION: &str = "wasm";
+
+/// The first bytes of every WebAssembly module.
+#[cfg(feature = "core")]
+const WASM_MAGIC: [u8; 4] = *b"\0asm";
+
+/// The file name an extension's library is expected to have on this platform,
+/// e.g. `libexample.so` on Linux, `libexample.dylib` on macOS and `example.dll` on Windows.
+#[cfg(feature = "core")]
+pub fn library_file_name(name: &str) -> String {
+	format!("{}{}.{}", std::env::consts::DLL_PREFIX, name, std::env::consts::DLL_EXTENSION)
+}
+
+/// Whether or not a file is a WebAssembly module rather than a native library, going by its contents.
+#[cfg(feature = "core")]
+pub fn is_wasm_module(path: &Path) -> std::io::Result<bool> {
+	use std::io::Read;
+
+	let mut magic = [0; 4];
+	Ok(std::fs::File::open(path)?.read_exact(&mut magic).is_ok() && magic == WASM_MAGIC)
+}
+
+/// The file name an extension is installed as, `example.wasm` for WebAssembly modules and [`library_file_name`] for everything else.
+#[cfg(feature = "core")]
+pub fn extension_file_name(id: &str, wasm: bool) -> String {
+	if wasm {
+		format!("{}.{}", id, WASM_EXTENSION)
+	} else {
+		library_file_name(id)
+	}
+}
+
+/// Lists the identifiers of disabled extensions, inside of the extensions directory.
+#[cfg(feature = "core")]
+const DISABLED_FILE: &str = "disabled.json";
+
+/// Holds the private copies native libraries are loaded from, inside of the extensions directory.
+#[cfg(feature = "core")]
+const LOADED_DIRECTORY: &str = "loaded";
+
+/// Holds extensions being installed until they have been verified, inside of the extensions directory.
+#[cfg(feature = "core")]
+const STAGING_DIRECTORY: &str = "staged";
+
+pub struct Extensions {
+	pub items: Vec<Arc<dyn Extension>>,
+
+	#[cfg(feature = "core")]
+	pub failed: Vec<FailedExtension>,
+
+	#[cfg(feature = "core")]
+	pub disabled: Vec<DisabledExtension>,
+
+	#[cfg(feature = "core")]
+	pub trust: TrustStore,
+
+	#[cfg(feature = "core")]
+	pub grants: GrantStore,
+
+	#[cfg(feature = "core")]
+	path: Option<PathBuf>,
+
+	#[cfg(feature = "core")]
+	libraries: Vec<Arc<ExtensionLibrary>>,
+
+	/// Libraries that have been opened but not yet built by [`load_pending`].
+	#[cfg(feature = "core")]
+	pending: Vec<ExtensionLibrary>,
+
+	/// Identifiers of the libraries [`load_pending`] is building, which are in none of the lists above until it is done.
+	#[cfg(feature = "core")]
+	building: Vec<String>,
+
+	#[cfg(feature = "core")]
+	shut_down: bool
+}
+
+/// An installed extension that the user has turned off, its library is left unopened.
+#[cfg(feature = "core")]
+#[derive(Clone, Debug)]
+pub struct DisabledExtension {
+	pub path: PathBuf,
+	pub manifest: ExtensionManifest
+}
+
+#[cfg(feature = "core")]
+struct ExtensionLibrary {
+	path: PathBuf,
+	manifest: ExtensionManifest,
+	backend: ExtensionBackend,
+	permissions: Arc<ExtensionPermissions>,
+
+	/// Localisation keys and container identifiers the extension added, so they can be removed when it is unloaded.
+	localisation: Vec<(String, Vec<String>)>,
+
+	#[cfg(feature = "ui")]
+	ui_containers: Vec<String>
+}
+
+#[cfg(feature = "core")]
+impl ExtensionLibrary {
+	/// Runs code belonging to the extension with its permissions.
+	fn call<T>(&self, entry_point: &'static str, callback: impl FnOnce() -> T) -> Result<T, ExtensionError> {
+		act_as(Some(self.permissions.clone()), || self.backend.call(entry_point, callback))
+	}
+}
+
+/// Loaded extensions taken out of [`Extensions`] by [`Extensions::hooks`], so that their hooks run without holding its lock
+/// and are free to use the rest of the state, even to load or unload extensions.
+#[cfg(feature = "core")]
+#[derive(Clone)]
+pub struct ExtensionHooks {
+	extensions: Vec<HookedExtension>
+}
+
+#[cfg(feature = "core")]
+#[derive(Clone)]
+struct HookedExtension {
+	extension: Arc<dyn Extension>,
+
+	/// The library the extension was loaded from, if it wasn't registered directly.
+	library: Option<Arc<ExtensionLibrary>>
+}
+
+#[cfg(feature = "core")]
+impl ExtensionHooks {
+	/// Calls a hook on every extension with its permissions, a panic in one is logged and doesn't stop the others.
+	pub fn dispatch(&self, hook: &'static str, callback: impl Fn(&dyn Extension)) {
+		for HookedExtension { extension, library } in &self.extensions {
+			if let Err(err) = call_extension(library.as_deref(), hook, || callback(extension.as_ref())) {
+				println!("extension {} failed in {}! {}", extension.id(), hook, err);
+			}
+		}
+	}
+
+	/// Calls a hook on a single extension, returning false if it isn't loaded.
+	pub fn dispatch_to(&self, id: &str, hook: &'static str, callback: impl FnOnce(&dyn Extension)) -> bool {
+		let Some(HookedExtension { extension, library }) = self.extensions.iter().find(|x| x.extension.id() == id) else {
+			return false;
+		};
+		if let Err(err) = call_extension(library.as_deref(), hook, || callback(extension.as_ref())) {
+			println!("extension {} failed in {}! {}", id, hook, err);
+		}
+		true
+	}
+
+	/// Games of every extension. Those provided by a library run with its permissions, see [`ActingGame`],
+	/// and any its manifest doesn't list are left out.
+	pub fn games(&self) -> Vec<Arc<Box<dyn Game>>> {
+		let mut games = vec![];
+		for HookedExtension { extension, library } in &self.extensions {
+			let Some(library) = library else {
+				games.extend(extension.games().unwrap_or_default());
+				continue;
+			};
+			match library.call("games", || extension.games().unwrap_or_default()) {
+				Ok(provided) => games.extend(provided.into_iter()
+					.filter(|x| library.manifest.games.iter().any(|id| id == x.id()))
+					.map(|x| Arc::new(Box::new(ActingGame::new(library.permissions.clone(), x)) as Box<dyn Game>))),
+				Err(err) => println!("extension {} failed in games! {}", extension.id(), err)
+			}
+		}
+		games
+	}
+
+	/// Content providers of every extension, those provided by a library run with its permissions, see [`ActingContentProvider`].
+	pub fn content_providers(&self) -> Vec<Box<dyn ContentProvider>> {
+		let mut providers = vec![];
+		for HookedExtension { extension, library } in &self.extensions {
+			let Some(library) = library else {
+				providers.extend(extension.content_providers());
+				continue;
+			};
+			match library.call("content_providers", || extension.content_providers()) {
+				Ok(provided) => providers.extend(provided.into_iter()
+					.map(|x| Box::new(ActingContentProvider::new(library.permissions.clone(), x)) as Box<dyn ContentProvider>)),
+				Err(err) => println!("extension {} failed in content_providers! {}", extension.id(), err)
+			}
+		}
+		providers
+	}
+}
+
+/// Runs code belonging to an extension, with the permissions of its library if it was loaded from one.
+#[cfg(feature = "core")]
+fn call_extension<T>(library: Option<&ExtensionLibrary>, entry_point: &'static str, callback: impl FnOnce() -> T) -> Result<T, ExtensionError> {
+	match library {
+		Some(library) => library.call(entry_point, callback),
+		None => Ok(callback())
+	}
+}
+
+#[cfg(feature = "core")]
+enum ExtensionBackend {
+	/// The library itself is never closed, see [`unload_extension`].
+	Native(ExtensionDeclaration),
+
+	#[cfg(feature = "wasm")]
+	Wasm(Arc<WasmRuntime>)
+}
+
+#[cfg(feature = "core")]
+impl ExtensionBackend {
+	fn build(&self, state: &State) -> Result<Box<dyn Extension>, ExtensionError> {
+		match self {
+			Self::Native(declaration) => declaration.build(state),
+
+			#[cfg(feature = "wasm")]
+			Self::Wasm(runtime) => Ok(Box::new(WasmExtension::new(runtime.clone())?))
+		}
+	}
+
+	/// Runs code belonging to the extension, a WebAssembly module can't unwind into us so there is nothing to catch.
+	fn call<T>(&self, entry_point: &'static str, callback: impl FnOnce() -> T) -> Result<T, ExtensionError> {
+		match self {
+			Self::Native(declaration) => declaration.call(entry_point, callback),
+
+			#[cfg(feature = "wasm")]
+			Self::Wasm(_) => Ok(callback())
+		}
+	}
+}
+
+#[cfg(feature = "core")]
+impl Extensions {
+	pub fn new() -> Self {
+		Self {
+			items: Vec::new(),
+			failed: Vec::new(),
+			disabled: Vec::new(),
+			trust: TrustStore::default(),
+			grants: GrantStore::default(),
+			path: None,
+			libraries: Vec::new(),
+			pending: Vec::new(),
+			building: Vec::new(),
+			shut_down: false
+		}
+	}
+
+	/// Manifests of every library that has been loaded.
+	pub fn manifests(&self) -> Vec<&ExtensionManifest> {
+		self.libraries.iter().map(|x| &x.manifest).collect()
+	}
+
+	pub fn manifest(&self, id: &str) -> Option<&ExtensionManifest> {
+		self.libraries.iter()
+			.map(|x| &x.manifest)
+			.chain(self.pending.iter().map(|x| &x.manifest))
+			.chain(self.disabled.iter().map(|x| &x.manifest))
+			.find(|x| x.id == id)
+	}
+
+	pub fn is_disabled(&self, id: &str) -> bool {
+		self.disabled.iter().any(|x| x.manifest.id == id)
+	}
+
+	pub fn is_loaded(&self, id: &str) -> bool {
+		self.libraries.iter().any(|x| x.manifest.id == id)
+	}
+
+	/// The loaded extensions, to call hooks on once this lock has been released.
+	pub fn hooks(&self) -> ExtensionHooks {
+		ExtensionHooks {
+			extensions: self.items.iter().map(|extension| HookedExtension {
+				extension: extension.clone(),
+				library: self.libraries.iter().find(|x| x.manifest.id == extension.id()).cloned()
+			}).collect()
+		}
+	}
+
+	/// Calls a hook on every loaded extension while holding this lock, prefer [`Extensions::hooks`] wherever it can be released.
+	pub fn dispatch(&self, hook: &'static str, callback: impl Fn(&dyn Extension)) {
+		self.hooks().dispatch(hook, callback)
+	}
+
+	/// Calls a hook on a single loaded extension while holding this lock, returning false if it isn't loaded.
+	pub fn dispatch_to(&self, id: &str, hook: &'static str, callback: impl FnOnce(&dyn Extension)) -> bool {
+		self.hooks().dispatch_to(id, hook, callback)
+	}
+
+	pub fn permissions(&self, id: &str) -> Option<Arc<ExtensionPermissions>> {
+		self.libraries.iter().find(|x| x.manifest.id == id).map(|x| x.permissions.clone())
+	}
+
+	/// Games of every loaded extension while holding this lock, see [`ExtensionHooks::games`].
+	pub fn games(&self) -> Vec<Arc<Box<dyn Game>>> {
+		self.hooks().games()
+	}
+
+	/// Content providers of every loaded extension while holding this lock, see [`ExtensionHooks::content_providers`].
+	pub fn content_providers(&self) -> Vec<Box<dyn ContentProvider>> {
+		self.hooks().content_providers()
+	}
+
+	/// Records that the user allows an extension to use these capabilities, taking effect immediately if it is loaded.
+	pub fn grant(&mut self, id: &str, capabilities: &[Capability]) -> std::io::Result<()> {
+		check_host("grant capabilities")?;
+		self.grants.grant(id, capabilities);
+		self.grants.save()?;
+		if let Some(permissions) = self.permissions(id) {
+			permissions.set_granted(self.grants.granted(id));
+		}
+		Ok(())
+	}
+
+	pub fn revoke(&mut self, id: &str) -> std::io::Result<()> {
+		check_host("revoke capabilities")?;
+		self.grants.revoke(id);
+		self.grants.save()?;
+		if let Some(permissions) = self.permissions(id) {
+			permissions.set_granted(vec![]);
+		}
+		Ok(())
+	}
+
+	pub fn load_libraries(&mut self, path: PathBuf) {
+		self.path = Some(path.clone());
+		self.trust = TrustStore::load(path.join(TRUST_FILE));
+		self.grants = GrantStore::load(path.join(GRANTS_FILE));
+
+		// nothing from a previous run is still mapped, but on Windows copies still in use by another instance of HERO can't be removed
+		if let Ok(entries) = std::fs::read_dir(path.join(LOADED_DIRECTORY)) {
+			for entry in entries.filter_map(|x| x.ok()) {
+				let _ = std::fs::remove_file(entry.path());
+			}
+		}
+
+		if let Ok(entries) = std::fs::read_dir(path) {
+			let mut paths: Vec<PathBuf> = entries.filter_map(|x| x.ok())
+				.map(|x| x.path())
+				.filter(|x| x.is_file())
+				.collect();
+			paths.sort();
+
+			for path in paths {
+				let extension = path.extension()
+					.map(|x| x.to_string_lossy().to_ascii_lowercase())
+					.unwrap_or_default();
+				if extension == MANIFEST_EXTENSION || extension == SIGNATURE_EXTENSION {
+					continue;
+				}
+				if extension == WASM_EXTENSION && !cfg!(feature = "wasm") {
+					println!("skipping extension {:?}, this build of HERO does not support WebAssembly extensions", path);
+					continue;
+				}
+				if extension != std::env::consts::DLL_EXTENSION && extension != WASM_EXTENSION {
+					if LIBRARY_EXTENSIONS.contains(&extension.as_str()) {
+						println!("skipping extension {:?}, it was built for another platform (expected a .{} library)", path, std::env::consts::DLL_EXTENSION);
+					} else {
+						println!("skipping {:?} in the extensions directory, it is not a .{} library", path, std::env::consts::DLL_EXTENSION);
+					}
+					continue;
+				}
+
+				if let Err(error) = self.load_library(&path) {
+					println!("failed to load extension {:?}! {}", path, error);
+					self.failed.push(FailedExtension { path, error });
+				}
+			}
+		}
+	}
+
+	fn load_library(&mut self, path: &Path) -> Result<(), ExtensionError> {
+		let manifest_path = path.with_extension(MANIFEST_EXTENSION);
+		if !manifest_path.is_file() {
+			return Err(ManifestError::Missing.into());
+		}
+
+		let manifest = ExtensionManifest::read(&manifest_path)?;
+		if self.manifest(&manifest.id).is_some() || self.building.contains(&manifest.id) {
+			return Err(ExtensionError::Duplicate(manifest.id));
+		}
+		if self.read_disabled().contains(&manifest.id) {
+			self.disabled.push(DisabledExtension {
+				path: path.to_path_buf(),
+				manifest
+			});
+			return Ok(());
+		}
+
+		let backend = self.open(path)?;
+		let permissions = ExtensionPermissions::new(
+			&manifest,
+			self.grants.granted(&manifest.id),
+			self.path.as_ref().and_then(|x| x.parent()).map(Path::to_path_buf).unwrap_or_default()
+		);
+		self.pending.push(ExtensionLibrary {
+			path: path.to_path_buf(),
+			manifest,
+			backend,
+			permissions: Arc::new(permissions),
+			localisation: vec![],
+
+			#[cfg(feature = "ui")]
+			ui_containers: vec![]
+		});
+
+		Ok(())
+	}
+
+	/// Checks a library the way [`Extensions::open`] would, without loading anything from it.
+	fn verify(&self, path: &Path) -> Result<(), ExtensionError> {
+		#[cfg(feature = "wasm")]
+		if path.extension().is_some_and(|x| x.eq_ignore_ascii_case(WASM_EXTENSION)) {
+			WasmRuntime::new(path)?;
+			return Ok(());
+		}
+
+		self.trust.verify(path)?;
+		Ok(())
+	}
+
+	fn open(&self, path: &Path) -> Result<ExtensionBackend, ExtensionError> {
+		// WebAssembly modules are sandboxed, so unlike native libraries they don't need to be signed
+		#[cfg(feature = "wasm")]
+		if path.extension().is_some_and(|x| x.eq_ignore_ascii_case(WASM_EXTENSION)) {
+			return Ok(ExtensionBackend::Wasm(Arc::new(WasmRuntime::new(path)?)));
+		}
+
+		// the library is loaded from a private copy of the bytes that were verified,
+		// so it can't be swapped out between checking its signature and loading it
+		let bytes = self.trust.verify(path)?;
+		let directory = path.parent().unwrap_or(Path::new(".")).join(LOADED_DIRECTORY);
+		std::fs::create_dir_all(&directory)?;
+		let copy = directory.join(format!("{}-{}", Uuid::new_v4(), path.file_name().unwrap_or_default().to_string_lossy()));
+		std::fs::write(&copy, bytes)?;
+
+		let library = unsafe { libloading::Library::new(&copy) }
+			.map_err(|err| ExtensionError::Library(err.to_string()))?;
+		let declaration = *check_abi(&library)?;
+
+		// games, containers, services and even the strings an extension hands out point into its library,
+		// and can outlive it being unloaded, so once anything inside of it may have run it stays mapped
+		std::mem::forget(library);
+		Ok(ExtensionBackend::Native(declaration))
+	}
+
+	/// Opens the libraries that failed because a dependency was missing again, once it is loaded or about to be.
+	fn retry_missing_dependencies(&mut self) {
+		while let Some(index) = self.failed.iter().position(|x| {
+			matches!(&x.error, ExtensionError::MissingDependency(id) if self.is_loaded(id) || self.pending.iter().any(|x| x.manifest.id == *id))
+		}) {
+			let path = self.failed.remove(index).path;
+			if let Err(error) = self.load_library(&path) {
+				self.failed.push(FailedExtension { path, error });
+			}
+		}
+	}
+
+	/// Takes an extension out along with every extension depending on it, which wait in [`Extensions::failed`] until it is loaded again.
+	/// What they added to the rest of the state is left for [`unload_extension`] to remove once this lock has been released.
+	fn take_loaded(&mut self, id: &str) -> Vec<UnloadedExtension> {
+		if !self.is_loaded(id) {
+			return vec![];
+		}
+
+		let mut unloaded = vec![];
+		let dependents: Vec<(String, PathBuf)> = self.libraries.iter()
+			.filter(|x| x.manifest.dependencies.iter().any(|x| x == id))
+			.map(|x| (x.manifest.id.clone(), x.path.clone()))
+			.collect();
+		for (dependent, path) in dependents {
+			let taken = self.take_loaded(&dependent);
+			if !taken.is_empty() {
+				self.failed.push(FailedExtension { path, error: ExtensionError::MissingDependency(id.to_string()) });
+			}
+			unloaded.extend(taken);
+		}
+
+		if let Some(index) = self.libraries.iter().position(|x| x.manifest.id == id) {
+			let library = self.libraries.remove(index);
+			let extension = self.items.iter().position(|x| x.id() == id).map(|index| self.items.remove(index));
+			unloaded.push(UnloadedExtension { library, extension });
+		}
+		unloaded
+	}
+
+	/// Stops trusting libraries signed by `key`, extensions that are already loaded stay loaded until HERO restarts.
+	pub fn untrust_key(&mut self, key: &str) -> std::io::Result<()> {
+		check_host("untrust keys")?;
+		self.trust.untrust_key(key);
+		self.trust.save()
+	}
+
+	fn read_disabled(&self) -> Vec<String> {
+		self.path.as_ref()
+			.and_then(|x| std::fs::read_to_string(x.join(DISABLED_FILE)).ok())
+			.and_then(|x| serde_json::from_str(&x).ok())
+			.unwrap_or_default()
+	}
+
+	fn save_disabled(&self) -> std::io::Result<()> {
+		let Some(path) = &self.path else {
+			return Ok(());
+		};
+		let ids: Vec<&str> = self.disabled.iter().map(|x| x.manifest.id.as_str()).collect();
+		std::fs::create_dir_all(path)?;
+		std::fs::write(path.join(DISABLED_FILE), serde_json::to_string(&ids)?)
+	}
+}
+
+#[cfg(feature = "core")]
+impl Default for Extensions {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(feature = "core")]
+impl Drop for Extensions {
+    fn drop(&mut self) {
+		println!("dropping extensions into the void");
+        if !self.items.is_empty() || !self.libraries.is_empty() {
+			for _extension in self.items.drain(..) {
+				
+			}
+            for library in self.libraries.drain(..) {
+				drop(library);
+			}
+			self.pending.clear();
+        }
+    }
+}
+
+/// Builds every library opened by [`Extensions::load_libraries`] that hasn't been built yet,
+/// each one after the extensions it depends on. Extensions that were waiting on a dependency are tried again.
+/// No lock is held while an extension is set up, so its setup is free to use the rest of the state.
+#[cfg(feature = "core")]
+pub fn load_pending(state: &State) {
+	let mut extensions = state.extensions.write();
+	extensions.retry_missing_dependencies();
+	let mut pending = std::mem::take(&mut extensions.pending);
+	let mut loaded: Vec<String> = extensions.libraries.iter().map(|x| x.manifest.id.clone()).collect();
+	extensions.building.extend(pending.iter().map(|x| x.manifest.id.clone()));
+	drop(extensions);
+
+	while let Some(index) = next_loadable(pending.iter().map(|x| &x.manifest), &loaded) {
+		let mut library = pending.remove(index);
+		match load_extension(state, &library) {
+			Ok(extension) => {
+				let mut localisation = state.localisation.write();
+				for (locale, data) in extension.localisation {
+					library.localisation.push((locale.to_string(), data.iter().map(|x| x.0.to_string()).collect()));
+					localisation.insert_data(locale, data);
+				}
+				drop(localisation);
+
+				#[cfg(feature = "ui")]
+				{
+					let mut ui_containers = state.ui_containers.write();
+					for container in extension.ui_containers {
+						library.ui_containers.push(container.id.to_string());
+						ui_containers.push(Arc::new(acting_container(library.permissions.clone(), container)));
+					}
+				}
+
+				loaded.push(library.manifest.id.clone());
+				let mut extensions = state.extensions.write();
+				extensions.building.retain(|x| *x != library.manifest.id);
+				extensions.items.push(Arc::from(extension.extension));
+				extensions.libraries.push(Arc::new(library));
+			},
+			Err(error) => {
+				println!("failed to load extension {}! {}", library.manifest.id, error);
+				let mut extensions = state.extensions.write();
+				extensions.building.retain(|x| *x != library.manifest.id);
+				extensions.failed.push(FailedExtension {
+					path: library.path.clone(),
+					error
+				});
+			}
+		}
+	}
+
+	let manifests: Vec<&ExtensionManifest> = pending.iter().map(|x| &x.manifest).collect();
+	let errors = unloadable(&manifests, &loaded);
+	let mut extensions = state.extensions.write();
+	for (library, error) in pending.into_iter().zip(errors) {
+		println!("failed to load extension {}! {}", library.manifest.id, error);
+		extensions.building.retain(|x| *x != library.manifest.id);
+		extensions.failed.push(FailedExtension {
+			path: library.path.clone(),
+			error
+		});
+	}
+}
+
+/// The first pending library whose dependencies have all been loaded.
+#[cfg(feature = "core")]
+fn next_loadable<'a>(mut pending: impl Iterator<Item = &'a ExtensionManifest>, loaded: &[String]) -> Option<usize> {
+	pending.position(|x| x.dependencies.iter().all(|x| loaded.contains(x)))
+}
+
+/// Why each of these extensions can't be loaded once nothing else can be,
+/// they depend on something that won't load, either directly, through another extension, or on itself.
+#[cfg(feature = "core")]
+fn unloadable(pending: &[&ExtensionManifest], loaded: &[String]) -> Vec<ExtensionError> {
+	let mut missing: Vec<String> = vec![];
+	loop {
+		let before = missing.len();
+		for manifest in pending {
+			let waits_on_missing = manifest.dependencies.iter()
+				.any(|x| missing.contains(x) || (!pending.iter().any(|manifest| manifest.id == *x) && !loaded.contains(x)));
+			if waits_on_missing && !missing.contains(&manifest.id) {
+				missing.push(manifest.id.clone());
+			}
+		}
+		if missing.len() == before {
+			break;
+		}
+	}
+
+	let cycle: Vec<String> = pending.iter().map(|x| x.id.clone()).filter(|x| !missing.contains(x)).collect();
+	pending.iter().map(|manifest| match manifest.dependencies.iter().find(|x| !loaded.contains(x) && !cycle.contains(x)) {
+		Some(dependency) if missing.contains(&manifest.id) => ExtensionError::MissingDependency(dependency.clone()),
+		_ => ExtensionError::DependencyCycle(cycle.clone())
+	}).collect()
+}
+
+/// Tells every extension that HERO is closing, only the first call does anything.
+/// The state lives until the process exits, so this has to be called rather than left to dropping it.
+#[cfg(feature = "core")]
+pub fn shutdown_extensions(state: &State) -> Result<(), HostOnlyError> {
+	state.capabilities.check_host("shut down extensions")?;
+	let mut extensions = state.extensions.write();
+	if std::mem::replace(&mut extensions.shut_down, true) {
+		return Ok(());
+	}
+	let hooks = extensions.hooks();
+	drop(extensions);
+
+	hooks.dispatch("on_shutdown", |x| x.on_shutdown());
+	Ok(())
+}
+
+/// An extension taken out of [`Extensions`], along with its library so what it added can still be removed.
+#[cfg(feature = "core")]
+struct UnloadedExtension {
+	library: Arc<ExtensionLibrary>,
+	extension: Option<Arc<dyn Extension>>
+}
+
+/// Removes an extension along with the games, localisation, containers and services it added.
+/// A native library stays mapped until HERO exits, as anything it handed out may still be in use.
+/// Extensions depending on it are unloaded first, and wait in [`Extensions::failed`] until it is loaded again.
+/// Returns false if no extension with the given identifier is loaded.
+#[cfg(feature = "core")]
+pub fn unload_extension(state: &State, id: &str) -> Result<bool, HostOnlyError> {
+	state.capabilities.check_host("manage extensions")?;
+	let unloaded = state.extensions.write().take_loaded(id);
+	let found = !unloaded.is_empty();
+	clean_up(state, unloaded);
+	Ok(found)
+}
+
+/// Removes what unloaded extensions added to the rest of the state.
+/// Other locks are only taken here, after the lock on [`Extensions`] has been released, as they are held elsewhere while it is taken.
+#[cfg(feature = "core")]
+fn clean_up(state: &State, unloaded: Vec<UnloadedExtension>) {
+	for UnloadedExtension { library, extension } in unloaded {
+		let id = &library.manifest.id;
+		state.services.write().remove_provider(id);
+		state.extension_settings.write().remove(id);
+		if let Some(extension) = extension {
+			let games = library.call("games", || extension.games().unwrap_or_default()).unwrap_or_default();
+			let game_ids: Vec<String> = games.iter().map(|x| x.id().to_string()).collect();
+			drop(games);
+
+			let mut current_game = state.current_game.write();
+			if current_game.as_ref().is_some_and(|x| game_ids.contains(x)) {
+				*current_game = None;
+			}
+			drop(current_game);
+
+			if let Err(err) = library.call("drop", || drop(extension)) {
+				println!("{}", err);
+			}
+		}
+
+		let mut localisation = state.localisation.write();
+		for (locale, keys) in &library.localisation {
+			localisation.remove_data(locale, keys);
+		}
+		drop(localisation);
+
+		#[cfg(feature = "ui")]
+		state.ui_containers.write().retain(|x| !library.ui_containers.iter().any(|id| id == x.id));
+	}
+}
+
+/// Unloads an extension and keeps it from being loaded again until it is enabled.
+#[cfg(feature = "core")]
+pub fn disable_extension(state: &State, id: &str) -> std::io::Result<()> {
+	state.capabilities.check_host("manage extensions")?;
+	let mut extensions = state.extensions.write();
+	let Some(library) = extensions.libraries.iter().find(|x| x.manifest.id == id) else {
+		return Ok(());
+	};
+	let disabled = DisabledExtension {
+		path: library.path.clone(),
+		manifest: library.manifest.clone()
+	};
+
+	let unloaded = extensions.take_loaded(id);
+	extensions.disabled.push(disabled);
+	let result = extensions.save_disabled();
+	drop(extensions);
+
+	clean_up(state, unloaded);
+	result
+}
+
+/// Loads a disabled extension, any failure ends up in [`Extensions::failed`].
+#[cfg(feature = "core")]
+pub fn enable_extension(state: &State, id: &str) -> std::io::Result<()> {
+	state.capabilities.check_host("manage extensions")?;
+	let mut extensions = state.extensions.write();
+	let Some(index) = extensions.disabled.iter().position(|x| x.manifest.id == id) else {
+		return Ok(());
+	};
+	let disabled = extensions.disabled.remove(index);
+	extensions.save_disabled()?;
+
+	if let Err(error) = extensions.load_library(&disabled.path) {
+		println!("failed to load extension {:?}! {}", disabled.path, error);
+		extensions.failed.push(FailedExtension { path: disabled.path, error });
+	}
+	drop(extensions);
+	load_pending(state);
+
+	Ok(())
+}
+
+/// Trusts a library that failed to load because of its signature, then loads it.
+#[cfg(feature = "core")]
+pub fn trust_library(state: &State, path: &Path) -> Result<(), ExtensionError> {
+	state.capabilities.check_host("trust libraries").map_err(std::io::Error::from)?;
+	let mut extensions = state.extensions.write();
+	extensions.trust.trust_library(path)?;
+	extensions.trust.save()?;
+
+	extensions.failed.retain(|x| x.path != path);
+	if let Err(error) = extensions.load_library(path) {
+		extensions.failed.push(FailedExtension { path: path.to_path_buf(), error: error.clone() });
+		return Err(error);
+	}
+	drop(extensions);
+	load_pending(state);
+
+	Ok(())
+}
+
+/// Trusts every library signed by `key`, then loads the ones that failed because of their signature.
+#[cfg(feature = "core")]
+pub fn trust_key(state: &State, key: &str) -> Result<(), ExtensionError> {
+	state.capabilities.check_host("trust keys").map_err(std::io::Error::from)?;
+	let mut extensions = state.extensions.write();
+	extensions.trust.trust_key(key)?;
+	extensions.trust.save()?;
+
+	let (untrusted, failed) = std::mem::take(&mut extensions.failed).into_iter()
+		.partition(|x| matches!(x.error, ExtensionError::Signature(_)));
+	extensions.failed = failed;
+	for extension in untrusted {
+		let FailedExtension { path, .. } = extension;
+		if let Err(error) = extensions.load_library(&path) {
+			extensions.failed.push(FailedExtension { path, error });
+		}
+	}
+	drop(extensions);
+	load_pending(state);
+
+	Ok(())
+}
+
+/// Unloads an extension and deletes its library, manifest and signature.
+#[cfg(feature = "core")]
+pub fn uninstall_extension(state: &State, id: &str) -> std::io::Result<()> {
+	state.capabilities.check_host("manage extensions")?;
+	let mut extensions = state.extensions.write();
+	let path = extensions.libraries.iter()
+		.find(|x| x.manifest.id == id)
+		.map(|x| x.path.clone())
+		.or_else(|| extensions.disabled.iter().find(|x| x.manifest.id == id).map(|x| x.path.clone()));
+	let Some(path) = path else {
+		return Ok(());
+	};
+
+	let unloaded = extensions.take_loaded(id);
+	let saved = if extensions.is_disabled(id) {
+		extensions.disabled.retain(|x| x.manifest.id != id);
+		extensions.save_disabled()
+	} else {
+		Ok(())
+	};
+	let revoked = extensions.revoke(id);
+	drop(extensions);
+
+	clean_up(state, unloaded);
+	saved?;
+	revoked?;
+
+	std::fs::remove_file(&path)?;
+	for extension in [MANIFEST_EXTENSION, SIGNATURE_EXTENSION] {
+		let path = path.with_extension(extension);
+		if path.exists() {
+			std::fs::remove_file(path)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Moves a library into the extensions directory and writes its manifest and signature next to it, replacing any installed version,
+/// then grants it `capabilities` and loads it unless it was disabled. If it fails to load it is left with the capabilities it had before.
+/// The installed version is only unloaded and replaced once the new one has been verified, until then it is kept in a staging directory.
+#[cfg(feature = "core")]
+pub fn install_extension(state: &State, library: &Path, manifest: &ExtensionManifest, signature: Option<&[u8]>, capabilities: &[Capability]) -> Result<(), ExtensionError> {
+	state.capabilities.check_host("install extensions").map_err(std::io::Error::from)?;
+	manifest.validate()?;
+
+	let wasm = is_wasm_module(library)?;
+	if wasm && !cfg!(feature = "wasm") {
+		return Err(ExtensionError::Wasm("this build of HERO does not support WebAssembly extensions".into()));
+	}
+
+	let directory = state.extensions.read().path.clone().unwrap_or_else(|| state.path.join("extensions"));
+	let file_name = extension_file_name(&manifest.id, wasm);
+	let path = directory.join(&file_name);
+	let staged = directory.join(STAGING_DIRECTORY).join(&file_name);
+	let verified = stage_extension(library, &staged, manifest, signature)
+		.and_then(|_| state.extensions.read().verify(&staged));
+	if let Err(error) = verified {
+		for path in [staged.clone(), staged.with_extension(MANIFEST_EXTENSION), staged.with_extension(SIGNATURE_EXTENSION)] {
+			let _ = std::fs::remove_file(path);
+		}
+		return Err(error);
+	}
+
+	let mut extensions = state.extensions.write();
+	let is_disabled = extensions.is_disabled(&manifest.id);
+	let previous = extensions.libraries.iter()
+		.find(|x| x.manifest.id == manifest.id)
+		.map(|x| x.path.clone())
+		.or_else(|| extensions.disabled.iter().find(|x| x.manifest.id == manifest.id).map(|x| x.path.clone()));
+	let unloaded = extensions.take_loaded(&manifest.id);
+	extensions.disabled.retain(|x| x.manifest.id != manifest.id);
+	extensions.failed.retain(|x| x.path != path);
+	drop(extensions);
+	clean_up(state, unloaded);
+
+	if let Some(previous) = previous.filter(|x| *x != path) {
+		std::fs::remove_file(&previous)?;
+		let _ = std::fs::remove_file(previous.with_extension(MANIFEST_EXTENSION));
+		let _ = std::fs::remove_file(previous.with_extension(SIGNATURE_EXTENSION));
+	}
+	for extension in [MANIFEST_EXTENSION, SIGNATURE_EXTENSION] {
+		let (from, to) = (staged.with_extension(extension), path.with_extension(extension));
+		if from.exists() {
+			std::fs::rename(from, to)?;
+		} else if to.exists() {
+			std::fs::remove_file(to)?;
+		}
+	}
+	std::fs::rename(&staged, &path)?;
+
+	// the library's permissions are read from the grants when it is opened, so they have to be in place by then
+	let mut extensions = state.extensions.write();
+	let previous_grants = extensions.grants.granted(&manifest.id);
+	if !capabilities.is_empty() {
+		extensions.grant(&manifest.id, capabilities)?;
+	}
+	if is_disabled {
+		extensions.disabled.push(DisabledExtension {
+			path,
+			manifest: manifest.clone()
+		});
+		return Ok(());
+	}
+
+	let loaded = extensions.load_library(&path);
+	drop(extensions);
+	if loaded.is_ok() {
+		load_pending(state);
+	}
+	let error = loaded.err().or_else(|| state.extensions.read().failed.iter().find(|x| x.path == path).map(|x| x.error.clone()));
+	let Some(error) = error else {
+		return Ok(());
+	};
+
+	let mut extensions = state.extensions.write();
+	let restored = extensions.revoke(&manifest.id).and_then(|_| match previous_grants.is_empty() {
+		true => Ok(()),
+		false => extensions.grant(&manifest.id, &previous_grants)
+	});
+	if let Err(err) = restored {
+		println!("failed to take back the capabilities granted to {}! {}", manifest.id, err);
+	}
+	Err(error)
+}
+
+/// Moves a library into the staging directory and writes its manifest and signature next to it.
+#[cfg(feature = "core")]
+fn stage_extension(library: &Path, staged: &Path, manifest: &ExtensionManifest, signature: Option<&[u8]>) -> Result<(), ExtensionError> {
+	if let Some(parent) = staged.parent() {
+		std::fs::create_dir_all(parent)?;
+	}
+	if std::fs::rename(library, staged).is_err() {
+		std::fs::copy(library, staged)?;
+		std::fs::remove_file(library)?;
+	}
+
+	std::fs::write(staged.with_extension(MANIFEST_EXTENSION), serde_json::to_string_pretty(manifest).map_err(std::io::Error::from)?)?;
+	match signature {
+		Some(signature) => std::fs::write(staged.with_extension(SIGNATURE_EXTENSION), signature)?,
+		None => if staged.with_extension(SIGNATURE_EXTENSION).exists() {
+			std::fs::remove_file(staged.with_extension(SIGNATURE_EXTENSION))?;
+		}
+	}
+	Ok(())
+}
+
+/// Unloads every extension, then loads everything in the extensions directory again.
+#[cfg(feature = "core")]
+pub fn reload_extensions(state: &State) -> Result<(), HostOnlyError> {
+	state.capabilities.check_host("manage extensions")?;
+	let mut extensions = state.extensions.write();
+	let ids: Vec<String> = extensions.libraries.iter().map(|x| x.manifest.id.clone()).collect();
+	let unloaded: Vec<UnloadedExtension> = ids.iter().flat_map(|id| extensions.take_loaded(id)).collect();
+	extensions.pending.clear();
+	extensions.failed.clear();
+	extensions.disabled.clear();
+	drop(extensions);
+	clean_up(state, unloaded);
+
+	let mut extensions = state.extensions.write();
+	if let Some(path) = extensions.path.clone() {
+		extensions.load_libraries(path);
+	}
+	drop(extensions);
+	load_pending(state);
+	Ok(())
+}
+
+#[cfg(feature = "core")]
+struct LoadedExtension {
+	extension: Box<dyn Extension>,
+	localisation: Vec<(&'static str, Vec<(&'static str, &'static str)>)>,
+
+	#[cfg(feature = "ui")]
+	ui_containers: Vec<Container>
+}
+
+/// Builds and sets up an extension, catching any panics along the way.
+#[cfg(feature = "core")]
+fn load_extension(state: &State, library: &ExtensionLibrary) -> Result<LoadedExtension, ExtensionError> {
+	let extension = act_as(Some(library.permissions.clone()), || library.backend.build(state))?;
+	if extension.id() != library.manifest.id {
+		return Err(ExtensionError::IdMismatch { expected: library.manifest.id.clone(), found: extension.id().into() });
+	}
+
+	let id = &library.manifest.id;
+	let schema = library.call("settings", || extension.settings())?;
+	let path = state.path.join(SETTINGS_DIRECTORY).join(format!("{}.json", id));
+	state.extension_settings.write().insert(id.clone(), ExtensionSettings::load(path, schema));
+
+	let result = set_up_extension(library, extension);
+	if result.is_err() {
+		state.extension_settings.write().remove(id);
+	}
+	result
+}
+
+#[cfg(feature = "core")]
+fn set_up_extension(library: &ExtensionLibrary, extension: Box<dyn Extension>) -> Result<LoadedExtension, ExtensionError> {
+	library.call("setup", || extension.setup())?;
+
+	let games = library.call("games", || extension.games().unwrap_or_default())?;
+	if let Some(game) = games.iter().find(|game| !library.manifest.games.iter().any(|x| x == game.id())) {
+		return Err(ExtensionError::UndeclaredGame(game.id().into()));
+	}
+	drop(games);
+
+	Ok(LoadedExtension {
+		localisation: library.call("localisation", || extension.localisation())?,
+
+		#[cfg(feature = "ui")]
+		ui_containers: library.call("ui_containers", || extension.ui_containers())?,
+		extension
+	})
+}
+
+#[cfg(feature = "core")]
+pub fn load_extensions() {
+	let state = State::get();
+	load_pending(&state);
+}
+
+#[cfg(all(test, feature = "core"))]
+mod tests {
+	use super::*;
+
+	fn manifest(id: &str, dependencies: &[&str]) -> ExtensionManifest {
+		ExtensionManifest {
+			id: id.into(),
+			name: id.into(),
+			version: "1.0.0".into(),
+			authors: vec![],
+			api_version: API_VERSION,
+			platforms: vec![WASM_PLATFORM.into()],
+			games: vec![],
+			capabilities: vec![],
+			dependencies: dependencies.iter().map(|x| x.to_string()).collect()
+		}
+	}
+
+	/// Goes through manifests the way [`load_pending`] does, returning the order they were loaded in and why the rest weren't.
+	fn load_order(manifests: &[ExtensionManifest]) -> (Vec<String>, Vec<(String, ExtensionError)>) {
+		let mut pending: Vec<&ExtensionManifest> = manifests.iter().collect();
+		let mut loaded = vec![];
+		while let Some(index) = next_loadable(pending.iter().copied(), &loaded) {
+			loaded.push(pending.remove(index).id.clone());
+		}
+		let errors = unloadable(&pending, &loaded);
+		(loaded, pending.iter().map(|x| x.id.clone()).zip(errors).collect())
+	}
+
+	#[test]
+	fn loads_extensions_after_their_dependencies() {
+		let (loaded, failed) = load_order(&[manifest("a", &["b"]), manifest("b", &["c"]), manifest("c", &[])]);
+		assert_eq!(loaded, ["c", "b", "a"]);
+		assert!(failed.is_empty());
+	}
+
+	#[test]
+	fn tells_missing_dependencies_from_cycles() {
+		let (loaded, failed) = load_order(&[
+			manifest("a", &["missing"]),
+			manifest("b", &["a"]),
+			manifest("c", &["d"]),
+			manifest("d", &["c"]),
+			manifest("e", &[])
+		]);
+		assert_eq!(loaded, ["e"]);
+		assert_eq!(failed.len(), 4);
+		assert!(matches!(&failed[0], (id, ExtensionError::MissingDependency(x)) if id == "a" && x == "missing"));
+		assert!(matches!(&failed[1], (id, ExtensionError::MissingDependency(x)) if id == "b" && x == "a"));
+		assert!(matches!(&failed[2], (id, ExtensionError::DependencyCycle(x)) if id == "c" && *x == ["c", "d"]));
+		assert!(matches!(&failed[3], (id, ExtensionError::DependencyCycle(x)) if id == "d" && *x == ["c", "d"]));
+	}
+
+	#[test]
+	#[cfg(feature = "testing")]
+	fn keeps_the_installed_version_when_the_new_one_fails_verification() {
+		let test = crate::testing::TestState::new();
+		let state = test.state();
+		let directory = state.path.join("extensions");
+		let installed = directory.join(library_file_name("example"));
+		std::fs::create_dir_all(&directory).unwrap();
+		std::fs::write(&installed, b"old").unwrap();
+
+		let library = test.path().join("library.download");
+		std::fs::write(&library, b"\x7fELF").unwrap();
+		let result = install_extension(&state, &library, &manifest("example", &[]), None, &[Capability::Process]);
+		assert!(matches!(result, Err(ExtensionError::Signature(SignatureError::Unsigned))), "{:?}", result.err());
+
+		assert_eq!(std::fs::read(&installed).unwrap(), b"old");
+		assert!(!directory.join(STAGING_DIRECTORY).join(library_file_name("example")).exists());
+		assert!(state.extensions.read().grants.granted("example").is_empty());
+	}
+
+	#[test]
+	#[cfg(feature = "testing")]
+	fn reports_panics_in_setup_instead_of_crashing() {
+		struct Panicking;
+		impl Icon for Panicking {
+			fn icon(&self) -> Option<crate::IconData> {
+				None
+			}
+		}
+		impl Extension for Panicking {
+			fn id(&self) -> &'static str {
+				"panicking"
+			}
+
+			fn setup(&self) {
+				panic!("broken setup");
+			}
+		}
+
+		unsafe extern "C" fn build(state: *const c_void, panic: *mut c_void) -> *mut c_void {
+			build_extension(|_| Box::new(Panicking), state, panic)
+		}
+
+		let test = crate::testing::TestState::new();
+		let state = test.state();
+		let manifest = manifest("panicking", &[]);
+		let permissions = ExtensionPermissions::new(&manifest, vec![], test.path());
+		state.extensions.write().pending.push(ExtensionLibrary {
+			path: test.path().join(library_file_name("panicking")),
+			manifest,
+			backend: ExtensionBackend::Native(ExtensionDeclaration {
+				api_version: API_VERSION,
+				rustc_version: RUSTC_VERSION.as_ptr() as *const c_char,
+				core_version: CORE_VERSION.as_ptr() as *const c_char,
+				target: TARGET.as_ptr() as *const c_char,
+				features: FEATURES.as_ptr() as *const c_char,
+				fingerprint: BUILD_FINGERPRINT.as_ptr() as *const c_char,
+				build,
+				catch_unwind
+			}),
+			permissions: Arc::new(permissions),
+			localisation: vec![],
+
+			#[cfg(feature = "ui")]
+			ui_containers: vec![]
+		});
+		load_pending(&state);
+
+		let extensions = state.extensions.read();
+		assert!(extensions.items.is_empty() && extensions.building.is_empty());
+		assert!(matches!(
+			&extensions.failed[..],
+			[FailedExtension { error: ExtensionError::Panicked { entry_point: "setup", message }, .. }] if message == "broken setup"
+		), "{:?}", extensions.failed.iter().map(|x| x.error.to_string()).collect::<Vec<_>>());
+		assert!(!state.extension_settings.read().contains_key("panicking"));
+	}
+
+	#[test]
+	fn names_extensions_by_their_contents() {
+		let dir = crate::testing::temp_dir("extension");
+		let module = dir.join("module.download");
+		let library = dir.join("library.download");
+		std::fs::write(&module, b"\0asm\x01\0\0\0").unwrap();
+		std::fs::write(&library, b"\x7fELF").unwrap();
+
+		assert!(is_wasm_module(&module).unwrap());
+		assert!(!is_wasm_module(&library).unwrap());
+		assert_eq!(extension_file_name("example", true), "example.wasm");
+		assert_eq!(extension_file_name("example", false), library_file_name("example"));
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn only_loads_libraries_built_for_this_platform() {
+		let dir = crate::testing::temp_dir("extension");
+		for file in ["notes.txt", "example.dll", "libexample.dylib", "libexample.so", "libexample.so.sig"] {
+			std::fs::write(dir.join(file), b"").unwrap();
+		}
+		#[cfg(target_os = "linux")]
+		assert_eq!(library_file_name("example"), "libexample.so");
+
+		let mut extensions = Extensions::new();
+		extensions.load_libraries(dir.clone());
+		let failed: Vec<_> = extensions.failed.iter().map(|x| x.path.file_name().unwrap().to_string_lossy().to_string()).collect();
+		assert_eq!(failed, [library_file_name("example")]);
+		assert!(matches!(extensions.failed[0].error, ExtensionError::Manifest(ManifestError::Missing)));
+		assert!(extensions.pending.is_empty());
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_ids_that_would_overwrite_stores() {
+		for file in [DISABLED_FILE, GRANTS_FILE, TRUST_FILE] {
+			let id = Path::new(file).file_stem().unwrap().to_str().unwrap();
+			assert!(RESERVED_IDS.contains(&id), "{} is not reserved", id);
+
+			let manifest = manifest(id, &[]);
+			assert!(matches!(manifest.validate(), Err(ManifestError::InvalidId(_))));
+			assert!(ExtensionManifest { id: "example".into(), ..manifest }.validate().is_ok());
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::any::Any;
+use std::sync::Arc;
+use std::collections::HashMap;
+
+use super::ExtensionPermissions;
+
+/// Something one extension shares with others, such as a Java runtime manager or an account provider.
+///
+/// Services are found by name and then checked by type, so the extension providing one and
+/// the extensions using it must be built against the same version of the crate that defines it.
+pub trait Service: Any + Send + Sync {
+	/// Unique name of the service, conventionally prefixed with the providing extension's identifier.
+	const NAME: &'static str;
+}
+
+struct ProvidedService {
+	provider: Option<String>,
+	service: Arc<dyn Any + Send + Sync>
+}
+
+#[derive(Clone, Debug)]
+pub enum ServiceError {
+	/// Another extension already provides a service with this name.
+	AlreadyProvided {
+		name: &'static str,
+		provider: Option<String>
+	}
+}
+
+impl fmt::Display for ServiceError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::AlreadyProvided { name, provider } => match provider {
+				Some(provider) => write!(f, "service {} is already provided by {}", name, provider),
+				None => write!(f, "service {} is already provided by HERO", name)
+			}
+		}
+	}
+}
+
+impl std::error::Error for ServiceError {}
+
+/// Services provided by extensions, use [`crate::state::State::provide_service`] and [`crate::state::State::service`] rather than this directly.
+#[derive(Default)]
+pub struct ServiceRegistry {
+	services: HashMap<&'static str, ProvidedService>
+}
+
+impl ServiceRegistry {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Registers a service on behalf of an extension, or HERO itself if there is none,
+	/// replacing whatever the same provider registered under that name before.
+	pub fn provide<T: Service>(&mut self, provider: Option<&ExtensionPermissions>, service: Arc<T>) -> Result<(), ServiceError> {
+		let provider = provider.map(|x| x.id.clone());
+		if let Some(existing) = self.services.get(T::NAME).filter(|x| x.provider != provider) {
+			return Err(ServiceError::AlreadyProvided { name: T::NAME, provider: existing.provider.clone() });
+		}
+
+		self.services.insert(T::NAME, ProvidedService { provider, service });
+		Ok(())
+	}
+
+	/// Finds a service, extensions can only see services provided by themselves, HERO, or extensions they depend on.
+	pub fn get<T: Service>(&self, consumer: Option<&ExtensionPermissions>) -> Option<Arc<T>> {
+		let provided = self.services.get(T::NAME)?;
+		if let (Some(consumer), Some(provider)) = (consumer, &provided.provider) {
+			if consumer.id != *provider && !consumer.dependencies.contains(provider) {
+				println!("extension {} looked up {} without depending on {}", consumer.id, T::NAME, provider);
+				return None;
+			}
+		}
+
+		provided.service.clone().downcast().ok()
+	}
+
+	/// Names of every provided service along with who provides it.
+	pub fn names(&self) -> Vec<(&'static str, Option<&str>)> {
+		self.services.iter().map(|(name, x)| (*name, x.provider.as_deref())).collect()
+	}
+
+	/// Removes every service an extension provided, used when it is unloaded.
+	pub fn remove_provider(&mut self, id: &str) {
+		self.services.retain(|_, x| x.provider.as_deref() != Some(id));
+	}
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::extension::ExtensionManifest;
+
+	struct Accounts;
+
+	impl Service for Accounts {
+		const NAME: &'static str = "accounts";
+	}
+
+	fn permissions(id: &str, dependencies: &[&str]) -> ExtensionPermissions {
+		let manifest = ExtensionManifest {
+			id: id.into(),
+			name: id.into(),
+			version: "1.0.0".into(),
+			authors: vec![],
+			api_version: crate::extension::API_VERSION,
+			platforms: vec![],
+			games: vec![],
+			capabilities: vec![],
+			dependencies: dependencies.iter().map(|x| x.to_string()).collect()
+		};
+		ExtensionPermissions::new(&manifest, vec![], "/data")
+	}
+
+	#[test]
+	fn shows_services_to_dependents_only() {
+		let provider = permissions("provider", &[]);
+		let mut registry = ServiceRegistry::new();
+		registry.provide(Some(&provider), Arc::new(Accounts)).unwrap();
+
+		assert!(registry.get::<Accounts>(None).is_some());
+		assert!(registry.get::<Accounts>(Some(&provider)).is_some());
+		assert!(registry.get::<Accounts>(Some(&permissions("dependent", &["provider"]))).is_some());
+		assert!(registry.get::<Accounts>(Some(&permissions("stranger", &[]))).is_none());
+
+		let other = permissions("other", &[]);
+		assert!(matches!(registry.provide(Some(&other), Arc::new(Accounts)), Err(ServiceError::AlreadyProvided { provider: Some(x), .. }) if x == "provider"));
+		registry.remove_provider("provider");
+		assert!(registry.get::<Accounts>(None).is_none());
+		assert!(registry.provide(Some(&other), Arc::new(Accounts)).is_ok());
+	}
+}
\ No newline at end of file
@@ -0,0 +1,235 @@
+use std::fmt;
+use std::sync::Arc;
+use std::collections::HashMap;
+use serde::{ Serialize, Deserialize };
+
+#[cfg(feature = "core")]
+use std::path::PathBuf;
+
+#[cfg(feature = "ui")]
+use crate::state::State;
+#[cfg(feature = "ui")]
+use crate::user_interface::{ Element, PathSelectKind, Value, ValueLink };
+
+/// Directory inside of [`crate::state::State::path`] holding the settings of every extension.
+#[cfg(feature = "core")]
+pub const SETTINGS_DIRECTORY: &str = "extension_settings";
+
+/// One option on an extension's settings page.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingDefinition {
+	pub key: String,
+
+	/// Localisation key of the label shown next to the option.
+	pub name: String,
+	pub kind: SettingKind
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SettingKind {
+	String {
+		#[serde(default)]
+		default: String
+	},
+	Path {
+		#[serde(default)]
+		directory: bool,
+
+		#[serde(default)]
+		default: Option<String>
+	},
+	Boolean {
+		#[serde(default)]
+		default: bool
+	},
+
+	/// One of a fixed list of options.
+	Enum {
+		options: Vec<String>,
+		default: String
+	},
+	Number {
+		default: f64,
+
+		#[serde(default)]
+		min: Option<f64>,
+
+		#[serde(default)]
+		max: Option<f64>
+	}
+}
+
+impl SettingKind {
+	pub fn default_value(&self) -> Option<SettingValue> {
+		match self {
+			Self::String { default } | Self::Enum { default, .. } => Some(SettingValue::String(default.clone())),
+			Self::Path { default, .. } => default.clone().map(SettingValue::String),
+			Self::Boolean { default } => Some(SettingValue::Boolean(*default)),
+			Self::Number { default, .. } => Some(SettingValue::Number(*default))
+		}
+	}
+
+	/// Whether or not a value can be stored for a setting of this kind.
+	pub fn accepts(&self, value: &SettingValue) -> bool {
+		match (self, value) {
+			(Self::String { .. } | Self::Path { .. }, SettingValue::String(_)) => true,
+			(Self::Enum { options, .. }, SettingValue::String(value)) => options.contains(value),
+			(Self::Boolean { .. }, SettingValue::Boolean(_)) => true,
+			(Self::Number { min, max, .. }, SettingValue::Number(value)) => {
+				min.map_or(true, |x| *value >= x) && max.map_or(true, |x| *value <= x)
+			},
+			_ => false
+		}
+	}
+
+	/// Reads a value of this kind from text, as written by the settings page.
+	pub fn parse(&self, value: &str) -> Option<SettingValue> {
+		let value = match self {
+			Self::String { .. } | Self::Path { .. } | Self::Enum { .. } => SettingValue::String(value.to_string()),
+			Self::Boolean { .. } => SettingValue::Boolean(value.parse().ok()?),
+			Self::Number { .. } => SettingValue::Number(value.parse().ok()?)
+		};
+		Some(value).filter(|x| self.accepts(x))
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SettingValue {
+	String(String),
+	Boolean(bool),
+	Number(f64)
+}
+
+impl SettingValue {
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Self::String(value) => Some(value),
+			_ => None
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Self::Boolean(value) => Some(*value),
+			_ => None
+		}
+	}
+
+	pub fn as_f64(&self) -> Option<f64> {
+		match self {
+			Self::Number(value) => Some(*value),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for SettingValue {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::String(value) => value.fmt(f),
+			Self::Boolean(value) => value.fmt(f),
+			Self::Number(value) => value.fmt(f)
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum SettingError {
+	UnknownKey(String),
+	InvalidValue(String),
+
+	/// Extensions can only change their own settings.
+	Forbidden(String),
+	Io(Arc<std::io::Error>)
+}
+
+impl fmt::Display for SettingError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::UnknownKey(key) => write!(f, "there is no setting called {}", key),
+			Self::InvalidValue(key) => write!(f, "invalid value for setting {}", key),
+			Self::Forbidden(id) => write!(f, "settings of {} belong to another extension", id),
+			Self::Io(err) => write!(f, "io error: {}", err)
+		}
+	}
+}
+
+impl std::error::Error for SettingError {}
+
+impl From<std::io::Error> for SettingError {
+	fn from(value: std::io::Error) -> Self {
+		Self::Io(Arc::new(value))
+	}
+}
+
+/// The settings an extension declared and the values the user picked, stored in `<State::path>/extension_settings/<id>.json`.
+pub struct ExtensionSettings {
+	pub schema: Vec<SettingDefinition>,
+	values: HashMap<String, SettingValue>,
+
+	#[cfg(feature = "core")]
+	path: PathBuf
+}
+
+impl ExtensionSettings {
+	/// Reads stored values, dropping any that no longer fit the schema.
+	#[cfg(feature = "core")]
+	pub fn load(path: PathBuf, schema: Vec<SettingDefinition>) -> Self {
+		let mut values: HashMap<String, SettingValue> = std::fs::read_to_string(&path)
+			.ok()
+			.and_then(|x| serde_json::from_str(&x).ok())
+			.unwrap_or_default();
+		values.retain(|key, value| schema.iter().any(|x| x.key == *key && x.kind.accepts(value)));
+
+		Self {
+			schema,
+			values,
+			path
+		}
+	}
+
+	pub fn definition(&self, key: &str) -> Option<&SettingDefinition> {
+		self.schema.iter().find(|x| x.key == key)
+	}
+
+	/// The stored value of a setting, or its default.
+	pub fn get(&self, key: &str) -> Option<SettingValue> {
+		self.values.get(key).cloned().or_else(|| self.definition(key)?.kind.default_value())
+	}
+
+	#[cfg(feature = "core")]
+	pub fn set(&mut self, key: &str, value: SettingValue) -> Result<(), SettingError> {
+		let definition = self.definition(key).ok_or_else(|| SettingError::UnknownKey(key.into()))?;
+		if !definition.kind.accepts(&value) {
+			return Err(SettingError::InvalidValue(key.into()));
+		}
+
+		self.values.insert(key.into(), value);
+		if let Some(parent) = self.path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		std::fs::write(&self.path, serde_json::to_string_pretty(&self.values).map_err(std::io::Error::from)?)?;
+		Ok(())
+	}
+
+	/// A translated label and an input for every setting, linked to the stored values.
+	#[cfg(feature = "ui")]
+	pub fn elements(&self, state: &State, id: &str) -> Vec<Element> {
+		self.schema.iter().flat_map(|definition| {
+			let value = Value::Link(ValueLink::ExtensionSetting { extension: id.into(), key: definition.key.clone() });
+			let input = match &definition.kind {
+				SettingKind::String { .. } => Element::TextInput { value },
+				SettingKind::Path { directory, .. } => Element::PathSelect {
+					kind: if *directory { PathSelectKind::Directory } else { PathSelectKind::File },
+					value
+				},
+				SettingKind::Boolean { .. } => Element::Checkbox { value },
+				SettingKind::Enum { options, .. } => Element::Dropdown { options: options.clone(), value },
+				SettingKind::Number { min, max, .. } => Element::NumberInput { min: *min, max: *max, value }
+			};
+			[Element::Text(Value::Static(state.t(definition.name.clone()))), input]
+		}).collect()
+	}
+}
\ No newline at end of file
@@ -0,0 +1,251 @@
+use std::fs;
+use std::fmt;
+use std::sync::Arc;
+use std::path::{ Path, PathBuf };
+use ed25519_dalek::{ Signature, VerifyingKey };
+use serde::{ Serialize, Deserialize };
+use sha2::{ Digest, Sha256 };
+
+use crate::downloader::ChecksumHasher;
+
+/// File extension of the detached signature that sits next to an extension library,
+/// `libexample.so` is signed by `libexample.sig`, which holds the 64 byte ed25519 signature of the library.
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Keeps track of which publishers and libraries the user trusts, inside of the extensions directory.
+pub const TRUST_FILE: &str = "trust.json";
+
+/// Publisher keys and unsigned libraries the user has chosen to trust.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+	/// Hexadecimal ed25519 public keys of trusted publishers.
+	#[serde(default)]
+	pub keys: Vec<String>,
+
+	/// Hexadecimal sha256 digests of libraries that may be loaded without a valid signature.
+	#[serde(default)]
+	pub libraries: Vec<String>,
+
+	#[serde(skip)]
+	path: Option<PathBuf>
+}
+
+impl TrustStore {
+	pub fn load(path: PathBuf) -> Self {
+		let mut store: Self = fs::read_to_string(&path)
+			.ok()
+			.and_then(|x| serde_json::from_str(&x).ok())
+			.unwrap_or_default();
+		store.path = Some(path);
+		store
+	}
+
+	pub fn save(&self) -> std::io::Result<()> {
+		if let Some(path) = &self.path {
+			if let Some(parent) = path.parent() {
+				fs::create_dir_all(parent)?;
+			}
+			fs::write(path, serde_json::to_string_pretty(self)?)?;
+		}
+		Ok(())
+	}
+
+	pub fn trust_key(&mut self, key: &str) -> Result<(), SignatureError> {
+		parse_key(key)?;
+		if !self.keys.iter().any(|x| x.eq_ignore_ascii_case(key)) {
+			self.keys.push(key.to_ascii_lowercase());
+		}
+		Ok(())
+	}
+
+	pub fn untrust_key(&mut self, key: &str) {
+		self.keys.retain(|x| !x.eq_ignore_ascii_case(key));
+	}
+
+	/// Allows this exact library to be loaded even though its signature is missing or not trusted,
+	/// any change to the file afterwards will make it untrusted again.
+	pub fn trust_library(&mut self, library: &Path) -> Result<(), SignatureError> {
+		let digest = sha256(&fs::read(library)?);
+		if !self.libraries.contains(&digest) {
+			self.libraries.push(digest);
+		}
+		Ok(())
+	}
+
+	/// Checks that a library is signed by a trusted publisher, or that the user trusts it anyway, returning its contents.
+	/// Load the library from these rather than from its path, which could have changed since.
+	pub fn verify(&self, library: &Path) -> Result<Vec<u8>, SignatureError> {
+		let bytes = fs::read(library)?;
+		if self.libraries.contains(&sha256(&bytes)) {
+			return Ok(bytes);
+		}
+
+		let signature_path = library.with_extension(SIGNATURE_EXTENSION);
+		if !signature_path.is_file() {
+			return Err(SignatureError::Unsigned);
+		}
+		let signature = Signature::from_slice(&fs::read(signature_path)?)
+			.map_err(|_| SignatureError::Malformed)?;
+
+		for key in &self.keys {
+			if parse_key(key)?.verify_strict(&bytes, &signature).is_ok() {
+				return Ok(bytes);
+			}
+		}
+		Err(SignatureError::Untrusted)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum SignatureError {
+	Unsigned,
+	Malformed,
+
+	/// The signature doesn't match the library with any trusted key, either it was tampered with or the publisher isn't trusted.
+	Untrusted,
+	InvalidKey(String),
+	Io(Arc<std::io::Error>)
+}
+
+impl fmt::Display for SignatureError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Unsigned => write!(f, "library is not signed"),
+			Self::Malformed => write!(f, "signature is malformed"),
+			Self::Untrusted => write!(f, "library was modified or is not signed by a trusted publisher"),
+			Self::InvalidKey(key) => write!(f, "{} is not a valid public key", key),
+			Self::Io(err) => write!(f, "io error: {}", err)
+		}
+	}
+}
+
+impl std::error::Error for SignatureError {}
+
+impl From<std::io::Error> for SignatureError {
+	fn from(value: std::io::Error) -> Self {
+		Self::Io(Arc::new(value))
+	}
+}
+
+fn parse_key(key: &str) -> Result<VerifyingKey, SignatureError> {
+	let bytes: [u8; 32] = decode_hex(key)
+		.and_then(|x| x.try_into().ok())
+		.ok_or_else(|| SignatureError::InvalidKey(key.into()))?;
+	VerifyingKey::from_bytes(&bytes).map_err(|_| SignatureError::InvalidKey(key.into()))
+}
+
+fn sha256(bytes: &[u8]) -> String {
+	let mut hasher = ChecksumHasher::Sha256(Sha256::new());
+	hasher.update(bytes);
+	hasher.finish()
+}
+
+pub(crate) fn decode_hex(value: &str) -> Option<Vec<u8>> {
+	if value.len() % 2 != 0 {
+		return None;
+	}
+	(0..value.len())
+		.step_by(2)
+		.map(|x| value.get(x..x + 2).and_then(|x| u8::from_str_radix(x, 16).ok()))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ed25519_dalek::{ Signer, SigningKey };
+	use crate::testing::temp_dir;
+
+	fn public_key(key: &SigningKey) -> String {
+		key.verifying_key().as_bytes().iter().fold(String::new(), |mut hex, x| {
+			let _ = std::fmt::Write::write_fmt(&mut hex, format_args!("{:02x}", x));
+			hex
+		})
+	}
+
+	fn signed_library(dir: &Path, key: &SigningKey) -> PathBuf {
+		let library = dir.join("libexample.so");
+		fs::write(&library, b"library").unwrap();
+		fs::write(library.with_extension(SIGNATURE_EXTENSION), key.sign(b"library").to_bytes()).unwrap();
+		library
+	}
+
+	#[test]
+	fn accepts_libraries_signed_by_trusted_keys() {
+		let dir = temp_dir("signature");
+		let key = SigningKey::from_bytes(&[1; 32]);
+		let library = signed_library(&dir, &key);
+
+		let mut store = TrustStore::default();
+		store.trust_key(&public_key(&key)).unwrap();
+		assert_eq!(store.verify(&library).unwrap(), b"library");
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_signatures_from_other_keys() {
+		let dir = temp_dir("signature");
+		let library = signed_library(&dir, &SigningKey::from_bytes(&[1; 32]));
+
+		let mut store = TrustStore::default();
+		store.trust_key(&public_key(&SigningKey::from_bytes(&[2; 32]))).unwrap();
+		assert!(matches!(store.verify(&library), Err(SignatureError::Untrusted)));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_tampered_libraries() {
+		let dir = temp_dir("signature");
+		let key = SigningKey::from_bytes(&[1; 32]);
+		let library = signed_library(&dir, &key);
+		fs::write(&library, b"tampered").unwrap();
+
+		let mut store = TrustStore::default();
+		store.trust_key(&public_key(&key)).unwrap();
+		assert!(matches!(store.verify(&library), Err(SignatureError::Untrusted)));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_unsigned_and_malformed_signatures() {
+		let dir = temp_dir("signature");
+		let library = dir.join("libexample.so");
+		fs::write(&library, b"library").unwrap();
+
+		let store = TrustStore::default();
+		assert!(matches!(store.verify(&library), Err(SignatureError::Unsigned)));
+
+		fs::write(library.with_extension(SIGNATURE_EXTENSION), b"short").unwrap();
+		assert!(matches!(store.verify(&library), Err(SignatureError::Malformed)));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn trusts_exact_libraries_only() {
+		let dir = temp_dir("signature");
+		let library = dir.join("libexample.so");
+		fs::write(&library, b"library").unwrap();
+
+		let mut store = TrustStore::default();
+		store.trust_library(&library).unwrap();
+		assert_eq!(store.verify(&library).unwrap(), b"library");
+
+		fs::write(&library, b"tampered").unwrap();
+		assert!(matches!(store.verify(&library), Err(SignatureError::Unsigned)));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_invalid_keys() {
+		let mut store = TrustStore::default();
+		assert!(matches!(store.trust_key("abc"), Err(SignatureError::InvalidKey(_))));
+		assert!(matches!(store.trust_key(&"zz".repeat(32)), Err(SignatureError::InvalidKey(_))));
+
+		let key = public_key(&SigningKey::from_bytes(&[1; 32]));
+		store.trust_key(&key.to_ascii_uppercase()).unwrap();
+		store.trust_key(&key).unwrap();
+		assert_eq!(store.keys, vec![key.clone()]);
+		store.untrust_key(&key);
+		assert!(store.keys.is_empty());
+	}
+}
\ No newline at end of file
@@ -0,0 +1,818 @@
+//! Extensions compiled to WebAssembly, run inside of an interpreter so they can only reach what HERO hands them,
+//! and don't need to be built for every platform.
+//!
+//! A module exports its `memory` along with:
+//! - `hero_api_version() -> i32`, returning [`WASM_API_VERSION`]
+//! - `hero_alloc(len: i32) -> i32`, reserving memory for HERO to write a request into
+//! - `hero_describe() -> i64`, returning a [`WasmDescription`]
+//! - `hero_call(ptr: i32, len: i32) -> i64`, handling a [`WasmCall`] and returning its response
+//! - `hero_setup()`, optionally
+//!
+//! Data is exchanged as JSON, returned data is packed with the pointer in the upper 32 bits and the length in the lower 32 bits.
+//! HERO provides `hero.log(ptr: i32, len: i32)` to modules, along with:
+//! - `hero.download(ptr: i32, len: i32) -> i32`, starting a [`DownloadRequest`] if the extension has been granted its capabilities.
+//!   Returns an identifier for the download, or [`WASM_INVALID`] if the request couldn't be read and [`WASM_REFUSED`] if it wasn't allowed.
+//! - `hero.download_status(id: i32) -> i32`, returning one of the `WASM_DOWNLOAD_*` codes, or [`WASM_INVALID`] for an unknown download.
+use std::fmt;
+use std::sync::Arc;
+use std::path::Path;
+use std::time::Duration;
+use std::collections::HashMap;
+use uuid::Uuid;
+use parking_lot::Mutex;
+use serde::{ Serialize, Deserialize, de::DeserializeOwned };
+use wasmi::{ Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc, Caller };
+
+use super::{ Extension, SettingDefinition, SettingValue };
+use crate::{ Icon, IconData };
+use crate::game::Game;
+use crate::state::State;
+use crate::instance::Instance;
+use crate::downloader::{ Download, DownloadError, DownloadRequest, DownloadStatus };
+use crate::content::{ ContentFile, ContentPage, ContentProvider, ContentProviderItem, ContentProviderSearchSortType };
+
+#[cfg(feature = "ui")]
+use crate::user_interface::{ Container, ContainerKind, Element, PathSelectKind, Value, ValueLink };
+
+/// Version of the interface between HERO and WebAssembly extensions.
+pub const WASM_API_VERSION: u32 = 2;
+
+/// Returned by host functions given something they can't make sense of.
+pub const WASM_INVALID: i32 = -1;
+
+/// Returned by `hero.download` when the extension isn't allowed to make the download.
+pub const WASM_REFUSED: i32 = -2;
+
+pub const WASM_DOWNLOAD_QUEUED: i32 = 0;
+pub const WASM_DOWNLOAD_RUNNING: i32 = 1;
+pub const WASM_DOWNLOAD_PAUSED: i32 = 2;
+pub const WASM_DOWNLOAD_FINISHED: i32 = 3;
+pub const WASM_DOWNLOAD_STOPPED: i32 = 4;
+pub const WASM_DOWNLOAD_FAILED: i32 = 5;
+
+/// How many instructions, roughly, a module may run for each call before it is stopped.
+const FUEL_PER_CALL: u64 = 500_000_000;
+
+/// How large a module's memory may grow, in bytes.
+const MAX_MEMORY: usize = 256 * 1024 * 1024;
+
+/// Everything a module provides, returned once by `hero_describe`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WasmDescription {
+	pub id: String,
+
+	#[serde(default)]
+	pub icon: Option<Vec<u8>>,
+
+	#[serde(default)]
+	pub games: Vec<WasmGameDescription>,
+
+	/// Translations by locale, then by key.
+	#[serde(default)]
+	pub localisation: HashMap<String, HashMap<String, String>>,
+
+	#[serde(default)]
+	pub content_providers: Vec<WasmContentProviderDescription>,
+
+	#[serde(default)]
+	pub ui_containers: Vec<WasmContainer>,
+
+	#[serde(default)]
+	pub settings: Vec<SettingDefinition>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WasmGameDescription {
+	pub id: String,
+
+	#[serde(default)]
+	pub icon: Option<Vec<u8>>,
+
+	/// Names of the content pages this game has.
+	#[serde(default)]
+	pub content_pages: Vec<String>,
+
+	#[serde(default)]
+	pub creation_options: Vec<SettingDefinition>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WasmContentProviderDescription {
+	pub name: String,
+
+	#[serde(default)]
+	pub supported_types: Vec<String>,
+
+	/// How long search queries should be cached for, in seconds.
+	#[serde(default)]
+	pub cache_duration: Option<u64>
+}
+
+/// A declarative version of [`crate::user_interface::Container`], shown on the instance page.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WasmContainer {
+	pub id: String,
+
+	#[serde(default)]
+	pub icon: Option<Vec<u8>>,
+
+	/// Only show this container for instances of these games.
+	#[serde(default)]
+	pub games: Option<Vec<String>>,
+	pub elements: Vec<WasmElement>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WasmElement {
+	Text {
+		value: WasmValue
+	},
+	PathSelect {
+		#[serde(default)]
+		directory: bool,
+		value: WasmValue
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WasmValue {
+	Static(String),
+	InstanceGameMeta(String)
+}
+
+/// A request for a module to do something, sent to `hero_call`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "call", rename_all = "snake_case")]
+pub enum WasmCall {
+	PreLoad {
+		game: String
+	},
+
+	/// Responds with a [`WasmCommand`] for HERO to start, or null.
+	Launch {
+		game: String,
+		instance: WasmInstance
+	},
+	Event(WasmEvent),
+
+	/// Responds with a list of [`ContentFile`].
+	ContentItems {
+		game: String,
+		page: String,
+		instance: WasmInstance
+	},
+
+	/// Responds with a list of [`ContentProviderItem`].
+	Search {
+		provider: String,
+		query: String,
+		sort: &'static str,
+		descending: bool
+	},
+	Install {
+		provider: String,
+		instance: WasmInstance,
+		item_id: String
+	}
+}
+
+/// One of the [`Extension`] hooks, sent to `hero_call` as `{ "call": "event", "event": ... }`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WasmEvent {
+	GameSelected {
+		game: String
+	},
+	InstancesLoaded {
+		game: String,
+		instances: Vec<Uuid>
+	},
+	BeforeLaunch {
+		instance: WasmInstance
+	},
+	AfterLaunch {
+		instance: WasmInstance
+	},
+	ProcessExited {
+		instance: WasmInstance,
+		exit_code: Option<i32>
+	},
+	ContentInstalled {
+		instance: WasmInstance,
+		item_id: String
+	},
+
+	/// Current value of every setting, sent right after `hero_setup`.
+	Settings {
+		values: HashMap<String, SettingValue>
+	},
+	SettingChanged {
+		key: String,
+		value: SettingValue
+	},
+	Shutdown
+}
+
+/// A program for HERO to start when launching a game, which requires the process capability.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WasmCommand {
+	pub program: String,
+
+	#[serde(default)]
+	pub args: Vec<String>,
+
+	#[serde(default)]
+	pub working_directory: Option<String>
+}
+
+/// What a module gets to know about an instance.
+#[derive(Clone, Debug, Serialize)]
+pub struct WasmInstance {
+	pub id: Uuid,
+	pub name: String,
+	pub author: String,
+	pub game_meta: HashMap<String, String>
+}
+
+impl From<&Instance> for WasmInstance {
+	fn from(value: &Instance) -> Self {
+		Self {
+			id: value.id(),
+			name: value.display_name(),
+			author: value.metadata.author.clone(),
+			game_meta: value.game_meta.items().clone()
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct WasmError(pub String);
+
+impl fmt::Display for WasmError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+impl std::error::Error for WasmError {}
+
+impl From<wasmi::Error> for WasmError {
+	fn from(value: wasmi::Error) -> Self {
+		Self(value.to_string())
+	}
+}
+
+impl From<wasmi::errors::LinkerError> for WasmError {
+	fn from(value: wasmi::errors::LinkerError) -> Self {
+		Self(value.to_string())
+	}
+}
+
+impl From<wasmi::core::Trap> for WasmError {
+	fn from(value: wasmi::core::Trap) -> Self {
+		Self(value.to_string())
+	}
+}
+
+struct WasmHost {
+	id: String,
+	limits: StoreLimits,
+
+	/// Downloads the module started, their index is the identifier it was given.
+	downloads: Vec<Download>
+}
+
+struct WasmInner {
+	store: Store<WasmHost>,
+	memory: Memory,
+	alloc: TypedFunc<i32, i32>,
+	describe: TypedFunc<(), i64>,
+	call: TypedFunc<(i32, i32), i64>,
+	setup: Option<TypedFunc<(), ()>>
+}
+
+/// An instantiated module, shared by everything it provides.
+pub struct WasmRuntime {
+	inner: Mutex<WasmInner>
+}
+
+impl WasmRuntime {
+	pub fn new(path: &Path) -> Result<Self, WasmError> {
+		let bytes = std::fs::read(path).map_err(|err| WasmError(err.to_string()))?;
+		let mut config = Config::default();
+		config.consume_fuel(true);
+
+		let engine = Engine::new(&config);
+		let module = Module::new(&engine, &bytes[..])?;
+		let mut store = Store::new(&engine, WasmHost {
+			id: path.file_stem().map(|x| x.to_string_lossy().into_owned()).unwrap_or_default(),
+			limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build(),
+			downloads: vec![]
+		});
+		store.limiter(|x| &mut x.limits);
+
+		let mut linker = <Linker<WasmHost>>::new(&engine);
+		linker.func_wrap("hero", "log", |caller: Caller<'_, WasmHost>, ptr: i32, len: i32| {
+			if let Some(data) = read_guest(&caller, ptr, len) {
+				println!("[{}] {}", caller.data().id, String::from_utf8_lossy(&data));
+			}
+		})?;
+		linker.func_wrap("hero", "download", |mut caller: Caller<'_, WasmHost>, ptr: i32, len: i32| -> i32 {
+			let request = read_guest(&caller, ptr, len).and_then(|x| serde_json::from_slice::<DownloadRequest>(&x).ok());
+			start_download(&mut caller.data_mut().downloads, request)
+		})?;
+		linker.func_wrap("hero", "download_status", |caller: Caller<'_, WasmHost>, id: i32| -> i32 {
+			download_status(&caller.data().downloads, id)
+		})?;
+
+		store.add_fuel(FUEL_PER_CALL).map_err(|err| WasmError(err.to_string()))?;
+		let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+		let memory = instance.get_memory(&store, "memory")
+			.ok_or_else(|| WasmError("module does not export its memory".into()))?;
+
+		let api_version = instance.get_typed_func::<(), i32>(&store, "hero_api_version")?.call(&mut store, ())?;
+		if api_version as u32 != WASM_API_VERSION {
+			return Err(WasmError(format!("built for API version {}, expected {}", api_version, WASM_API_VERSION)));
+		}
+
+		Ok(Self {
+			inner: Mutex::new(WasmInner {
+				alloc: instance.get_typed_func(&store, "hero_alloc")?,
+				describe: instance.get_typed_func(&store, "hero_describe")?,
+				call: instance.get_typed_func(&store, "hero_call")?,
+				setup: instance.get_typed_func(&store, "hero_setup").ok(),
+				memory,
+				store
+			})
+		})
+	}
+
+	pub fn describe(&self) -> Result<WasmDescription, WasmError> {
+		let mut inner = self.inner.lock();
+		inner.refuel()?;
+
+		let describe = inner.describe;
+		let result = describe.call(&mut inner.store, ())?;
+		inner.read_json(result)
+	}
+
+	pub fn setup(&self) -> Result<(), WasmError> {
+		let mut inner = self.inner.lock();
+		if let Some(setup) = inner.setup {
+			inner.refuel()?;
+			setup.call(&mut inner.store, ())?;
+		}
+		Ok(())
+	}
+
+	pub fn call<T: DeserializeOwned>(&self, call: &WasmCall) -> Result<T, WasmError> {
+		let data = serde_json::to_vec(call).map_err(|err| WasmError(err.to_string()))?;
+		let mut inner = self.inner.lock();
+		inner.refuel()?;
+
+		let alloc = inner.alloc;
+		let ptr = alloc.call(&mut inner.store, data.len() as i32)?;
+		let memory = inner.memory;
+		memory.write(&mut inner.store, ptr as usize, &data).map_err(|err| WasmError(err.to_string()))?;
+
+		let call = inner.call;
+		let result = call.call(&mut inner.store, (ptr, data.len() as i32))?;
+		inner.read_json(result)
+	}
+}
+
+impl WasmInner {
+	/// Tops the module back up to a full allowance of fuel, so no single call can run forever.
+	fn refuel(&mut self) -> Result<(), WasmError> {
+		let remaining = self.store.consume_fuel(0).map_err(|err| WasmError(err.to_string()))?;
+		self.store.add_fuel(FUEL_PER_CALL.saturating_sub(remaining)).map_err(|err| WasmError(err.to_string()))
+	}
+
+	fn read_json<T: DeserializeOwned>(&self, packed: i64) -> Result<T, WasmError> {
+		let ptr = (packed as u64 >> 32) as usize;
+		let len = (packed as u64 & 0xFFFF_FFFF) as usize;
+		let data = self.memory.data(&self.store)
+			.get(ptr..ptr + len)
+			.ok_or_else(|| WasmError("module returned data outside of its memory".into()))?;
+		serde_json::from_slice(data).map_err(|err| WasmError(err.to_string()))
+	}
+}
+
+/// Copies data out of a module's memory, nothing is allocated unless it lies entirely inside of it.
+fn read_guest(caller: &Caller<'_, WasmHost>, ptr: i32, len: i32) -> Option<Vec<u8>> {
+	let memory = caller.get_export("memory").and_then(|x| x.into_memory())?;
+	guest_slice(memory.data(caller), ptr, len).map(<[u8]>::to_vec)
+}
+
+fn guest_slice(memory: &[u8], ptr: i32, len: i32) -> Option<&[u8]> {
+	let ptr = usize::try_from(ptr).ok()?;
+	let len = usize::try_from(len).ok()?;
+	memory.get(ptr..ptr.checked_add(len)?)
+}
+
+/// Starts a download for a module as the acting extension, returning the identifier it gets to refer to it by.
+fn start_download(downloads: &mut Vec<Download>, request: Option<DownloadRequest>) -> i32 {
+	let Some(request) = request else {
+		return WASM_INVALID;
+	};
+	let download = State::get().download(request);
+	if matches!(download.status(), DownloadStatus::Failed(DownloadError::Forbidden(_) | DownloadError::InvalidChecksum(_))) {
+		return WASM_REFUSED;
+	}
+
+	downloads.push(download);
+	downloads.len() as i32 - 1
+}
+
+fn download_status(downloads: &[Download], id: i32) -> i32 {
+	let Some(download) = usize::try_from(id).ok().and_then(|x| downloads.get(x)) else {
+		return WASM_INVALID;
+	};
+	match download.status() {
+		DownloadStatus::Queued => WASM_DOWNLOAD_QUEUED,
+		DownloadStatus::Downloading | DownloadStatus::Extracting => WASM_DOWNLOAD_RUNNING,
+		DownloadStatus::Paused => WASM_DOWNLOAD_PAUSED,
+		DownloadStatus::Finished => WASM_DOWNLOAD_FINISHED,
+		DownloadStatus::Stopped => WASM_DOWNLOAD_STOPPED,
+		DownloadStatus::Failed(_) => WASM_DOWNLOAD_FAILED
+	}
+}
+
+/// The trait methods these implement can't fail, so errors from a module are logged and replaced with nothing.
+fn log_error<T: Default>(id: &str, result: Result<T, WasmError>) -> T {
+	result.unwrap_or_else(|err| {
+		println!("wasm extension {} failed! {}", id, err);
+		T::default()
+	})
+}
+
+/// The extension traits hand out `&'static str`, so strings from a module are leaked once when it is loaded.
+fn leak(value: &str) -> &'static str {
+	Box::leak(value.to_string().into_boxed_str())
+}
+
+fn icon_data(id: &str, icon: &Option<Vec<u8>>) -> Option<IconData> {
+	icon.as_ref().map(|bytes| IconData {
+		path: format!("bytes://wasm/{}", id),
+		bytes: bytes.clone()
+	})
+}
+
+pub struct WasmExtension {
+	id: &'static str,
+	runtime: Arc<WasmRuntime>,
+	description: WasmDescription,
+	games: Vec<Arc<Box<dyn Game>>>,
+	localisation: Vec<(&'static str, Vec<(&'static str, &'static str)>)>
+}
+
+impl WasmExtension {
+	pub fn new(runtime: Arc<WasmRuntime>) -> Result<Self, WasmError> {
+		let description = runtime.describe()?;
+		let id = leak(&description.id);
+		let games = description.games.iter().map(|x| Arc::new(Box::new(WasmGame {
+			id: leak(&x.id),
+			icon: icon_data(&x.id, &x.icon),
+			content_pages: x.content_pages.iter().map(|x| leak(x)).collect(),
+			creation_options: x.creation_options.clone(),
+			runtime: runtime.clone()
+		}) as Box<dyn Game>)).collect();
+		let localisation = description.localisation.iter().map(|(locale, data)| {
+			(leak(locale), data.iter().map(|(key, value)| (leak(key), leak(value))).collect())
+		}).collect();
+
+		Ok(Self {
+			id,
+			runtime,
+			description,
+			games,
+			localisation
+		})
+	}
+}
+
+impl WasmExtension {
+	fn event(&self, event: WasmEvent) {
+		log_error::<()>(self.id, self.runtime.call(&WasmCall::Event(event)));
+	}
+}
+
+impl Icon for WasmExtension {
+	fn icon(&self) -> Option<IconData> {
+		icon_data(self.id, &self.description.icon)
+	}
+}
+
+impl Extension for WasmExtension {
+	fn id(&self) -> &'static str {
+		self.id
+	}
+
+	fn setup(&self) {
+		log_error(self.id, self.runtime.setup());
+
+		let state = State::get();
+		let values = self.description.settings.iter()
+			.filter_map(|x| Some((x.key.clone(), state.extension_setting(self.id, &x.key)?)))
+			.collect();
+		self.event(WasmEvent::Settings { values });
+	}
+
+	fn on_game_selected(&self, game_id: &str) {
+		self.event(WasmEvent::GameSelected { game: game_id.into() });
+	}
+
+	fn on_instances_loaded(&self, game_id: &str, instances: &[Uuid]) {
+		self.event(WasmEvent::InstancesLoaded { game: game_id.into(), instances: instances.to_vec() });
+	}
+
+	fn before_launch(&self, instance: &Instance) {
+		self.event(WasmEvent::BeforeLaunch { instance: instance.into() });
+	}
+
+	fn after_launch(&self, instance: &Instance) {
+		self.event(WasmEvent::AfterLaunch { instance: instance.into() });
+	}
+
+	fn on_process_exited(&self, instance: &Instance, exit_code: Option<i32>) {
+		self.event(WasmEvent::ProcessExited { instance: instance.into(), exit_code });
+	}
+
+	fn on_content_installed(&self, instance: &Instance, item_id: &str) {
+		self.event(WasmEvent::ContentInstalled { instance: instance.into(), item_id: item_id.into() });
+	}
+
+	fn on_shutdown(&self) {
+		self.event(WasmEvent::Shutdown);
+	}
+
+	fn settings(&self) -> Vec<SettingDefinition> {
+		self.description.settings.clone()
+	}
+
+	fn on_setting_changed(&self, key: &str, value: &SettingValue) {
+		self.event(WasmEvent::SettingChanged { key: key.into(), value: value.clone() });
+	}
+
+	fn games(&self) -> Option<Vec<Arc<Box<dyn Game>>>> {
+		if self.games.is_empty() { None } else { Some(self.games.clone()) }
+	}
+
+	fn localisation(&self) -> Vec<(&'static str, Vec<(&'static str, &'static str)>)> {
+		self.localisation.clone()
+	}
+
+	#[cfg(feature = "ui")]
+	fn ui_containers(&self) -> Vec<Container> {
+		self.description.ui_containers.iter().map(|container| {
+			let games = container.games.clone();
+			let elements = container.elements.clone();
+			Container {
+				id: leak(&container.id),
+				icon: icon_data(&container.id, &container.icon).unwrap_or(IconData {
+					path: format!("bytes://wasm/{}", container.id),
+					bytes: vec![]
+				}),
+				kind: ContainerKind::InstancePage(games.map(|games| -> crate::user_interface::InstancePageFilter {
+					Box::new(move |_, game| games.iter().any(|x| x == game.id()))
+				})),
+				render: Box::new(move || elements.iter().map(|x| x.clone().into()).collect())
+			}
+		}).collect()
+	}
+
+	fn content_providers(&self) -> Vec<Box<dyn ContentProvider>> {
+		self.description.content_providers.iter().map(|x| Box::new(WasmContentProvider {
+			name: leak(&x.name),
+			supported_types: x.supported_types.iter().map(|x| leak(x)).collect(),
+			cache_duration: x.cache_duration.map(Duration::from_secs),
+			runtime: self.runtime.clone()
+		}) as Box<dyn ContentProvider>).collect()
+	}
+}
+
+pub struct WasmGame {
+	id: &'static str,
+	icon: Option<IconData>,
+	content_pages: Vec<&'static str>,
+	creation_options: Vec<SettingDefinition>,
+	runtime: Arc<WasmRuntime>
+}
+
+impl Icon for WasmGame {
+	fn icon(&self) -> Option<IconData> {
+		self.icon.clone()
+	}
+}
+
+impl Game for WasmGame {
+	fn id(&self) -> &'static str {
+		self.id
+	}
+
+	fn launch(&self, instance: &Instance) -> Option<std::process::Child> {
+		let command: Option<WasmCommand> = log_error(self.id, self.runtime.call(&WasmCall::Launch {
+			game: self.id.into(),
+			instance: instance.into()
+		}));
+
+		let command = command?;
+		let mut process = std::process::Command::new(&command.program);
+		process.args(&command.args);
+		if let Some(directory) = &command.working_directory {
+			process.current_dir(directory);
+		}
+		State::get().spawn(&mut process)
+			.map_err(|err| println!("wasm extension {} failed to launch {:?}! {}", self.id, command.program, err))
+			.ok()
+	}
+
+	fn creation_options(&self) -> Vec<SettingDefinition> {
+		self.creation_options.clone()
+	}
+
+	fn pre_load(&self) {
+		log_error::<()>(self.id, self.runtime.call(&WasmCall::PreLoad {
+			game: self.id.into()
+		}));
+	}
+
+	fn content_pages(&self) -> Vec<Box<dyn ContentPage>> {
+		self.content_pages.iter().copied().map(|name| Box::new(WasmContentPage {
+			game: self.id,
+			name,
+			runtime: self.runtime.clone()
+		}) as Box<dyn ContentPage>).collect()
+	}
+}
+
+pub struct WasmContentPage {
+	game: &'static str,
+	name: &'static str,
+	runtime: Arc<WasmRuntime>
+}
+
+impl ContentPage for WasmContentPage {
+	fn name(&self) -> &'static str {
+		self.name
+	}
+
+	fn items(&self, instance: &Instance) -> Vec<ContentFile> {
+		log_error(self.game, self.runtime.call(&WasmCall::ContentItems {
+			game: self.game.into(),
+			page: self.name.into(),
+			instance: instance.into()
+		}))
+	}
+}
+
+pub struct WasmContentProvider {
+	name: &'static str,
+	supported_types: Vec<&'static str>,
+	cache_duration: Option<Duration>,
+	runtime: Arc<WasmRuntime>
+}
+
+impl ContentProvider for WasmContentProvider {
+	fn name(&self) -> &'static str {
+		self.name
+	}
+
+	fn search(&mut self, query: String, sort_type: ContentProviderSearchSortType, descending: bool) -> Vec<ContentProviderItem> {
+		let sort = match sort_type {
+			ContentProviderSearchSortType::Relevance => "relevance",
+			ContentProviderSearchSortType::DownloadCount => "download_count",
+			ContentProviderSearchSortType::Rating => "rating",
+			ContentProviderSearchSortType::RecentlyPublished => "recently_published",
+			ContentProviderSearchSortType::RecentlyUpdated => "recently_updated"
+		};
+		log_error(self.name, self.runtime.call(&WasmCall::Search {
+			provider: self.name.into(),
+			query,
+			sort,
+			descending
+		}))
+	}
+
+	fn install(&mut self, instance: &Instance, item_id: &str) {
+		log_error::<()>(self.name, self.runtime.call(&WasmCall::Install {
+			provider: self.name.into(),
+			instance: instance.into(),
+			item_id: item_id.into()
+		}));
+	}
+
+	fn cache_duration(&self) -> Option<Duration> {
+		self.cache_duration
+	}
+
+	fn supported_types(&self) -> Vec<&'static str> {
+		self.supported_types.clone()
+	}
+}
+
+#[cfg(feature = "ui")]
+impl From<WasmElement> for Element {
+	fn from(value: WasmElement) -> Self {
+		match value {
+			WasmElement::Text { value } => Element::Text(value.into()),
+			WasmElement::PathSelect { directory, value } => Element::PathSelect {
+				kind: if directory { PathSelectKind::Directory } else { PathSelectKind::File },
+				value: value.into()
+			}
+		}
+	}
+}
+
+#[cfg(feature = "ui")]
+impl From<WasmValue> for Value<String> {
+	fn from(value: WasmValue) -> Self {
+		match value {
+			WasmValue::Static(value) => Value::Static(value),
+			WasmValue::InstanceGameMeta(key) => Value::Link(ValueLink::InstanceGameMeta(key))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[cfg(feature = "testing")]
+	use crate::extension::{ act_as, Capability };
+	#[cfg(feature = "testing")]
+	use crate::extension::capability::tests::permissions;
+
+	/// Downloads the request written at 1024 when set up, with its length at 0,
+	/// storing the identifier it got back at 4 and the status of that download at 8.
+	#[cfg(feature = "testing")]
+	const DOWNLOADING_MODULE: &str = r#"(module
+		(import "hero" "download" (func $download (param i32 i32) (result i32)))
+		(import "hero" "download_status" (func $download_status (param i32) (result i32)))
+		(memory (export "memory") 1)
+		(func (export "hero_api_version") (result i32) (i32.const API_VERSION))
+		(func (export "hero_alloc") (param i32) (result i32) (i32.const 1024))
+		(func (export "hero_describe") (result i64) (i64.const 0))
+		(func (export "hero_call") (param i32 i32) (result i64) (i64.const 0))
+		(func (export "hero_setup")
+			(i32.store (i32.const 4) (call $download (i32.const 1024) (i32.load (i32.const 0))))
+			(i32.store (i32.const 8) (call $download_status (i32.load (i32.const 4))))))"#;
+
+	/// Sets the module up as an extension granted `granted`, returning the identifier and status its download got.
+	#[cfg(feature = "testing")]
+	fn download_from_module(runtime: &WasmRuntime, request: &[u8], granted: Vec<Capability>) -> (i32, i32) {
+		let mut inner = runtime.inner.lock();
+		let memory = inner.memory;
+		memory.write(&mut inner.store, 0, &(request.len() as i32).to_le_bytes()).unwrap();
+		memory.write(&mut inner.store, 1024, request).unwrap();
+		drop(inner);
+
+		act_as(Some(Arc::new(permissions(granted.clone(), granted))), || runtime.setup()).unwrap();
+		let inner = runtime.inner.lock();
+		let data = inner.memory.data(&inner.store);
+		let read = |offset: usize| i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+		(read(4), read(8))
+	}
+
+	#[test]
+	#[cfg(feature = "testing")]
+	fn hands_modules_an_identifier_for_their_downloads() {
+		let test = crate::testing::TestState::new();
+		test.state().downloader().unwrap().set_retry_policy(crate::downloader::RetryPolicy::none());
+		let path = test.path().join("test.wasm");
+		std::fs::write(&path, wat::parse_str(DOWNLOADING_MODULE.replace("API_VERSION", &WASM_API_VERSION.to_string())).unwrap()).unwrap();
+		let runtime = WasmRuntime::new(&path).unwrap();
+
+		let request = serde_json::to_vec(&DownloadRequest::new("http://127.0.0.1:1/file", test.path().join("file"))).unwrap();
+		let everything = vec![Capability::Network("*".into()), Capability::Filesystem("/".into())];
+		assert_eq!(download_from_module(&runtime, b"not a request", everything.clone()), (WASM_INVALID, WASM_INVALID));
+		assert_eq!(download_from_module(&runtime, &request, vec![]), (WASM_REFUSED, WASM_INVALID));
+
+		let (id, status) = download_from_module(&runtime, &request, everything.clone());
+		assert_eq!(id, 0);
+		assert!([WASM_DOWNLOAD_QUEUED, WASM_DOWNLOAD_RUNNING, WASM_DOWNLOAD_FAILED].contains(&status), "{}", status);
+		assert_eq!(download_from_module(&runtime, &request, everything).0, 1);
+
+		let downloads = runtime.inner.lock().store.data().downloads.clone();
+		assert!(downloads.iter().all(|x| x.extension() == Some("test")));
+		for download in &downloads {
+			let _ = download.wait_blocking();
+		}
+		assert_eq!(download_status(&downloads, 0), WASM_DOWNLOAD_FAILED);
+		assert_eq!(download_status(&downloads, 2), WASM_INVALID);
+	}
+
+	#[test]
+	fn reads_guest_memory_within_bounds_only() {
+		let memory = [1, 2, 3, 4];
+		assert_eq!(guest_slice(&memory, 1, 2), Some(&[2, 3][..]));
+		assert_eq!(guest_slice(&memory, 0, 4), Some(&memory[..]));
+		assert_eq!(guest_slice(&memory, 4, 0), Some(&[][..]));
+		assert_eq!(guest_slice(&memory, 3, 2), None);
+		assert_eq!(guest_slice(&memory, 0, i32::MAX), None);
+		assert_eq!(guest_slice(&memory, -1, 1), None);
+		assert_eq!(guest_slice(&memory, 0, -1), None);
+	}
+}
\ No newline at end of file
@@ -1,15 +1,26 @@
 use crate::Icon;
 use crate::content::ContentPage;
 use crate::instance::Instance;
+use crate::extension::SettingDefinition;
 
 pub trait Game: Icon + Sync + Send {
 	/// The unique identifier for your game.
 	fn id(&self) -> &'static str;
 
-	fn launch(&self, instance: &Instance);
+	/// Starts the game, returning its process so HERO can tell extensions when it exits.
+	fn launch(&self, instance: &Instance) -> Option<std::process::Child>;
 
 	fn pre_load(&self) {}
 
+	/// Choices offered when creating an instance, such as the version or edition to play.
+	/// The picked values end up in the instance's `game_meta`, under the same keys.
+	fn creation_options(&self) -> Vec<SettingDefinition> {
+		Vec::new()
+	}
+
+	/// Runs after HERO has created an instance of this game.
+	fn on_instance_created(&self, _instance: &Instance) {}
+
 	fn content_pages(&self) -> Vec<Box<dyn ContentPage>> {
 		Vec::new()
 	}
@@ -0,0 +1,295 @@
+use std::io::{ Read, Write };
+use std::fs::File;
+use std::path::{ Component, Path };
+use std::time::SystemTime;
+use std::collections::HashSet;
+use uuid::Uuid;
+use serde::{ Serialize, Deserialize };
+
+use super::{ Instance, InstanceError, INSTANCES_DIRECTORY, METADATA_FILE, directory_name, parse_metadata, unused_directory };
+use crate::state::State;
+use crate::downloader::{ ArchiveFormat, ExtractError, Extraction, extract_archive };
+
+/// Version of the archive layout written by [`export_instance`], bumped whenever it changes.
+pub const INSTANCE_ARCHIVE_VERSION: u32 = 1;
+
+/// File extension of exported instances, they are zip archives underneath.
+pub const INSTANCE_ARCHIVE_EXTENSION: &str = "heroinstance";
+
+/// Name of the manifest at the root of an archive, the instance's directory is stored next to it under `instance/`.
+const MANIFEST_FILE: &str = "hero_instance.json";
+const INSTANCE_PREFIX: &str = "instance";
+
+/// Describes an exported instance.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InstanceArchiveManifest {
+	pub format_version: u32,
+	pub game_id: String,
+
+	/// Identifier of the exported instance, replaced on import if it is already taken.
+	pub id: Uuid,
+	pub name: String,
+
+	#[serde(with = "super::unix_seconds")]
+	pub exported_at: SystemTime,
+
+	/// Version of `hero-core` that exported the instance.
+	pub hero_version: String
+}
+
+impl From<zip::result::ZipError> for InstanceError {
+	fn from(value: zip::result::ZipError) -> Self {
+		match value {
+			zip::result::ZipError::Io(err) => err.into(),
+			other => Self::InvalidArchive(other.to_string())
+		}
+	}
+}
+
+impl From<ExtractError> for InstanceError {
+	fn from(value: ExtractError) -> Self {
+		match value {
+			ExtractError::Io(err) => Self::Io(err),
+			other => Self::InvalidArchive(other.to_string())
+		}
+	}
+}
+
+/// Writes an instance, along with everything inside of its directory, to a single compressed archive.
+pub fn export_instance(id: &Uuid, destination: &Path) -> Result<(), InstanceError> {
+	let state = State::get();
+	let instances = state.instances.read();
+	let instance = instances.items.get(id).ok_or(InstanceError::NotFound(*id))?;
+
+	let result = write_archive(instance, destination);
+	if result.is_err() {
+		let _ = std::fs::remove_file(destination);
+	}
+	result
+}
+
+fn write_archive(instance: &Instance, destination: &Path) -> Result<(), InstanceError> {
+	let manifest = InstanceArchiveManifest {
+		format_version: INSTANCE_ARCHIVE_VERSION,
+		game_id: instance.game_id.clone(),
+		id: instance.id(),
+		name: instance.display_name(),
+		exported_at: SystemTime::now(),
+		hero_version: env!("CARGO_PKG_VERSION").into()
+	};
+
+	let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+	let mut zip = zip::ZipWriter::new(File::create(destination)?);
+	zip.start_file(MANIFEST_FILE, options)?;
+	zip.write_all(serde_json::to_string_pretty(&manifest).map_err(std::io::Error::from)?.as_bytes())?;
+
+	// an icon kept outside of the instance would be lost, so it is brought along and the archived metadata points to the copy
+	let outside_icon = instance.metadata.icon_path.as_ref()
+		.filter(|x| !x.components().all(|x| matches!(x, Component::Normal(_) | Component::CurDir)))
+		.map(|x| instance.path.join(x));
+	if let Some(icon) = &outside_icon {
+		let mut metadata = instance.metadata.clone();
+		if icon.is_file() {
+			let extension = icon.extension().map(|x| format!(".{}", x.to_string_lossy())).unwrap_or_default();
+			let name = (1..)
+				.map(|x| if x == 1 { format!("icon{}", extension) } else { format!("icon {}{}", x, extension) })
+				.find(|x| !instance.path.join(x).exists())
+				.unwrap();
+			zip.start_file(format!("{}/{}", INSTANCE_PREFIX, name), options)?;
+			std::io::copy(&mut File::open(icon)?, &mut zip)?;
+			metadata.icon_path = Some(name.into());
+		} else {
+			println!("icon {:?} of instance {} doesn't exist, exporting it without one", icon, instance.id());
+			metadata.icon_path = None;
+		}
+		zip.start_file(format!("{}/{}", INSTANCE_PREFIX, METADATA_FILE), options)?;
+		zip.write_all(serde_json::to_string_pretty(&metadata).map_err(std::io::Error::from)?.as_bytes())?;
+	}
+
+	let mut directories = vec![(instance.path.clone(), INSTANCE_PREFIX.to_string())];
+	while let Some((directory, prefix)) = directories.pop() {
+		zip.add_directory(&prefix, options)?;
+		for entry in std::fs::read_dir(&directory)? {
+			let entry = entry?;
+			let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+			if entry.file_type()?.is_dir() {
+				directories.push((entry.path(), name));
+			} else if outside_icon.is_none() || directory != instance.path || entry.file_name() != METADATA_FILE {
+				zip.start_file(name, options)?;
+				std::io::copy(&mut File::open(entry.path())?, &mut zip)?;
+			}
+		}
+	}
+
+	zip.finish()?;
+	Ok(())
+}
+
+/// Reads the manifest of an exported instance without extracting anything.
+pub fn read_instance_archive(archive: &Path) -> Result<InstanceArchiveManifest, InstanceError> {
+	let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+	let mut data = String::new();
+	zip.by_name(MANIFEST_FILE)?.read_to_string(&mut data)?;
+
+	let manifest: InstanceArchiveManifest = serde_json::from_str(&data).map_err(|x| InstanceError::InvalidArchive(x.to_string()))?;
+	if manifest.format_version > INSTANCE_ARCHIVE_VERSION {
+		return Err(InstanceError::InvalidArchive(format!("made by a newer version of HERO ({})", manifest.hero_version)));
+	}
+	if manifest.game_id.is_empty() || directory_name(&manifest.game_id) != manifest.game_id {
+		return Err(InstanceError::InvalidArchive(format!("{:?} is not a valid game identifier", manifest.game_id)));
+	}
+	Ok(manifest)
+}
+
+/// Extracts an exported instance into HERO's instances directory, giving it a new identifier
+/// if another instance of the same game already has its one. Returns the identifier it ended up with.
+pub fn import_instance(archive: &Path) -> Result<Uuid, InstanceError> {
+	let manifest = read_instance_archive(archive)?;
+	let state = State::get();
+	let parent = state.path.join(INSTANCES_DIRECTORY).join(&manifest.game_id);
+	std::fs::create_dir_all(&parent)?;
+
+	let name = directory_name(&manifest.name);
+	let path = unused_directory(&parent, if name.is_empty() { "instance" } else { &name });
+	let result = extract_archive(archive, &Extraction::new(&path).format(ArchiveFormat::Zip).strip_components(1), |_, _| {})
+		.map_err(InstanceError::from)
+		.and_then(|_| Instance::new(path.clone(), &manifest.game_id).map_err(|x| InstanceError::InvalidArchive(x.to_string())));
+	let mut instance = match result {
+		Ok(instance) => instance,
+		Err(err) => {
+			let _ = std::fs::remove_dir_all(&path);
+			return Err(err);
+		}
+	};
+
+	let mut taken: HashSet<Uuid> = state.instances.read().items.keys().copied().collect();
+	for directory in state.instance_directories() {
+		if let Ok(entries) = std::fs::read_dir(directory.join(&manifest.game_id)) {
+			taken.extend(entries
+				.filter_map(|x| x.ok())
+				.filter(|x| x.path() != path)
+				.filter_map(|x| {
+					let path = x.path();
+					parse_metadata(&std::fs::read_to_string(path.join(METADATA_FILE)).ok()?, &path).ok()
+				})
+				.map(|(metadata, _)| metadata.id));
+		}
+	}
+	if taken.contains(&instance.id()) {
+		instance.metadata.id = Uuid::new_v4();
+		instance.save_metadata()?;
+		println!("imported instance {} conflicts with an existing one, it is now {}", manifest.id, instance.id());
+	}
+
+	let id = instance.id();
+	if state.current_game.read().as_deref() == Some(manifest.game_id.as_str()) {
+		state.instances.write().items.insert(id, instance);
+	}
+	Ok(id)
+}
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+	use super::*;
+	use crate::Icon;
+	use crate::testing::{ FakeExtension, FakeGame, TestState };
+	use crate::instance::delete_instance;
+
+	fn test_state() -> TestState {
+		let test = TestState::new();
+		test.add_extension(FakeExtension::new("fake_extension").game(FakeGame::new("fake_game")));
+		test
+	}
+
+	fn instance_path(id: &Uuid) -> std::path::PathBuf {
+		State::get().instances.read().items[id].path.clone()
+	}
+
+	#[test]
+	fn imports_exported_instances() {
+		let test = test_state();
+		let id = test.add_instance("fake_game", "Survival");
+		test.load_game("fake_game");
+		let path = instance_path(&id);
+		std::fs::create_dir(path.join("saves")).unwrap();
+		std::fs::write(path.join("saves/world.dat"), b"world").unwrap();
+
+		let archive = test.path().join(format!("Survival.{}", INSTANCE_ARCHIVE_EXTENSION));
+		export_instance(&id, &archive).unwrap();
+		let manifest = read_instance_archive(&archive).unwrap();
+		assert_eq!((manifest.game_id.as_str(), manifest.id, manifest.name.as_str()), ("fake_game", id, "Survival"));
+
+		delete_instance(&id).unwrap();
+		assert_eq!(import_instance(&archive).unwrap(), id);
+		assert_eq!(std::fs::read(instance_path(&id).join("saves/world.dat")).unwrap(), b"world");
+	}
+
+	#[test]
+	fn gives_conflicting_imports_a_new_identifier() {
+		let test = test_state();
+		let id = test.add_instance("fake_game", "Survival");
+		test.load_game("fake_game");
+
+		let archive = test.path().join(format!("Survival.{}", INSTANCE_ARCHIVE_EXTENSION));
+		export_instance(&id, &archive).unwrap();
+		let imported = import_instance(&archive).unwrap();
+		assert_ne!(imported, id);
+
+		let state = State::get();
+		let instances = state.instances.read();
+		assert_eq!(instances.items.len(), 2);
+		assert_ne!(instances.items[&imported].path, instances.items[&id].path);
+	}
+
+	#[test]
+	fn refuses_entries_leaving_the_instance() {
+		let test = test_state();
+		let archive = test.path().join(format!("evil.{}", INSTANCE_ARCHIVE_EXTENSION));
+		let manifest = InstanceArchiveManifest {
+			format_version: INSTANCE_ARCHIVE_VERSION,
+			game_id: "fake_game".into(),
+			id: Uuid::new_v4(),
+			name: "Evil".into(),
+			exported_at: SystemTime::now(),
+			hero_version: env!("CARGO_PKG_VERSION").into()
+		};
+		let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+		zip.start_file(MANIFEST_FILE, zip::write::FileOptions::default()).unwrap();
+		zip.write_all(serde_json::to_string(&manifest).unwrap().as_bytes()).unwrap();
+		zip.start_file("instance/../../escaped.txt", zip::write::FileOptions::default()).unwrap();
+		zip.write_all(b"nope").unwrap();
+		zip.finish().unwrap();
+
+		assert!(matches!(import_instance(&archive), Err(InstanceError::InvalidArchive(_))));
+		let parent = test.path().join(INSTANCES_DIRECTORY).join("fake_game");
+		assert_eq!(std::fs::read_dir(&parent).unwrap().count(), 0);
+		assert!(!parent.parent().unwrap().join("escaped.txt").exists());
+	}
+
+	#[test]
+	fn brings_icons_from_outside_of_the_instance_along() {
+		let test = test_state();
+		let id = test.add_instance("fake_game", "Survival");
+		test.load_game("fake_game");
+		let icon = test.path().join("elsewhere.png");
+		std::fs::write(&icon, b"icon").unwrap();
+		{
+			let state = State::get();
+			let mut instances = state.instances.write();
+			let instance = instances.items.get_mut(&id).unwrap();
+			instance.metadata.icon_path = Some(icon.clone());
+			instance.save_metadata().unwrap();
+		}
+
+		let archive = test.path().join(format!("Survival.{}", INSTANCE_ARCHIVE_EXTENSION));
+		export_instance(&id, &archive).unwrap();
+		delete_instance(&id).unwrap();
+		std::fs::remove_file(&icon).unwrap();
+		import_instance(&archive).unwrap();
+
+		let state = State::get();
+		let instances = state.instances.read();
+		let instance = &instances.items[&id];
+		assert_eq!(instance.metadata.icon_path.as_deref(), Some(Path::new("icon.png")));
+		assert_eq!(instance.icon().unwrap().bytes, b"icon");
+	}
+}
\ No newline at end of file
@@ -0,0 +1,235 @@
+use std::path::Path;
+use std::time::{ SystemTime, UNIX_EPOCH };
+use serde_json::{ Map, Value };
+
+use super::{ InstanceLoadError, InstanceMetadata, METADATA_FILE, write_metadata };
+
+/// Version of [`InstanceMetadata`] written by this version of HERO, bumped whenever its layout changes.
+/// Every bump needs an entry in [`MIGRATIONS`] upgrading the previous version.
+pub const INSTANCE_FORMAT_VERSION: u32 = 1;
+
+type Migration = fn(&mut Map<String, Value>, &Path);
+
+/// Upgrades metadata from the version at its index to the next one, metadata written before
+/// versioning existed counts as version zero.
+const MIGRATIONS: [Migration; INSTANCE_FORMAT_VERSION as usize] = [
+	created_at_timestamp
+];
+
+/// Reads the metadata of the instance in `directory`, upgrading it and writing it back if it was made by an older version of HERO.
+pub(super) fn read_metadata(directory: &Path) -> Result<InstanceMetadata, InstanceLoadError> {
+	let data = std::fs::read_to_string(directory.join(METADATA_FILE)).map_err(|err| match err.kind() {
+		std::io::ErrorKind::NotFound => InstanceLoadError::MissingMetadata,
+		_ => err.into()
+	})?;
+	let (metadata, migrated) = parse_metadata(&data, directory)?;
+	if migrated {
+		match write_metadata(directory, &metadata) {
+			Ok(_) => println!("upgraded metadata of instance {} to version {}", metadata.id, INSTANCE_FORMAT_VERSION),
+			Err(err) => println!("failed to write upgraded metadata of instance {}! {}", metadata.id, err)
+		}
+	}
+	Ok(metadata)
+}
+
+/// Parses metadata of any known version without touching the file, also returning whether it had to be upgraded.
+pub(super) fn parse_metadata(data: &str, directory: &Path) -> Result<(InstanceMetadata, bool), InstanceLoadError> {
+	let invalid = |x: serde_json::Error| InstanceLoadError::InvalidMetadata(x.to_string());
+	let mut value: Value = serde_json::from_str(data).map_err(invalid)?;
+	let object = value.as_object_mut()
+		.ok_or_else(|| InstanceLoadError::InvalidMetadata("expected an object".into()))?;
+
+	let version = match object.get("format_version") {
+		None => 0,
+		Some(version) => version.as_u64()
+			.and_then(|x| u32::try_from(x).ok())
+			.ok_or_else(|| InstanceLoadError::InvalidMetadata(format!("{} is not a valid format version", version)))?
+	};
+	if version > INSTANCE_FORMAT_VERSION {
+		return Err(InstanceLoadError::UnsupportedVersion(version));
+	}
+
+	for migration in &MIGRATIONS[version as usize..] {
+		migration(object, directory);
+	}
+	object.insert("format_version".into(), INSTANCE_FORMAT_VERSION.into());
+
+	let metadata = serde_json::from_value(value).map_err(invalid)?;
+	Ok((metadata, version < INSTANCE_FORMAT_VERSION))
+}
+
+/// Version zero stored `created_at` as a string, usually holding seconds since the Unix epoch but sometimes a date,
+/// or a placeholder such as `"now"` which is replaced by when the metadata was last modified.
+fn created_at_timestamp(object: &mut Map<String, Value>, directory: &Path) {
+	let seconds = match object.get("created_at") {
+		Some(Value::Number(number)) => number.as_u64(),
+		Some(Value::String(string)) => string.trim().parse::<u64>().ok().or_else(|| parse_date(string)),
+		_ => None
+	};
+	let seconds = seconds.filter(|x| *x > 0).unwrap_or_else(|| {
+		std::fs::metadata(directory.join(METADATA_FILE))
+			.and_then(|x| x.modified())
+			.unwrap_or_else(|_| SystemTime::now())
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |x| x.as_secs())
+	});
+	object.insert("created_at".into(), seconds.into());
+}
+
+/// Parses an RFC 3339 or ISO 8601 date such as `2023-11-02T14:30:00Z`, `2023-11-02 15:30:00+01:00` or just `2023-11-02`
+/// into seconds since the Unix epoch, times without an offset are taken to be in UTC.
+fn parse_date(value: &str) -> Option<u64> {
+	let value = value.trim();
+	let (date, time) = match value.find(['T', 't', ' ']) {
+		Some(index) => (&value[..index], Some(&value[index + 1..])),
+		None => (value, None)
+	};
+
+	let mut parts = date.split('-');
+	let year = digits(parts.next()?, 4)? as i64;
+	let month = digits(parts.next()?, 2)?;
+	let day = digits(parts.next()?, 2)?;
+	if parts.next().is_some() || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+		return None;
+	}
+	let mut seconds = days_from_civil(year, month, day) * 86_400;
+
+	if let Some(time) = time {
+		let (clock, offset) = match time.find(['Z', 'z', '+', '-']) {
+			Some(index) => (&time[..index], Some(&time[index..])),
+			None => (time, None)
+		};
+
+		let mut parts = clock.split(':');
+		let hours = digits(parts.next()?, 2)?;
+		let minutes = digits(parts.next()?, 2)?;
+		let seconds_part = parts.next().map(|x| x.split_once('.').map_or(x, |x| x.0));
+		let clock_seconds = match seconds_part {
+			Some(x) => digits(x, 2)?,
+			None => 0
+		};
+		if parts.next().is_some() || hours > 23 || minutes > 59 || clock_seconds > 60 {
+			return None;
+		}
+		seconds += (hours * 3600 + minutes * 60 + clock_seconds) as i64;
+
+		if let Some(offset) = offset.filter(|x| !x.eq_ignore_ascii_case("z")) {
+			let sign = if offset.starts_with('-') { -1 } else { 1 };
+			let offset = offset[1..].replace(':', "");
+			let (hours, minutes) = match offset.len() {
+				2 => (digits(&offset, 2)?, 0),
+				4 => (digits(&offset[..2], 2)?, digits(&offset[2..], 2)?),
+				_ => return None
+			};
+			seconds -= sign * (hours * 3600 + minutes * 60) as i64;
+		}
+	}
+
+	u64::try_from(seconds).ok()
+}
+
+/// Parses a number that is exactly `length` digits long.
+fn digits(value: &str, length: usize) -> Option<u32> {
+	if value.len() != length || !value.bytes().all(|x| x.is_ascii_digit()) {
+		return None;
+	}
+	value.parse().ok()
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+	match month {
+		2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31
+	}
+}
+
+/// Days between the Unix epoch and a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use uuid::Uuid;
+	use crate::testing::temp_dir;
+
+	/// Metadata as written by HERO before it was versioned.
+	fn version_zero(created_at: Value) -> String {
+		serde_json::json!({
+			"id": Uuid::nil(),
+			"author": "test",
+			"icon_path": null,
+			"created_at": created_at,
+			"display_names": { "custom": "Survival" }
+		}).to_string()
+	}
+
+	fn created_at(metadata: &InstanceMetadata) -> u64 {
+		metadata.created_at.duration_since(UNIX_EPOCH).unwrap().as_secs()
+	}
+
+	#[test]
+	fn parses_dates() {
+		assert_eq!(parse_date("2023-11-02T14:30:00Z"), Some(1_698_935_400));
+		assert_eq!(parse_date("2023-11-02t14:30:00.123z"), Some(1_698_935_400));
+		assert_eq!(parse_date("2023-11-02 15:30:00+01:00"), Some(1_698_935_400));
+		assert_eq!(parse_date("2023-11-02T09:00:00-0530"), Some(1_698_935_400));
+		assert_eq!(parse_date("2023-11-02T14:30"), Some(1_698_935_400));
+		assert_eq!(parse_date("2023-11-02"), Some(1_698_883_200));
+		assert_eq!(parse_date("2024-02-29T23:59:59Z"), Some(1_709_251_199));
+		assert_eq!(parse_date("1970-01-01T00:00:00Z"), Some(0));
+
+		for invalid in ["now", "", "2023-13-01", "2023-02-29", "2023-11-02T25:00:00Z", "2023-11-02T14:30:00+1", "1969-12-31", "23-11-02"] {
+			assert_eq!(parse_date(invalid), None, "{}", invalid);
+		}
+	}
+
+	#[test]
+	fn upgrades_version_zero_timestamps() {
+		let dir = temp_dir("migration");
+		for (value, expected) in [
+			(Value::from(1_698_935_400), 1_698_935_400),
+			(Value::from("1698935400"), 1_698_935_400),
+			(Value::from("2023-11-02T14:30:00Z"), 1_698_935_400),
+			(Value::from("2023-11-02T15:30:00+01:00"), 1_698_935_400)
+		] {
+			let (metadata, migrated) = parse_metadata(&version_zero(value), &dir).unwrap();
+			assert!(migrated);
+			assert_eq!(metadata.format_version, INSTANCE_FORMAT_VERSION);
+			assert_eq!(created_at(&metadata), expected);
+		}
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn replaces_version_zero_placeholders_with_the_modification_time() {
+		let dir = temp_dir("migration");
+		std::fs::write(dir.join(METADATA_FILE), version_zero("now".into())).unwrap();
+		let modified = std::fs::metadata(dir.join(METADATA_FILE)).unwrap().modified().unwrap();
+
+		let metadata = read_metadata(&dir).unwrap();
+		assert_eq!(created_at(&metadata), modified.duration_since(UNIX_EPOCH).unwrap().as_secs());
+
+		// the upgraded metadata is written back, so it only has to be migrated once
+		let data = std::fs::read_to_string(dir.join(METADATA_FILE)).unwrap();
+		let (upgraded, migrated) = parse_metadata(&data, &dir).unwrap();
+		assert!(!migrated);
+		assert_eq!(created_at(&upgraded), created_at(&metadata));
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_newer_versions() {
+		let data = serde_json::json!({ "format_version": INSTANCE_FORMAT_VERSION + 1 }).to_string();
+		assert!(matches!(parse_metadata(&data, Path::new(".")), Err(InstanceLoadError::UnsupportedVersion(_))));
+		assert!(matches!(parse_metadata("[]", Path::new(".")), Err(InstanceLoadError::InvalidMetadata(_))));
+	}
+}
\ No newline at end of file
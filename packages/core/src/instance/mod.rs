@@ -0,0 +1,874 @@
+use std::fmt;
+use std::sync::Arc;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::collections::HashMap;
+use uuid::Uuid;
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+	storage::PLUTO,
+	Icon,
+	IconData
+};
+#[cfg(feature = "core")]
+use std::path::Path;
+#[cfg(feature = "core")]
+use crate::storage::read_pluto_proto_file;
+#[cfg(feature = "core")]
+use parking_lot::RwLock;
+#[cfg(feature = "core")]
+use crate::state::State;
+#[cfg(feature = "core")]
+use crate::state::LoadingBarType;
+
+#[cfg(feature = "core")]
+mod archive;
+#[cfg(feature = "core")]
+pub use archive::*;
+#[cfg(feature = "core")]
+mod migration;
+#[cfg(feature = "core")]
+pub use migration::INSTANCE_FORMAT_VERSION;
+#[cfg(feature = "core")]
+use migration::{ parse_metadata, read_metadata };
+
+/// Directory inside of [`State::path`] holding instances, with a directory per game.
+pub const INSTANCES_DIRECTORY: &str = "instances";
+
+/// Where an instance keeps its [`InstanceMetadata`], inside of its directory.
+pub const METADATA_FILE: &str = "instance_meta.json";
+
+/// Directory inside of [`State::path`] deleted instances are moved to, with a directory per game.
+pub const TRASH_DIRECTORY: &str = "trash";
+
+/// Cloning an instance takes a snapshot of it, changes to the copy don't reach [`Instances`].
+#[derive(Clone)]
+pub struct Instance {
+	pub path: PathBuf,
+	pub game_id: String,
+	pub metadata: InstanceMetadata,
+	pub game_meta: PLUTO
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InstanceMetadata {
+	/// Version of this layout, older metadata is upgraded when loaded, see [`INSTANCE_FORMAT_VERSION`].
+	pub format_version: u32,
+	pub id: Uuid,
+	pub author: String,
+	pub icon_path: Option<std::path::PathBuf>,
+
+	#[serde(with = "unix_seconds")]
+	pub created_at: SystemTime,
+	pub display_names: DisplayNames
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DisplayNames {
+	pub custom: String
+}
+
+impl Instance {
+	#[cfg(feature = "core")]
+	pub fn new(path: PathBuf, game_id: impl ToString) -> Result<Self, InstanceLoadError> {
+		let game_id = game_id.to_string();
+		let metadata = read_metadata(&path)?;
+		let game_meta = read_pluto_proto_file(game_meta_path(&path, &game_id)).map_err(|x| InstanceLoadError::GameMeta(Arc::new(x)))?;
+
+		println!("loaded instance {}", metadata.id);
+		Ok(Self {
+			path,
+			game_id,
+			metadata,
+			game_meta
+		})
+	}
+
+	pub fn id(&self) -> Uuid {
+		self.metadata.id
+	}
+
+	#[cfg(feature = "core")]
+	pub fn save_metadata(&self) -> std::io::Result<()> {
+		write_metadata(&self.path, &self.metadata)
+	}
+
+	pub fn display_name(&self) -> String {
+		self.metadata.display_names.custom.clone()
+	}
+}
+
+impl Icon for Instance {
+	fn icon(&self) -> Option<IconData> {
+		let path = self.path.join(self.metadata.icon_path.as_ref()?);
+		if let Ok(bytes) = std::fs::read(&path) {
+			return Some(IconData {
+				path: path.to_string_lossy().to_string(),
+				bytes
+			});
+		}
+		None
+	}
+}
+
+pub struct Instances {
+	pub items: HashMap<Uuid, Instance>,
+
+	/// Directories of the selected game that couldn't be loaded as instances.
+	pub broken: Vec<BrokenInstance>
+}
+
+impl Instances {
+	pub fn new() -> Self {
+		Self {
+			items: HashMap::new(),
+			broken: vec![]
+		}
+	}
+}
+
+/// A directory that looks like an instance but couldn't be loaded, see [`repair_instance`].
+pub struct BrokenInstance {
+	pub path: PathBuf,
+	pub game_id: String,
+	pub error: InstanceLoadError
+}
+
+#[derive(Clone, Debug)]
+pub enum InstanceLoadError {
+	/// There is no metadata file, so the directory might not be an instance at all.
+	MissingMetadata,
+	InvalidMetadata(String),
+
+	/// The metadata was written by a newer version of HERO, so it is left alone rather than repaired.
+	UnsupportedVersion(u32),
+
+	/// The game's metadata couldn't be read.
+	GameMeta(Arc<std::io::Error>),
+	Io(Arc<std::io::Error>)
+}
+
+impl fmt::Display for InstanceLoadError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::MissingMetadata => write!(f, "{} is missing", METADATA_FILE),
+			Self::InvalidMetadata(reason) => write!(f, "{} is invalid: {}", METADATA_FILE, reason),
+			Self::UnsupportedVersion(version) => write!(f, "{} is version {}, which was made by a newer version of HERO", METADATA_FILE, version),
+			Self::GameMeta(err) => write!(f, "game metadata is unreadable: {}", err),
+			Self::Io(err) => write!(f, "io error: {}", err)
+		}
+	}
+}
+
+impl std::error::Error for InstanceLoadError {}
+
+impl From<std::io::Error> for InstanceLoadError {
+	fn from(value: std::io::Error) -> Self {
+		Self::Io(Arc::new(value))
+	}
+}
+
+/// Serializes timestamps as whole seconds since the Unix epoch.
+pub(crate) mod unix_seconds {
+	use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+	use serde::{ Deserialize, Deserializer, Serializer };
+
+	pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_u64(time.duration_since(UNIX_EPOCH).map_or(0, |x| x.as_secs()))
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+		Ok(UNIX_EPOCH + Duration::from_secs(u64::deserialize(deserializer)?))
+	}
+}
+
+/// Writes the metadata of the instance in `directory` to a temporary file first,
+/// so the existing file is only replaced once the new one is complete.
+#[cfg(feature = "core")]
+fn write_metadata(directory: &Path, metadata: &InstanceMetadata) -> std::io::Result<()> {
+	let temporary = directory.join(format!("{}.tmp", METADATA_FILE));
+	std::fs::write(&temporary, serde_json::to_string_pretty(metadata)?)?;
+	std::fs::rename(&temporary, directory.join(METADATA_FILE)).map_err(|err| {
+		let _ = std::fs::remove_file(&temporary);
+		err
+	})
+}
+
+/// Where an instance keeps the metadata of its game.
+#[cfg(feature = "core")]
+fn game_meta_path(path: &Path, game_id: &str) -> PathBuf {
+	path.join(format!("{}_meta.PLUTO_PROTO", game_id))
+}
+
+#[cfg(feature = "core")]
+impl Instances {
+	fn get(&self, id: &Uuid) -> Result<&Instance, InstanceError> {
+		self.items.get(id).ok_or(InstanceError::NotFound(*id))
+	}
+
+	/// Changes the name shown for an instance, its directory keeps its old name.
+	pub fn rename(&mut self, id: &Uuid, name: impl Into<String>) -> Result<(), InstanceError> {
+		let name: String = name.into();
+		if name.trim().is_empty() {
+			return Err(InstanceError::InvalidName(name));
+		}
+
+		let instance = self.items.get_mut(id).ok_or(InstanceError::NotFound(*id))?;
+		let previous = std::mem::replace(&mut instance.metadata.display_names.custom, name.trim().into());
+		if let Err(err) = instance.save_metadata() {
+			instance.metadata.display_names.custom = previous;
+			return Err(err.into());
+		}
+		Ok(())
+	}
+}
+
+impl Default for Instances {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum InstanceError {
+	UnknownGame(String),
+
+	/// The name is empty, or made up entirely of characters that can't be in a directory name.
+	InvalidName(String),
+
+	/// An option isn't one the game offers, or its value doesn't fit.
+	InvalidOption(String),
+
+	/// No loaded instance has this identifier.
+	NotFound(Uuid),
+
+	/// An archive being imported is damaged or wasn't exported by HERO.
+	InvalidArchive(String),
+	Load(InstanceLoadError),
+	Io(Arc<std::io::Error>)
+}
+
+impl fmt::Display for InstanceError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::UnknownGame(id) => write!(f, "game {} is not provided by any extension", id),
+			Self::InvalidName(name) => write!(f, "{:?} is not a valid instance name", name),
+			Self::InvalidOption(key) => write!(f, "invalid value for option {}", key),
+			Self::NotFound(id) => write!(f, "instance {} is not loaded", id),
+			Self::InvalidArchive(reason) => write!(f, "invalid instance archive: {}", reason),
+			Self::Load(err) => write!(f, "failed to load instance: {}", err),
+			Self::Io(err) => write!(f, "io error: {}", err)
+		}
+	}
+}
+
+impl std::error::Error for InstanceError {}
+
+impl From<std::io::Error> for InstanceError {
+	fn from(value: std::io::Error) -> Self {
+		Self::Io(Arc::new(value))
+	}
+}
+
+impl From<InstanceLoadError> for InstanceError {
+	fn from(value: InstanceLoadError) -> Self {
+		Self::Load(value)
+	}
+}
+
+/// Everything needed to create an instance, see [`create_instance`].
+pub struct NewInstance {
+	pub game_id: String,
+	pub name: String,
+
+	/// Defaults to the name of the user running HERO.
+	pub author: Option<String>,
+
+	/// Image copied into the instance's directory.
+	pub icon: Option<PathBuf>,
+
+	/// Values for the game's [`crate::game::Game::creation_options`], options left out use their defaults.
+	pub options: HashMap<String, String>
+}
+
+impl NewInstance {
+	pub fn new(game_id: impl Into<String>, name: impl Into<String>) -> Self {
+		Self {
+			game_id: game_id.into(),
+			name: name.into(),
+			author: None,
+			icon: None,
+			options: HashMap::new()
+		}
+	}
+
+	pub fn author(mut self, author: impl Into<String>) -> Self {
+		self.author = Some(author.into());
+		self
+	}
+
+	pub fn icon(mut self, icon: impl Into<PathBuf>) -> Self {
+		self.icon = Some(icon.into());
+		self
+	}
+
+	pub fn option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+		self.options.insert(key.into(), value.into());
+		self
+	}
+}
+
+/// Copies an instance along with everything inside of it, returning the identifier of the copy.
+/// The lock is only held to look the instance up and to add the copy, never while copying.
+#[cfg(feature = "core")]
+fn duplicate(instances: &RwLock<Instances>, id: &Uuid) -> Result<Uuid, InstanceError> {
+	let instance = instances.read().get(id)?.clone();
+	let parent = instance.path.parent().ok_or(InstanceError::NotFound(*id))?;
+	let path = unused_directory(parent, &directory_file_name(&instance.path));
+	copy_directory(&instance.path, &path)?;
+
+	let copy = match identify_copy(&instance, path.clone()) {
+		Ok(copy) => copy,
+		Err(err) => {
+			let _ = std::fs::remove_dir_all(&path);
+			return Err(err);
+		}
+	};
+
+	let copy_id = copy.id();
+	instances.write().items.insert(copy_id, copy);
+	Ok(copy_id)
+}
+
+/// Moves an instance into `<trash>/<game>`, returning where it ended up so it can be restored later.
+#[cfg(feature = "core")]
+fn delete(instances: &RwLock<Instances>, id: &Uuid, trash: &Path) -> Result<PathBuf, InstanceError> {
+	let instance = instances.read().get(id)?.clone();
+	let parent = trash.join(&instance.game_id);
+	std::fs::create_dir_all(&parent)?;
+
+	let path = unused_directory(&parent, &directory_file_name(&instance.path));
+	move_directory(&instance.path, &path)?;
+	instances.write().items.remove(id);
+	Ok(path)
+}
+
+/// Moves an instance out of the trash and into a directory of instances, loading it again.
+#[cfg(feature = "core")]
+fn restore(instances: &RwLock<Instances>, trashed: &Path, directory: &Path) -> Result<Uuid, InstanceError> {
+	let game_id = trashed.parent()
+		.and_then(|x| x.file_name())
+		.map(|x| x.to_string_lossy().to_string())
+		.ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+	let parent = directory.join(&game_id);
+	std::fs::create_dir_all(&parent)?;
+
+	let path = unused_directory(&parent, &directory_file_name(trashed));
+	move_directory(trashed, &path)?;
+
+	let instance = Instance::new(path, game_id)?;
+	let id = instance.id();
+	instances.write().items.insert(id, instance);
+	Ok(id)
+}
+
+/// Tries to load a broken instance again after fixing what can be fixed automatically.
+/// Unreadable metadata is set aside with a `.broken` extension and replaced, losing the instance's identity but not its files.
+#[cfg(feature = "core")]
+fn repair(instances: &RwLock<Instances>, path: &Path) -> Result<Uuid, InstanceError> {
+	let game_id = instances.read().broken.iter()
+		.find(|x| x.path == path)
+		.map(|x| x.game_id.clone())
+		.ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+
+	let mut result = Instance::new(path.to_path_buf(), &game_id);
+	if let Err(InstanceLoadError::MissingMetadata | InstanceLoadError::InvalidMetadata(_)) = result {
+		set_aside(&path.join(METADATA_FILE))?;
+		let metadata = InstanceMetadata {
+			format_version: INSTANCE_FORMAT_VERSION,
+			id: Uuid::new_v4(),
+			author: "unknown".into(),
+			icon_path: None,
+			created_at: SystemTime::now(),
+			display_names: DisplayNames { custom: directory_file_name(path) }
+		};
+		write_metadata(path, &metadata)?;
+		result = Instance::new(path.to_path_buf(), &game_id);
+	}
+	if let Err(InstanceLoadError::GameMeta(_)) = result {
+		set_aside(&game_meta_path(path, &game_id))?;
+		result = Instance::new(path.to_path_buf(), &game_id);
+	}
+
+	let mut instances = instances.write();
+	match result {
+		Ok(instance) => {
+			let id = instance.id();
+			instances.broken.retain(|x| x.path != path);
+			instances.items.insert(id, instance);
+			println!("repaired instance {:?}", path);
+			Ok(id)
+		},
+		Err(error) => {
+			if let Some(broken) = instances.broken.iter_mut().find(|x| x.path == path) {
+				broken.error = error.clone();
+			}
+			Err(error.into())
+		}
+	}
+}
+
+/// Moves an instance into another directory of instances, see [`crate::settings::Settings::instance_directories`].
+#[cfg(feature = "core")]
+fn move_to(instances: &RwLock<Instances>, id: &Uuid, directory: &Path) -> Result<(), InstanceError> {
+	let instance = instances.read().get(id)?.clone();
+	let parent = directory.join(&instance.game_id);
+	if instance.path.parent() == Some(parent.as_path()) {
+		return Ok(());
+	}
+	std::fs::create_dir_all(&parent)?;
+
+	let path = unused_directory(&parent, &directory_file_name(&instance.path));
+	let moved = move_directory(&instance.path, &path);
+	if !path.exists() {
+		// nothing was moved, so the instance is still where it was
+		return moved.map_err(Into::into);
+	}
+
+	// the instance lives at its new path now, even if the old one couldn't be removed completely,
+	// so the entry for the old path has to go whether or not it can be loaded from there
+	let loaded = Instance::new(path.clone(), &instance.game_id);
+	let mut instances = instances.write();
+	instances.items.remove(id);
+	match loaded {
+		Ok(instance) => {
+			instances.items.insert(*id, instance);
+		},
+		Err(error) => {
+			instances.broken.push(BrokenInstance { path, game_id: instance.game_id, error: error.clone() });
+			return Err(error.into());
+		}
+	}
+	moved.map_err(Into::into)
+}
+
+/// Turns an instance name into a directory name, replacing anything that isn't safe on every platform.
+#[cfg(feature = "core")]
+fn directory_name(name: &str) -> String {
+	name.trim()
+		.chars()
+		.map(|x| if x.is_alphanumeric() || matches!(x, ' ' | '-' | '_' | '.') { x } else { '_' })
+		.collect::<String>()
+		.trim_matches(|x| x == '.' || x == ' ')
+		.to_string()
+}
+
+/// Loads a freshly copied instance, giving it an identity of its own.
+#[cfg(feature = "core")]
+fn identify_copy(instance: &Instance, path: PathBuf) -> Result<Instance, InstanceError> {
+	let mut copy = Instance::new(path, &instance.game_id)?;
+	copy.metadata.id = Uuid::new_v4();
+	copy.metadata.created_at = SystemTime::now();
+	copy.metadata.display_names.custom = format!("{} (copy)", instance.display_name());
+	copy.save_metadata()?;
+	Ok(copy)
+}
+
+#[cfg(feature = "core")]
+fn directory_file_name(path: &Path) -> String {
+	path.file_name().map_or_else(|| "instance".into(), |x| x.to_string_lossy().to_string())
+}
+
+/// A directory for a new instance that doesn't exist yet, numbered if the name is taken.
+#[cfg(feature = "core")]
+fn unused_directory(parent: &Path, name: &str) -> PathBuf {
+	let mut path = parent.join(name);
+	let mut number = 2;
+	while path.exists() {
+		path = parent.join(format!("{} ({})", name, number));
+		number += 1;
+	}
+	path
+}
+
+/// Creates an instance on disk, loading it right away if its game is the selected one.
+#[cfg(feature = "core")]
+pub fn create_instance(new: NewInstance) -> Result<Uuid, InstanceError> {
+	let state = State::get();
+	let game = state.get_game(new.game_id.clone()).ok_or_else(|| InstanceError::UnknownGame(new.game_id.clone()))?;
+	let name = new.name.trim().to_string();
+	let directory = directory_name(&name);
+	if directory.is_empty() {
+		return Err(InstanceError::InvalidName(new.name));
+	}
+
+	let mut game_meta = HashMap::new();
+	for option in game.creation_options() {
+		let value = match new.options.get(&option.key) {
+			Some(value) => option.kind.parse(value).ok_or_else(|| InstanceError::InvalidOption(option.key.clone()))?,
+			None => match option.kind.default_value() {
+				Some(value) => value,
+				None => continue
+			}
+		};
+		game_meta.insert(option.key, value.to_string());
+	}
+	if let Some(key) = new.options.keys().find(|x| !game_meta.contains_key(*x)) {
+		return Err(InstanceError::InvalidOption(key.clone()));
+	}
+
+	let bar = state.init_loading(LoadingBarType::CreateInstance { game_id: new.game_id.clone(), name: name.clone() }, 1.);
+	let result = write_instance(&state.path.join(INSTANCES_DIRECTORY).join(game.id()), &directory, new, name, game_meta);
+	state.finish_loading(&bar);
+
+	let path = result?;
+	let instance = Instance::new(path, game.id())?;
+	let id = instance.id();
+	println!("created instance {} of {}", id, game.id());
+
+	game.on_instance_created(&instance);
+	if state.current_game.read().as_deref() == Some(game.id()) {
+		state.instances.write().items.insert(id, instance);
+	}
+	Ok(id)
+}
+
+/// Copies an instance along with everything inside of it, returning the identifier of the copy.
+#[cfg(feature = "core")]
+pub fn duplicate_instance(id: &Uuid) -> Result<Uuid, InstanceError> {
+	let state = State::get();
+	state.capabilities.check_host("manage instances").map_err(std::io::Error::from)?;
+	duplicate(&state.instances, id)
+}
+
+/// Moves an instance into the trash, returning where it ended up so it can be restored with [`restore_instance`].
+#[cfg(feature = "core")]
+pub fn delete_instance(id: &Uuid) -> Result<PathBuf, InstanceError> {
+	let state = State::get();
+	state.capabilities.check_host("manage instances").map_err(std::io::Error::from)?;
+	delete(&state.instances, id, &state.path.join(TRASH_DIRECTORY))
+}
+
+/// Moves an instance out of the trash and back into HERO's own directory of instances.
+#[cfg(feature = "core")]
+pub fn restore_instance(trashed: &Path) -> Result<Uuid, InstanceError> {
+	let state = State::get();
+	state.capabilities.check_host("manage instances").map_err(std::io::Error::from)?;
+	restore(&state.instances, trashed, &state.path.join(INSTANCES_DIRECTORY))
+}
+
+/// Instances of a game that are in the trash, see [`delete_instance`].
+#[cfg(feature = "core")]
+pub fn trashed_instances(game_id: &str) -> std::io::Result<Vec<PathBuf>> {
+	let state = State::get();
+	state.capabilities.check_host("see deleted instances")?;
+	let mut paths: Vec<PathBuf> = match std::fs::read_dir(state.path.join(TRASH_DIRECTORY).join(game_id)) {
+		Ok(entries) => entries.filter_map(|x| x.ok()).map(|x| x.path()).filter(|x| x.is_dir()).collect(),
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+		Err(err) => return Err(err)
+	};
+	paths.sort();
+	Ok(paths)
+}
+
+/// Tries to load a broken instance again, see [`Instances::broken`].
+#[cfg(feature = "core")]
+pub fn repair_instance(path: &Path) -> Result<Uuid, InstanceError> {
+	let state = State::get();
+	state.capabilities.check_host("manage instances").map_err(std::io::Error::from)?;
+	repair(&state.instances, path)
+}
+
+/// Moves an instance into another directory of instances, see [`crate::settings::Settings::instance_directories`].
+#[cfg(feature = "core")]
+pub fn move_instance(id: &Uuid, directory: &Path) -> Result<(), InstanceError> {
+	let state = State::get();
+	state.capabilities.check_host("manage instances").map_err(std::io::Error::from)?;
+	move_to(&state.instances, id, directory)
+}
+
+#[cfg(feature = "core")]
+fn write_instance(parent: &Path, directory: &str, new: NewInstance, name: String, game_meta: HashMap<String, String>) -> Result<PathBuf, InstanceError> {
+	std::fs::create_dir_all(parent)?;
+	let path = unused_directory(parent, directory);
+	std::fs::create_dir(&path)?;
+
+	let result = write_instance_files(&path, new, name, game_meta);
+	if result.is_err() {
+		let _ = std::fs::remove_dir_all(&path);
+	}
+	result.map(|_| path)
+}
+
+#[cfg(feature = "core")]
+fn write_instance_files(path: &Path, new: NewInstance, name: String, game_meta: HashMap<String, String>) -> Result<(), InstanceError> {
+	let icon_path = match &new.icon {
+		Some(icon) => {
+			let file_name = PathBuf::from(match icon.extension() {
+				Some(extension) => format!("icon.{}", extension.to_string_lossy()),
+				None => "icon".into()
+			});
+			std::fs::copy(icon, path.join(&file_name))?;
+			Some(file_name)
+		},
+		None => None
+	};
+
+	let metadata = InstanceMetadata {
+		format_version: INSTANCE_FORMAT_VERSION,
+		id: Uuid::new_v4(),
+		author: new.author
+			.or_else(|| std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok())
+			.unwrap_or_else(|| "unknown".into()),
+		icon_path,
+		created_at: SystemTime::now(),
+		display_names: DisplayNames { custom: name }
+	};
+	write_metadata(path, &metadata)?;
+	PLUTO::new(game_meta_path(path, &new.game_id), game_meta).save()?;
+
+	Ok(())
+}
+
+#[cfg(feature = "core")]
+fn copy_directory(from: &Path, to: &Path) -> std::io::Result<()> {
+	std::fs::create_dir(to)?;
+	for entry in std::fs::read_dir(from)? {
+		let entry = entry?;
+		let target = to.join(entry.file_name());
+		let file_type = entry.file_type()?;
+		if file_type.is_symlink() {
+			copy_symlink(&entry.path(), &target)?;
+		} else if file_type.is_dir() {
+			copy_directory(&entry.path(), &target)?;
+		} else {
+			std::fs::copy(entry.path(), target)?;
+		}
+	}
+	Ok(())
+}
+
+/// Recreates a symbolic link rather than copying what it points to, which may be outside of the instance or not exist at all.
+#[cfg(feature = "core")]
+fn copy_symlink(from: &Path, to: &Path) -> std::io::Result<()> {
+	let target = std::fs::read_link(from)?;
+
+	#[cfg(unix)]
+	return std::os::unix::fs::symlink(target, to);
+
+	#[cfg(windows)]
+	return match std::fs::metadata(from) {
+		Ok(metadata) if metadata.is_dir() => std::os::windows::fs::symlink_dir(target, to),
+		_ => std::os::windows::fs::symlink_file(target, to)
+	};
+
+	#[cfg(not(any(unix, windows)))]
+	Err(std::io::Error::new(std::io::ErrorKind::Unsupported, format!("can't recreate the link to {:?}", target)))
+}
+
+/// Renames a damaged file so it is kept around without getting in the way.
+#[cfg(feature = "core")]
+fn set_aside(path: &Path) -> std::io::Result<()> {
+	if !path.exists() {
+		return Ok(());
+	}
+	let mut broken = path.as_os_str().to_owned();
+	broken.push(".broken");
+	let _ = std::fs::remove_file(&broken);
+	std::fs::rename(path, broken)
+}
+
+/// Renames a directory, falling back to copying it when that isn't possible, such as between drives.
+#[cfg(feature = "core")]
+fn move_directory(from: &Path, to: &Path) -> std::io::Result<()> {
+	if std::fs::rename(from, to).is_ok() {
+		return Ok(());
+	}
+	if let Err(err) = copy_directory(from, to) {
+		let _ = std::fs::remove_dir_all(to);
+		return Err(err);
+	}
+	std::fs::remove_dir_all(from)
+}
+
+#[cfg(feature = "core")]
+pub fn load_instance(_path: PathBuf) {
+
+}
+
+#[cfg(feature = "core")]
+pub fn load_instances() {
+	let state = State::get();
+	if let Some(game) = state.get_current_game() {
+		let mut instances = state.instances.write();
+		instances.broken.clear();
+		for directory in state.instance_directories() {
+			let path = directory.join(game.id());
+			println!("loading instances from {:?}", path);
+
+			let Ok(entries) = std::fs::read_dir(path) else {
+				continue;
+			};
+			for entry in entries.filter_map(|x| x.ok()) {
+				if entry.file_type().is_ok_and(|x| x.is_dir()) {
+					match Instance::new(entry.path(), game.id()) {
+						Ok(instance) => {
+							instances.items.insert(instance.metadata.id, instance);
+						},
+						Err(error) => {
+							println!("failed to load instance {:?}! {}", entry.path(), error);
+							instances.broken.push(BrokenInstance {
+								path: entry.path(),
+								game_id: game.id().into(),
+								error
+							});
+						}
+					}
+				}
+			}
+		}
+	}
+}
+
+#[cfg(feature = "core")]
+pub async fn launch(instance_id: Uuid) {
+	let state = State::get();
+	let Some(game) = state.get_current_game() else {
+		return;
+	};
+
+	// extensions and the game are called with a copy of the instance and without holding any locks,
+	// so that they can use the state however they like
+	let Some(instance) = state.instances.read().items.get(&instance_id).cloned() else {
+		return;
+	};
+	println!("launching instance {}", instance_id);
+
+	let hooks = state.extensions.read().hooks();
+	hooks.dispatch("before_launch", |x| x.before_launch(&instance));
+	let process = game.launch(&instance);
+	hooks.dispatch("after_launch", |x| x.after_launch(&instance));
+
+	if let Some(mut process) = process {
+		let exit_code = tokio::task::spawn_blocking(move || process.wait())
+			.await
+			.ok()
+			.and_then(|x| x.ok())
+			.and_then(|x| x.code());
+		println!("instance {} exited with {:?}", instance_id, exit_code);
+
+		let instance = state.instances.read().items.get(&instance_id).cloned();
+		if let Some(instance) = instance {
+			let hooks = state.extensions.read().hooks();
+			hooks.dispatch("on_process_exited", |x| x.on_process_exited(&instance, exit_code));
+		}
+	}
+}
+
+#[cfg(all(test, feature = "core"))]
+mod tests {
+	use super::*;
+	use crate::testing::temp_dir;
+
+	/// Writes an instance of `game` into a directory of instances and loads it.
+	fn instances_in(directory: &Path) -> (RwLock<Instances>, Uuid) {
+		let new = NewInstance::new("game", "Survival").author("test");
+		let path = write_instance(&directory.join("game"), "Survival", new, "Survival".into(), HashMap::new()).unwrap();
+		let instance = Instance::new(path, "game").unwrap();
+		let id = instance.id();
+
+		let mut instances = Instances::new();
+		instances.items.insert(id, instance);
+		(RwLock::new(instances), id)
+	}
+
+	#[test]
+	#[cfg(feature = "testing")]
+	fn sets_malformed_instances_aside() {
+		use crate::testing::{ FakeExtension, FakeGame, TestState };
+
+		let test = TestState::new();
+		test.add_extension(FakeExtension::new("fake_extension").game(FakeGame::new("fake_game")));
+		let id = test.add_instance("fake_game", "Survival");
+		let directory = test.path().join(INSTANCES_DIRECTORY).join("fake_game");
+		std::fs::create_dir(directory.join("Garbled")).unwrap();
+		std::fs::write(directory.join("Garbled").join(METADATA_FILE), "{ not json").unwrap();
+		std::fs::create_dir(directory.join("Empty")).unwrap();
+		std::fs::write(directory.join("stray.txt"), "").unwrap();
+		test.load_game("fake_game");
+
+		let state = test.state();
+		let instances = state.instances.read();
+		assert_eq!(instances.items.keys().collect::<Vec<_>>(), [&id]);
+		let mut broken: Vec<_> = instances.broken.iter().map(|x| (x.path.file_name().unwrap().to_str().unwrap(), &x.error)).collect();
+		broken.sort_by_key(|x| x.0);
+		assert!(matches!(broken[..], [("Empty", InstanceLoadError::MissingMetadata), ("Garbled", InstanceLoadError::InvalidMetadata(_))]));
+	}
+
+	#[test]
+	fn moves_instances_between_directories() {
+		let dir = temp_dir("instances");
+		let (instances, id) = instances_in(&dir.join("from"));
+
+		move_to(&instances, &id, &dir.join("to")).unwrap();
+		assert_eq!(instances.read().get(&id).unwrap().path, dir.join("to").join("game").join("Survival"));
+		assert!(!dir.join("from").join("game").join("Survival").exists());
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn forgets_moved_instances_that_fail_to_load() {
+		let dir = temp_dir("instances");
+		let (instances, id) = instances_in(&dir.join("from"));
+		std::fs::write(dir.join("from").join("game").join("Survival").join(METADATA_FILE), "{").unwrap();
+
+		assert!(move_to(&instances, &id, &dir.join("to")).is_err());
+		let instances = instances.read();
+		assert!(instances.get(&id).is_err());
+		assert_eq!(instances.broken.len(), 1);
+		assert_eq!(instances.broken[0].path, dir.join("to").join("game").join("Survival"));
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn keeps_instances_that_could_not_be_moved() {
+		let dir = temp_dir("instances");
+		let (instances, id) = instances_in(&dir.join("from"));
+		std::fs::write(dir.join("to"), "").unwrap();
+
+		assert!(move_to(&instances, &id, &dir.join("to")).is_err());
+		assert_eq!(instances.read().get(&id).unwrap().path, dir.join("from").join("game").join("Survival"));
+		assert!(instances.read().broken.is_empty());
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn restores_deleted_instances() {
+		let dir = temp_dir("instances");
+		let (instances, id) = instances_in(&dir.join("instances"));
+
+		let trashed = delete(&instances, &id, &dir.join("trash")).unwrap();
+		assert_eq!(trashed, dir.join("trash").join("game").join("Survival"));
+		assert!(instances.read().get(&id).is_err());
+
+		assert_eq!(restore(&instances, &trashed, &dir.join("instances")).unwrap(), id);
+		assert_eq!(instances.read().get(&id).unwrap().path, dir.join("instances").join("game").join("Survival"));
+		assert!(!trashed.exists());
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn copies_symbolic_links_as_links() {
+		let dir = temp_dir("instances");
+		let (instances, id) = instances_in(&dir.join("instances"));
+		let path = dir.join("instances").join("game").join("Survival");
+		std::os::unix::fs::symlink(dir.join("elsewhere"), path.join("link")).unwrap();
+
+		let copy = duplicate(&instances, &id).unwrap();
+		let copy = instances.read().get(&copy).unwrap().path.join("link");
+		assert_eq!(std::fs::read_link(copy).unwrap(), dir.join("elsewhere"));
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+}
\ No newline at end of file
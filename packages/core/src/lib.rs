@@ -3,6 +3,7 @@ pub mod state;
 pub mod content;
 pub mod storage;
 pub mod instance;
+pub mod settings;
 pub mod extension;
 pub mod downloader;
 pub mod localisation;
@@ -10,6 +11,9 @@ pub mod localisation;
 #[cfg(feature = "ui")]
 pub mod user_interface;
 
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 pub use uuid;
 pub use parking_lot;
 
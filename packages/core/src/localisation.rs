@@ -33,4 +33,19 @@ impl Localisation {
 			self.data.insert(name, data.into_iter().map(|x| (x.0.to_string(), x.1.to_string())).collect());
 		}
 	}
+
+	#[cfg(feature = "core")]
+	pub fn remove_data(&mut self, locale: &str, keys: &[String]) {
+		if let Some(current) = self.data.get_mut(locale) {
+			for key in keys {
+				current.remove(key);
+			}
+		}
+	}
+}
+
+impl Default for Localisation {
+	fn default() -> Self {
+		Self::new()
+	}
 }
\ No newline at end of file
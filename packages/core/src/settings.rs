@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+use serde::{ Serialize, Deserialize };
+
+#[cfg(feature = "core")]
+use std::path::Path;
+
+use crate::downloader::{ Downloader, DEFAULT_MAX_CONCURRENT };
+
+/// Application wide settings, stored in `settings.json`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+	/// How many downloads may run at once.
+	pub max_concurrent_downloads: usize,
+
+	/// Combined download speed limit in bytes per second, zero means unlimited.
+	pub download_speed_limit: u64,
+
+	/// Directories holding instances besides the one inside of HERO's data directory, each with a directory per game.
+	pub instance_directories: Vec<PathBuf>
+}
+
+impl Settings {
+	#[cfg(feature = "core")]
+	pub fn load(path: &Path) -> Self {
+		match std::fs::read_to_string(path) {
+			Ok(data) => serde_json::from_str(&data).unwrap_or_else(|err| {
+				println!("failed to read settings, using defaults! {}", err);
+				Self::default()
+			}),
+			Err(_) => Self::default()
+		}
+	}
+
+	#[cfg(feature = "core")]
+	pub fn save(&self, path: &Path) -> std::io::Result<()> {
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		std::fs::write(path, serde_json::to_string_pretty(self)?)
+	}
+
+	pub fn apply(&self, downloader: &Downloader) {
+		downloader.set_max_concurrent(self.max_concurrent_downloads);
+		downloader.set_speed_limit(self.download_speed_limit);
+	}
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			max_concurrent_downloads: DEFAULT_MAX_CONCURRENT,
+			download_speed_limit: 0,
+			instance_directories: vec![]
+		}
+	}
+}
\ No newline at end of file
@@ -1,16 +1,24 @@
 use std::sync::Arc;
 use std::path::PathBuf;
+use std::time::{ Duration, Instant };
 
 #[cfg(feature = "core")]
 use std::sync::OnceLock;
+#[cfg(feature = "core")]
+use crate::downloader::{ DownloadCache, DEFAULT_CACHE_SIZE };
+#[cfg(feature = "core")]
+use crate::extension::SettingError;
 
+use std::future::Future;
 use std::collections::HashMap;
 use uuid::Uuid;
-use parking_lot::RwLock;
+use parking_lot::{ RwLock, RwLockReadGuard, RwLockWriteGuard };
 
 use crate::game::Game;
-use crate::instance::Instances;
-use crate::extension::Extensions;
+use crate::instance::{ Instance, Instances, INSTANCES_DIRECTORY };
+use crate::settings::Settings;
+use crate::downloader::{ Download, DownloadRequest, Downloader };
+use crate::extension::{ Capability, CapabilityError, CapabilityGuard, Extensions, ExtensionSettings, HostOnlyError, Service, ServiceError, ServiceRegistry, SettingValue };
 use crate::localisation::Localisation;
 
 #[cfg(feature = "ui")]
@@ -19,27 +27,102 @@ use crate::user_interface::Container;
 #[cfg(feature = "core")]
 static CORE_STATE: OnceLock<RwLock<State>> = OnceLock::new();
 
+/// Everything HERO knows about, shared with extensions.
+/// Only the accessors are public, those that would let an extension see or change more than it has been granted
+/// refuse while one is acting, see [`crate::extension::act_as`].
 pub struct State {
 	pub path: PathBuf,
-	pub instances: RwLock<Instances>,
-	pub extensions: RwLock<Extensions>,
-	pub current_game: RwLock<Option<String>>,
-	pub localisation: RwLock<Localisation>,
-	pub loading_bars: RwLock<HashMap<Uuid, LoadingBar>>,
+	pub(crate) instances: RwLock<Instances>,
+	pub(crate) extensions: RwLock<Extensions>,
+	pub(crate) settings: RwLock<Settings>,
+	pub(crate) downloader: Arc<Downloader>,
+	pub(crate) current_game: RwLock<Option<String>>,
+	pub(crate) localisation: RwLock<Localisation>,
+	pub(crate) loading_bars: RwLock<HashMap<Uuid, LoadingBar>>,
+
+	/// Checks what extensions ask for against what they've been granted.
+	pub(crate) capabilities: CapabilityGuard,
+	pub(crate) services: RwLock<ServiceRegistry>,
+
+	/// Settings of each loaded extension, kept apart from [`Self::extensions`] so extensions can read them during setup.
+	pub(crate) extension_settings: RwLock<HashMap<String, ExtensionSettings>>,
 
 	#[cfg(feature = "ui")]
-	pub ui_containers: RwLock<Vec<Arc<Container>>>
+	pub(crate) ui_containers: RwLock<Vec<Arc<Container>>>
 }
 
 pub struct LoadingBar {
 	pub total: f64,
     pub current: f64,
-	pub bar_type: LoadingBarType
+	pub bar_type: LoadingBarType,
+
+	/// Smoothed rate of progress, in units of `total` per second.
+	pub speed: f64,
+	updated_at: Instant
+}
+
+impl LoadingBar {
+	pub fn new(bar_type: LoadingBarType, total: f64) -> Self {
+		Self {
+			total,
+			current: 0.,
+			bar_type,
+			speed: 0.,
+			updated_at: Instant::now()
+		}
+	}
+
+	pub fn set_current(&mut self, current: f64) {
+		let now = Instant::now();
+		let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+		if elapsed > 0. {
+			let speed = (current - self.current).max(0.) / elapsed;
+			self.speed = if self.speed == 0. { speed } else { self.speed * 0.7 + speed * 0.3 };
+		}
+
+		self.current = current;
+		self.updated_at = now;
+	}
+
+	/// How far along this bar is, from zero to one, if the total is known.
+	pub fn fraction(&self) -> Option<f64> {
+		if self.total > 0. { Some((self.current / self.total).min(1.)) } else { None }
+	}
+
+	/// Estimated time until completion, based on the current speed.
+	pub fn eta(&self) -> Option<Duration> {
+		if self.total > 0. && self.speed > 0. {
+			return Some(Duration::from_secs_f64((self.total - self.current).max(0.) / self.speed));
+		}
+		None
+	}
+
+	pub fn is_complete(&self) -> bool {
+		self.total > 0. && self.current >= self.total
+	}
 }
 
 pub enum LoadingBarType {
 	LoadGame {
 		game_id: String
+	},
+
+	/// Progress is measured in bytes.
+	Download {
+		download_id: Uuid,
+		name: String
+	},
+	Extract {
+		download_id: Uuid,
+		name: String
+	},
+	InstallContent {
+		instance_id: Uuid,
+		item_id: String
+	},
+	CreateInstance {
+		game_id: String,
+		name: String
 	}
 }
 
@@ -54,38 +137,152 @@ impl State {
 
 	#[cfg(feature = "core")]
 	pub fn initialise() -> RwLock<Self> {
-		RwLock::new(Self {
-			path: dirs::config_dir().unwrap().join("HAKUMI").join("HERO"),
+		RwLock::new(Self::new(dirs::config_dir().unwrap().join("HAKUMI").join("HERO")))
+	}
+
+	/// Creates a state keeping everything inside of the given directory.
+	#[cfg(feature = "core")]
+	pub fn new(path: PathBuf) -> Self {
+		let settings = Settings::load(&path.join("settings.json"));
+		let downloader = Downloader::new();
+		downloader.set_cache(Some(DownloadCache::new(path.join("cache"), DEFAULT_CACHE_SIZE)));
+		downloader.set_queue_path(Some(path.join("downloads.json")));
+		downloader.set_capability_guard(Some(CapabilityGuard::new()));
+		settings.apply(&downloader);
+
+		Self {
+			path,
 			instances: RwLock::new(Instances::new()),
 			extensions: RwLock::new(Extensions::new()),
+			settings: RwLock::new(settings),
+			downloader: Arc::new(downloader),
 			current_game: RwLock::new(None),
 			localisation: RwLock::new(Localisation::new()),
 			loading_bars: RwLock::new(HashMap::new()),
+			capabilities: CapabilityGuard::new(),
+			services: RwLock::new(ServiceRegistry::new()),
+			extension_settings: RwLock::new(HashMap::new()),
 
 			#[cfg(feature = "ui")]
 			ui_containers: RwLock::new(vec![])
-		})
+		}
+	}
+
+	/// Swaps the state returned by [`Self::get`] for another one, used by [`crate::testing`].
+	/// This needs every guard returned by [`Self::get`] to be dropped first, including ones held by threads
+	/// that are still running, so instead of deadlocking it panics if one is still alive after a few seconds.
+	#[cfg(feature = "testing")]
+	pub(crate) fn replace(state: Self) {
+		if let Err(state) = CORE_STATE.set(RwLock::new(state)) {
+			let mut current = CORE_STATE.get().unwrap()
+				.try_write_for(std::time::Duration::from_secs(5))
+				.expect("a guard returned by State::get is still alive, drop it before replacing the state");
+			*current = state.into_inner();
+		}
+	}
+
+	/// The installed extensions, only HERO itself may look at or manage them.
+	pub fn extensions(&self) -> Result<RwLockReadGuard<'_, Extensions>, HostOnlyError> {
+		self.capabilities.check_host("manage extensions")?;
+		Ok(self.extensions.read())
+	}
+
+	pub fn extensions_mut(&self) -> Result<RwLockWriteGuard<'_, Extensions>, HostOnlyError> {
+		self.capabilities.check_host("manage extensions")?;
+		Ok(self.extensions.write())
+	}
+
+	/// Every loaded instance, extensions only get to see the ones they may through [`Self::instance_ids`] and [`Self::with_instance`].
+	pub fn instances(&self) -> Result<RwLockReadGuard<'_, Instances>, HostOnlyError> {
+		self.capabilities.check_host("see every instance")?;
+		Ok(self.instances.read())
+	}
+
+	pub fn instances_mut(&self) -> Result<RwLockWriteGuard<'_, Instances>, HostOnlyError> {
+		self.capabilities.check_host("manage instances")?;
+		Ok(self.instances.write())
+	}
+
+	/// Identifier of the selected game, if one has been selected.
+	pub fn current_game_id(&self) -> Option<String> {
+		self.current_game.read().clone()
+	}
+
+	pub fn loading_bars(&self) -> RwLockReadGuard<'_, HashMap<Uuid, LoadingBar>> {
+		self.loading_bars.read()
+	}
+
+	pub fn localisation_mut(&self) -> Result<RwLockWriteGuard<'_, Localisation>, HostOnlyError> {
+		self.capabilities.check_host("change translations")?;
+		Ok(self.localisation.write())
+	}
+
+	/// Settings of every loaded extension, extensions read their own with [`Self::extension_setting`] instead.
+	pub fn extension_settings(&self) -> Result<RwLockReadGuard<'_, HashMap<String, ExtensionSettings>>, HostOnlyError> {
+		self.capabilities.check_host("see the settings of every extension")?;
+		Ok(self.extension_settings.read())
+	}
+
+	/// The shared downloader, which only HERO itself may configure or look through.
+	pub fn downloader(&self) -> Result<&Arc<Downloader>, HostOnlyError> {
+		self.capabilities.check_host("manage downloads")?;
+		Ok(&self.downloader)
+	}
+
+	/// Starts a download, if the acting extension has been granted the host and directory it needs.
+	pub fn download(&self, request: DownloadRequest) -> Download {
+		self.downloader.download_file_over_http(request)
+	}
+
+	/// Downloads started by the acting extension, or every download for HERO itself.
+	pub fn downloads(&self) -> Vec<Download> {
+		let acting = self.capabilities.acting();
+		self.downloader.items().into_iter()
+			.filter(|x| acting.as_ref().map_or(true, |acting| x.extension() == Some(acting.id.as_str())))
+			.collect()
+	}
+
+	/// Starts a thread that runs as whichever extension started it, so it can't do anything the extension couldn't.
+	/// Threads started any other way run as HERO, only WebAssembly extensions are kept from doing that.
+	pub fn spawn_thread<T: Send + 'static>(&self, callback: impl FnOnce() -> T + Send + 'static) -> std::thread::JoinHandle<T> {
+		let guard = self.capabilities;
+		let acting = guard.acting();
+		std::thread::spawn(move || guard.act_as(acting, callback))
+	}
+
+	/// Spawns a task that runs as whichever extension spawned it, see [`Self::spawn_thread`].
+	pub fn spawn_task<F>(&self, future: F) -> tokio::task::JoinHandle<F::Output> where F: Future + Send + 'static, F::Output: Send + 'static {
+		let guard = self.capabilities;
+		let acting = guard.acting();
+		let mut future = Box::pin(future);
+		crate::downloader::runtime_handle().spawn(std::future::poll_fn(move |cx| guard.act_as(acting.clone(), || future.as_mut().poll(cx))))
 	}
 
 	pub fn get_games(&self) -> Vec<Arc<Box<dyn Game>>> {
+		// games are asked for after releasing the lock, so that creating or launching an instance of one never holds it
+		#[cfg(feature = "core")]
+		return {
+			let hooks = self.extensions.read().hooks();
+			hooks.games()
+		};
+
+		#[cfg(not(feature = "core"))]
 		self.extensions.read().items.iter().filter_map(|x| x.games()).flatten().collect()
 	}
 
 	pub fn get_game(&self, id: String) -> Option<Arc<Box<dyn Game>>> {
-		for extension in self.extensions.read().items.iter() {
-			if let Some(games) = extension.games() {
-				for game in games {
-					if game.id().to_string() == id {
-						return Some(game);
-					}
-				}
-			}
-		}
-		None
+		self.get_games().into_iter().find(|x| x.id() == id)
 	}
 
 	pub fn get_current_game(&self) -> Option<Arc<Box<dyn Game>>> {
-		self.current_game.read().clone().and_then(|x| self.get_game(x))
+		// the lock is released first, as looking the game up takes the lock on the extensions
+		let game_id = self.current_game_id()?;
+		self.get_game(game_id)
+	}
+
+	#[cfg(feature = "ui")]
+	pub fn ui_containers(&self) -> Vec<Arc<Container>> {
+		self.ui_containers.read().clone()
 	}
 
 	#[cfg(feature = "ui")]
@@ -97,11 +294,7 @@ impl State {
 	#[cfg(feature = "core")]
 	pub fn init_loading(&self, bar_type: LoadingBarType, total: f64) -> Uuid {
 		let id = Uuid::new_v4();
-		self.loading_bars.write().insert(id, LoadingBar {
-			total,
-			current: 0.,
-			bar_type
-		});
+		self.loading_bars.write().insert(id, LoadingBar::new(bar_type, total));
 
 		id
 	}
@@ -109,12 +302,119 @@ impl State {
 	#[cfg(feature = "core")]
 	pub fn add_loading(&self, bar_id: &Uuid, amount: f64) {
 		let mut bars = self.loading_bars.write();
-		if let Some(bar) = bars.get_mut(&bar_id) {
-			bar.current += amount;
+		if let Some(bar) = bars.get_mut(bar_id) {
+			bar.set_current(bar.current + amount);
 			if bar.current >= bar.total {
-				bars.remove(&bar_id);
+				bars.remove(bar_id);
+			}
+		}
+	}
+
+	#[cfg(feature = "core")]
+	pub fn set_loading(&self, bar_id: &Uuid, current: f64, total: Option<f64>) {
+		let mut bars = self.loading_bars.write();
+		if let Some(bar) = bars.get_mut(bar_id) {
+			if let Some(total) = total {
+				bar.total = total;
+			}
+			bar.set_current(current);
+			if bar.is_complete() {
+				bars.remove(bar_id);
+			}
+		}
+	}
+
+	#[cfg(feature = "core")]
+	pub fn finish_loading(&self, bar_id: &Uuid) {
+		self.loading_bars.write().remove(bar_id);
+	}
+
+	/// Every directory instances are loaded from, starting with the one inside of [`Self::path`].
+	pub fn instance_directories(&self) -> Vec<PathBuf> {
+		let mut directories = vec![self.path.join(INSTANCES_DIRECTORY)];
+		for directory in &self.settings.read().instance_directories {
+			if !directories.contains(directory) {
+				directories.push(directory.clone());
 			}
 		}
+		directories
+	}
+
+	pub fn settings(&self) -> Settings {
+		self.settings.read().clone()
+	}
+
+	/// Replaces the current settings, applying and saving them. Only HERO itself may change them.
+	#[cfg(feature = "core")]
+	pub fn set_settings(&self, settings: Settings) -> Result<(), HostOnlyError> {
+		self.capabilities.check_host("change settings")?;
+		settings.apply(&self.downloader);
+		if let Err(err) = settings.save(&self.path.join("settings.json")) {
+			println!("failed to save settings! {}", err);
+		}
+		*self.settings.write() = settings;
+		Ok(())
+	}
+
+	/// Checks that whichever extension is running may use a capability, HERO itself always can.
+	pub fn check(&self, capability: &Capability) -> Result<(), CapabilityError> {
+		self.capabilities.check(capability)
+	}
+
+	/// Starts a program, if the acting extension is allowed to.
+	pub fn spawn(&self, command: &mut std::process::Command) -> std::io::Result<std::process::Child> {
+		self.check(&Capability::Process).map_err(|err| std::io::Error::new(std::io::ErrorKind::PermissionDenied, err))?;
+		command.spawn()
+	}
+
+	/// Shares a service with other extensions, usually from [`crate::extension::Extension::setup`].
+	/// It is removed again when the providing extension is unloaded.
+	pub fn provide_service<T: Service>(&self, service: Arc<T>) -> Result<(), ServiceError> {
+		self.services.write().provide(self.capabilities.acting().as_deref(), service)
+	}
+
+	/// Looks up a service provided by an extension the acting one depends on.
+	pub fn service<T: Service>(&self) -> Option<Arc<T>> {
+		self.services.read().get(self.capabilities.acting().as_deref())
+	}
+
+	/// Whether or not the acting extension may touch the settings of an extension.
+	fn owns_settings(&self, id: &str) -> bool {
+		self.capabilities.acting().map_or(true, |x| x.id == id)
+	}
+
+	/// Current value of one of an extension's settings, extensions can only read their own.
+	pub fn extension_setting(&self, id: &str, key: &str) -> Option<SettingValue> {
+		if !self.owns_settings(id) {
+			return None;
+		}
+		self.extension_settings.read().get(id)?.get(key)
+	}
+
+	#[cfg(feature = "core")]
+	pub fn set_extension_setting(&self, id: &str, key: &str, value: SettingValue) -> Result<(), SettingError> {
+		if !self.owns_settings(id) {
+			return Err(SettingError::Forbidden(id.into()));
+		}
+		self.extension_settings.write()
+			.get_mut(id)
+			.ok_or_else(|| SettingError::UnknownKey(key.into()))?
+			.set(key, value)
+	}
+
+	/// Identifiers of the loaded instances the acting extension may see.
+	pub fn instance_ids(&self) -> Vec<Uuid> {
+		self.instances.read().items.values()
+			.filter(|x| self.capabilities.can_see_game(&x.game_id))
+			.map(|x| x.id())
+			.collect()
+	}
+
+	/// Runs the callback with an instance, returning nothing if it doesn't exist or the acting extension may not see it.
+	pub fn with_instance<T>(&self, id: &Uuid, callback: impl FnOnce(&Instance) -> T) -> Option<T> {
+		self.instances.read().items.get(id)
+			.filter(|x| self.capabilities.can_see_game(&x.game_id))
+			.map(callback)
 	}
 
 	pub fn t(&self, key: String) -> String {
@@ -128,12 +428,100 @@ pub async fn load_game(game_id: String) {
 	*state.current_game.write() = Some(game_id.clone());
 
 	let id = state.init_loading(LoadingBarType::LoadGame { game_id: game_id.clone() }, 2.);
+	let hooks = state.extensions.read().hooks();
+	hooks.dispatch("on_game_selected", |x| x.on_game_selected(&game_id));
 
-	let game = state.get_game(game_id).unwrap();
+	let game = state.get_game(game_id.clone()).unwrap();
 	game.pre_load();
 
 	state.add_loading(&id, 1.);
 	crate::instance::load_instances();
 
+	let instances: Vec<Uuid> = state.instances.read().items.keys().copied().collect();
+	hooks.dispatch("on_instances_loaded", |x| x.on_instances_loaded(&game_id, &instances));
+
 	state.add_loading(&id, 1.);
+}
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+	use super::*;
+	use crate::testing::TestState;
+	use crate::extension::{ act_as, acting_extension };
+	use crate::extension::capability::tests::permissions;
+
+	#[test]
+	fn refuses_host_only_access_while_an_extension_acts() {
+		let test = TestState::new();
+		let state = test.state();
+		let acting = Some(Arc::new(permissions(vec![], vec![])));
+
+		act_as(acting.clone(), || {
+			assert!(state.extensions().is_err());
+			assert!(state.extensions_mut().is_err());
+			assert!(state.instances().is_err());
+			assert!(state.instances_mut().is_err());
+			assert!(state.extension_settings().is_err());
+			assert!(state.downloader().is_err());
+			assert!(state.set_settings(state.settings()).is_err());
+		});
+		let mut extensions = state.extensions_mut().unwrap();
+		assert!(act_as(acting, || extensions.grant("test", &[Capability::Process])).is_err());
+		assert!(extensions.grant("test", &[Capability::Process]).is_ok());
+		drop(extensions);
+
+		assert!(state.instances().is_ok());
+		assert!(state.set_settings(state.settings()).is_ok());
+	}
+
+	#[test]
+	fn runs_spawned_work_as_the_extension_that_spawned_it() {
+		let test = TestState::new();
+		let state = test.state();
+		let runtime = tokio::runtime::Runtime::new().unwrap();
+
+		let (thread, task, plain) = act_as(Some(Arc::new(permissions(vec![], vec![]))), || (
+			state.spawn_thread(|| acting_extension().map(|x| x.id.clone())),
+			state.spawn_task(async { acting_extension().map(|x| x.id.clone()) }),
+			std::thread::spawn(|| acting_extension().map(|x| x.id.clone()))
+		));
+		assert_eq!(thread.join().unwrap().as_deref(), Some("test"));
+		assert_eq!(runtime.block_on(task).unwrap().as_deref(), Some("test"));
+		assert_eq!(plain.join().unwrap(), None);
+		assert!(acting_extension().is_none());
+	}
+
+	/// Moves a bar's progress to `current` as if `seconds` had passed since its last update.
+	fn advance(bar: &mut LoadingBar, current: f64, seconds: f64) {
+		bar.updated_at = Instant::now() - Duration::from_secs_f64(seconds);
+		bar.set_current(current);
+	}
+
+	#[test]
+	fn smooths_loading_bar_speed_into_an_estimate() {
+		let mut bar = LoadingBar::new(LoadingBarType::Download { download_id: Uuid::new_v4(), name: "file".into() }, 1000.);
+		assert_eq!((bar.fraction(), bar.eta()), (Some(0.), None));
+
+		advance(&mut bar, 200., 2.);
+		assert!((bar.speed - 100.).abs() < 1., "{}", bar.speed);
+		assert!((bar.eta().unwrap().as_secs_f64() - 8.).abs() < 0.1);
+
+		// the first measurement sets the speed, later ones only pull it part of the way
+		advance(&mut bar, 500., 1.);
+		assert!((bar.speed - 160.).abs() < 1., "{}", bar.speed);
+		assert_eq!(bar.fraction(), Some(0.5));
+
+		let speed = bar.speed;
+		advance(&mut bar, 400., 1.);
+		assert!(bar.speed > 0. && bar.speed < speed);
+
+		advance(&mut bar, 1200., 1.);
+		assert_eq!((bar.fraction(), bar.eta(), bar.is_complete()), (Some(1.), Some(Duration::ZERO), true));
+	}
+
+	#[test]
+	fn has_no_estimate_without_a_total() {
+		let mut bar = LoadingBar::new(LoadingBarType::LoadGame { game_id: "game".into() }, 0.);
+		advance(&mut bar, 10., 1.);
+		assert_eq!((bar.fraction(), bar.eta(), bar.is_complete()), (None, None, false));
+	}
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use std::fs;
 use std::path::PathBuf;
 use std::collections::HashMap;
+
+#[derive(Clone)]
 pub struct PLUTO {
 	path: PathBuf,
 	items: HashMap<String, String>
@@ -12,33 +14,37 @@ impl PLUTO {
 		Self { path, items }
 	}
 
+	pub fn items(&self) -> &HashMap<String, String> {
+		&self.items
+	}
+
 	pub fn get<T: From<String>>(&self, key: impl Into<String>) -> Option<T> {
 		self.items.get(&key.into()).map(|x| x.clone().into())
 	}
 
 	pub fn set<T: Into<String>>(&mut self, key: impl Into<String>, value: T) {
 		self.items.insert(key.into(), value.into());
+		self.save().unwrap();
+	}
 
+	pub fn save(&self) -> std::io::Result<()> {
 		let data = self.items.iter().enumerate().map(|(_,x)| format!("{}\n{}", x.0, x.1)).collect::<Vec<String>>().join("\n");
-		fs::write(&self.path, data).unwrap();
+		fs::write(&self.path, data)
 	}
 }
 
-pub fn read_pluto_proto_file(path: PathBuf) -> Result<PLUTO, ()> {
+pub fn read_pluto_proto_file(path: PathBuf) -> std::io::Result<PLUTO> {
 	if path.exists() {
-		if let Ok(data) = fs::read_to_string(&path) {
-			let mut items = HashMap::new();
-	
-			let lines = data.lines();
-			for a in lines.clone().step_by(2).zip(lines.skip(1).step_by(2)) {
-				println!("{:?}", a);
-				items.insert(a.0.into(), a.1.into());
-			}
-	
-			return Ok(PLUTO::new(path, items));
+		let data = fs::read_to_string(&path)?;
+		let mut items = HashMap::new();
+
+		let lines = data.lines();
+		for a in lines.clone().step_by(2).zip(lines.skip(1).step_by(2)) {
+			println!("{:?}", a);
+			items.insert(a.0.into(), a.1.into());
 		}
-	} else {
-		return Ok(PLUTO::new(path, HashMap::new()))
+
+		return Ok(PLUTO::new(path, items));
 	}
-	Err(())
+	Ok(PLUTO::new(path, HashMap::new()))
 }
\ No newline at end of file
@@ -0,0 +1,536 @@
+use std::sync::Arc;
+use std::path::{ Path, PathBuf };
+use uuid::Uuid;
+use parking_lot::{ Mutex, MutexGuard, RwLockReadGuard };
+
+use crate::{ Icon, IconData };
+use crate::game::Game;
+use crate::state::State;
+use crate::instance::{ DisplayNames, Instance, InstanceMetadata, INSTANCES_DIRECTORY, INSTANCE_FORMAT_VERSION, METADATA_FILE };
+use crate::extension::{ Extension, ExtensionSettings, SettingDefinition, SettingValue, SETTINGS_DIRECTORY };
+use crate::content::{ ContentProvider, ContentProviderItem, ContentProviderSearchSortType };
+
+/// There is only one [`State`] per process, so tests using it take turns.
+static STATE_LOCK: Mutex<()> = parking_lot::const_mutex(());
+
+/// Replaces the global [`State`] with one living in a temporary directory, which is deleted again when this is dropped.
+/// Only one of these can exist at a time, creating another waits until the first is dropped.
+/// Guards returned by [`TestState::state`] or [`State::get`] must be dropped before this is, see [`State::replace`].
+pub struct TestState {
+	path: PathBuf,
+	runtime: tokio::runtime::Runtime,
+	_lock: MutexGuard<'static, ()>
+}
+
+impl TestState {
+	pub fn new() -> Self {
+		let lock = STATE_LOCK.lock();
+		let path = super::temp_dir("test");
+		State::replace(State::new(path.clone()));
+
+		Self {
+			path,
+			runtime: tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap(),
+			_lock: lock
+		}
+	}
+
+	/// The temporary directory standing in for HERO's data directory.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	pub fn state(&self) -> Arc<RwLockReadGuard<'static, State>> {
+		State::get()
+	}
+
+	/// Registers an extension as if it had been loaded from a library, running its setup.
+	/// It acts as HERO itself, so capabilities aren't checked.
+	pub fn add_extension(&self, extension: impl Extension + 'static) {
+		let state = State::get();
+		let id = extension.id();
+		let path = state.path.join(SETTINGS_DIRECTORY).join(format!("{}.json", id));
+		state.extension_settings.write().insert(id.into(), ExtensionSettings::load(path, extension.settings()));
+
+		extension.setup();
+
+		let mut localisation = state.localisation.write();
+		for (locale, data) in extension.localisation() {
+			localisation.insert_data(locale, data);
+		}
+
+		#[cfg(feature = "ui")]
+		state.ui_containers.write().extend(extension.ui_containers().into_iter().map(Arc::new));
+		state.extensions.write().items.push(Arc::new(extension));
+	}
+
+	/// Writes an empty instance of a game to disk, it shows up once the game is loaded.
+	pub fn add_instance(&self, game_id: &str, name: &str) -> Uuid {
+		let id = Uuid::new_v4();
+		let path = self.path.join(INSTANCES_DIRECTORY).join(game_id).join(name);
+		std::fs::create_dir_all(&path).unwrap();
+		let metadata = InstanceMetadata {
+			format_version: INSTANCE_FORMAT_VERSION,
+			id,
+			author: "test".into(),
+			icon_path: None,
+			created_at: std::time::SystemTime::now(),
+			display_names: DisplayNames { custom: name.into() }
+		};
+		std::fs::write(path.join(METADATA_FILE), serde_json::to_string_pretty(&metadata).unwrap()).unwrap();
+		std::fs::write(path.join(format!("{}_meta.PLUTO_PROTO", game_id)), "").unwrap();
+
+		id
+	}
+
+	/// Selects a game and loads its instances, see [`crate::state::load_game`].
+	pub fn load_game(&self, game_id: &str) {
+		self.runtime.block_on(crate::state::load_game(game_id.into()));
+	}
+
+	/// Reloads the instances of the selected game.
+	pub fn load_instances(&self) {
+		crate::instance::load_instances();
+	}
+
+	/// Launches an instance of the selected game, waiting until its process exits.
+	pub fn launch(&self, instance_id: Uuid) {
+		self.runtime.block_on(crate::instance::launch(instance_id));
+	}
+}
+
+impl Default for TestState {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Drop for TestState {
+	fn drop(&mut self) {
+		crate::extension::shutdown_extensions(&State::get()).unwrap();
+		let _ = std::fs::remove_dir_all(&self.path);
+	}
+}
+
+/// A game that records what HERO asks of it instead of starting anything, clones share their records.
+#[derive(Clone)]
+pub struct FakeGame {
+	id: &'static str,
+	creation_options: Vec<SettingDefinition>,
+	pre_loads: Arc<Mutex<usize>>,
+	launches: Arc<Mutex<Vec<Uuid>>>
+}
+
+impl FakeGame {
+	pub fn new(id: &'static str) -> Self {
+		Self {
+			id,
+			creation_options: vec![],
+			pre_loads: Arc::default(),
+			launches: Arc::default()
+		}
+	}
+
+	pub fn creation_option(mut self, option: SettingDefinition) -> Self {
+		self.creation_options.push(option);
+		self
+	}
+
+	/// How many times the game has been selected.
+	pub fn pre_loads(&self) -> usize {
+		*self.pre_loads.lock()
+	}
+
+	/// Instances launched so far, in order.
+	pub fn launches(&self) -> Vec<Uuid> {
+		self.launches.lock().clone()
+	}
+}
+
+impl Icon for FakeGame {
+	fn icon(&self) -> Option<IconData> {
+		None
+	}
+}
+
+impl Game for FakeGame {
+	fn id(&self) -> &'static str {
+		self.id
+	}
+
+	fn launch(&self, instance: &Instance) -> Option<std::process::Child> {
+		self.launches.lock().push(instance.id());
+		None
+	}
+
+	fn pre_load(&self) {
+		*self.pre_loads.lock() += 1;
+	}
+
+	fn creation_options(&self) -> Vec<SettingDefinition> {
+		self.creation_options.clone()
+	}
+}
+
+/// A content provider serving a fixed list of items, clones share their records.
+#[derive(Clone)]
+pub struct FakeContentProvider {
+	name: &'static str,
+	games: Vec<&'static str>,
+	items: Vec<ContentProviderItem>,
+	installed: Arc<Mutex<Vec<(Uuid, String)>>>
+}
+
+impl FakeContentProvider {
+	pub fn new(name: &'static str) -> Self {
+		Self {
+			name,
+			games: vec![],
+			items: vec![],
+			installed: Arc::default()
+		}
+	}
+
+	pub fn game(mut self, game_id: &'static str) -> Self {
+		self.games.push(game_id);
+		self
+	}
+
+	pub fn item(mut self, id: impl Into<String>, name: impl Into<String>) -> Self {
+		self.items.push(ContentProviderItem {
+			id: id.into(),
+			name: name.into(),
+			icon: None,
+			authors: None,
+			summary: None,
+			can_be_installed: true
+		});
+		self
+	}
+
+	/// Instances and items installed so far, in order.
+	pub fn installed(&self) -> Vec<(Uuid, String)> {
+		self.installed.lock().clone()
+	}
+}
+
+impl ContentProvider for FakeContentProvider {
+	fn name(&self) -> &'static str {
+		self.name
+	}
+
+	/// Items whose name contains the query, ignoring case, in the order they were added.
+	fn search(&mut self, query: String, _sort_type: ContentProviderSearchSortType, descending: bool) -> Vec<ContentProviderItem> {
+		let query = query.to_lowercase();
+		let mut items: Vec<ContentProviderItem> = self.items.iter().filter(|x| x.name.to_lowercase().contains(&query)).cloned().collect();
+		if descending {
+			items.reverse();
+		}
+		items
+	}
+
+	fn install(&mut self, instance: &Instance, item_id: &str) {
+		self.installed.lock().push((instance.id(), item_id.into()));
+	}
+
+	fn supported_types(&self) -> Vec<&'static str> {
+		self.games.clone()
+	}
+}
+
+/// An extension built from fakes, which records every hook it receives, clones share their records.
+#[derive(Clone)]
+pub struct FakeExtension {
+	id: &'static str,
+	games: Vec<Arc<Box<dyn Game>>>,
+	content_providers: Vec<FakeContentProvider>,
+	settings: Vec<SettingDefinition>,
+	events: Arc<Mutex<Vec<String>>>
+}
+
+impl FakeExtension {
+	pub fn new(id: &'static str) -> Self {
+		Self {
+			id,
+			games: vec![],
+			content_providers: vec![],
+			settings: vec![],
+			events: Arc::default()
+		}
+	}
+
+	pub fn game(mut self, game: impl Game + 'static) -> Self {
+		self.games.push(Arc::new(Box::new(game)));
+		self
+	}
+
+	pub fn content_provider(mut self, provider: FakeContentProvider) -> Self {
+		self.content_providers.push(provider);
+		self
+	}
+
+	pub fn setting(mut self, setting: SettingDefinition) -> Self {
+		self.settings.push(setting);
+		self
+	}
+
+	/// Hooks received so far, such as `setup` or `before_launch <instance id>`, in order.
+	pub fn events(&self) -> Vec<String> {
+		self.events.lock().clone()
+	}
+
+	fn record(&self, event: String) {
+		self.events.lock().push(event);
+	}
+}
+
+impl Icon for FakeExtension {
+	fn icon(&self) -> Option<IconData> {
+		None
+	}
+}
+
+impl Extension for FakeExtension {
+	fn id(&self) -> &'static str {
+		self.id
+	}
+
+	fn setup(&self) {
+		self.record("setup".into());
+	}
+
+	fn on_game_selected(&self, game_id: &str) {
+		self.record(format!("game_selected {}", game_id));
+	}
+
+	fn on_instances_loaded(&self, game_id: &str, instances: &[Uuid]) {
+		self.record(format!("instances_loaded {} {}", game_id, instances.len()));
+	}
+
+	fn before_launch(&self, instance: &Instance) {
+		self.record(format!("before_launch {}", instance.id()));
+	}
+
+	fn after_launch(&self, instance: &Instance) {
+		self.record(format!("after_launch {}", instance.id()));
+	}
+
+	fn on_process_exited(&self, instance: &Instance, exit_code: Option<i32>) {
+		self.record(format!("process_exited {} {:?}", instance.id(), exit_code));
+	}
+
+	fn on_content_installed(&self, instance: &Instance, item_id: &str) {
+		self.record(format!("content_installed {} {}", instance.id(), item_id));
+	}
+
+	fn on_shutdown(&self) {
+		self.record("shutdown".into());
+	}
+
+	fn settings(&self) -> Vec<SettingDefinition> {
+		self.settings.clone()
+	}
+
+	fn on_setting_changed(&self, key: &str, value: &SettingValue) {
+		self.record(format!("setting_changed {} {}", key, value));
+	}
+
+	fn games(&self) -> Option<Vec<Arc<Box<dyn Game>>>> {
+		if self.games.is_empty() { None } else { Some(self.games.clone()) }
+	}
+
+	fn content_providers(&self) -> Vec<Box<dyn ContentProvider>> {
+		self.content_providers.iter().map(|x| Box::new(x.clone()) as Box<dyn ContentProvider>).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::extension::SettingKind;
+	use crate::instance::{ create_instance, NewInstance };
+
+	fn fake_game() -> FakeGame {
+		FakeGame::new("fake_game").creation_option(SettingDefinition {
+			key: "mode".into(),
+			name: "Mode".into(),
+			kind: SettingKind::String { default: "survival".into() }
+		})
+	}
+
+	#[test]
+	fn loads_games_with_their_instances() {
+		let game = fake_game();
+		let extension = FakeExtension::new("fake_extension").game(game.clone());
+		let test = TestState::new();
+		test.add_extension(extension.clone());
+		let instance = test.add_instance("fake_game", "Survival");
+
+		test.load_game("fake_game");
+		assert_eq!(game.pre_loads(), 1);
+		assert!(test.state().instances.read().items.contains_key(&instance));
+		assert_eq!(extension.events(), vec![
+			"setup".to_string(),
+			"game_selected fake_game".into(),
+			"instances_loaded fake_game 1".into()
+		]);
+	}
+
+	#[test]
+	fn creates_instances_with_creation_options() {
+		let game = fake_game();
+		let test = TestState::new();
+		test.add_extension(FakeExtension::new("fake_extension").game(game.clone()));
+		test.load_game("fake_game");
+
+		let id = create_instance(NewInstance::new("fake_game", "Creative").option("mode", "creative")).unwrap();
+		let state = test.state();
+		let instances = state.instances.read();
+		let instance = instances.items.get(&id).expect("instance of the selected game was not loaded");
+		assert_eq!(instance.display_name(), "Creative");
+		assert_eq!(instance.game_meta.get::<String>("mode").as_deref(), Some("creative"));
+		assert!(test.path().join(INSTANCES_DIRECTORY).join("fake_game").join("Creative").join(METADATA_FILE).is_file());
+		drop(instances);
+		drop(state);
+
+		assert!(create_instance(NewInstance::new("fake_game", "Broken").option("unknown", "value")).is_err());
+		assert!(create_instance(NewInstance::new("missing_game", "Missing")).is_err());
+	}
+
+	/// Records whether the extensions and instances could be locked for writing from inside of its hooks.
+	#[derive(Clone, Default)]
+	struct LockingExtension {
+		locked: Arc<Mutex<Vec<bool>>>
+	}
+
+	impl LockingExtension {
+		fn lock(&self) {
+			let state = State::get();
+			let timeout = std::time::Duration::from_secs(1);
+			let locked = state.extensions.try_write_for(timeout).is_some() && state.instances.try_write_for(timeout).is_some();
+			self.locked.lock().push(locked);
+		}
+	}
+
+	impl Icon for LockingExtension {
+		fn icon(&self) -> Option<IconData> {
+			None
+		}
+	}
+
+	impl Extension for LockingExtension {
+		fn id(&self) -> &'static str {
+			"locking_extension"
+		}
+
+		fn on_game_selected(&self, _game_id: &str) {
+			self.lock();
+		}
+
+		fn on_instances_loaded(&self, _game_id: &str, _instances: &[Uuid]) {
+			self.lock();
+		}
+
+		fn before_launch(&self, _instance: &Instance) {
+			self.lock();
+		}
+
+		fn after_launch(&self, _instance: &Instance) {
+			self.lock();
+		}
+
+		fn on_shutdown(&self) {
+			self.lock();
+		}
+
+		fn games(&self) -> Option<Vec<Arc<Box<dyn Game>>>> {
+			self.lock();
+			None
+		}
+	}
+
+	#[test]
+	fn calls_hooks_without_holding_locks() {
+		let locking = LockingExtension::default();
+		let test = TestState::new();
+		test.add_extension(FakeExtension::new("fake_extension").game(fake_game()));
+		test.add_extension(locking.clone());
+		let instance = test.add_instance("fake_game", "Survival");
+
+		test.load_game("fake_game");
+		test.launch(instance);
+		let locked = locking.locked.lock();
+		assert!(locked.len() > 4 && locked.iter().all(|x| *x), "{:?}", locked);
+	}
+
+	#[test]
+	fn shuts_down_without_holding_locks() {
+		let locking = LockingExtension::default();
+		let test = TestState::new();
+		test.add_extension(locking.clone());
+
+		crate::extension::shutdown_extensions(&test.state()).unwrap();
+		crate::extension::shutdown_extensions(&test.state()).unwrap();
+		assert_eq!(*locking.locked.lock(), vec![true]);
+	}
+
+	/// A game that records whether the extensions and instances could be locked for writing while it was being asked about an instance.
+	#[derive(Clone, Default)]
+	struct LockingGame {
+		extension: LockingExtension
+	}
+
+	impl Icon for LockingGame {
+		fn icon(&self) -> Option<IconData> {
+			None
+		}
+	}
+
+	impl Game for LockingGame {
+		fn id(&self) -> &'static str {
+			"locking_game"
+		}
+
+		fn launch(&self, _instance: &Instance) -> Option<std::process::Child> {
+			None
+		}
+
+		fn pre_load(&self) {}
+
+		fn creation_options(&self) -> Vec<SettingDefinition> {
+			self.extension.lock();
+			vec![]
+		}
+
+		fn on_instance_created(&self, _instance: &Instance) {
+			self.extension.lock();
+		}
+	}
+
+	#[test]
+	fn creates_instances_without_holding_locks() {
+		let game = LockingGame::default();
+		let test = TestState::new();
+		test.add_extension(FakeExtension::new("fake_extension").game(game.clone()));
+		test.add_extension(game.extension.clone());
+
+		create_instance(NewInstance::new("locking_game", "Survival")).unwrap();
+		let locked = game.extension.locked.lock();
+		assert!(locked.len() == 3 && locked.iter().all(|x| *x), "{:?}", locked);
+	}
+
+	#[test]
+	fn launches_instances() {
+		let game = fake_game();
+		let extension = FakeExtension::new("fake_extension").game(game.clone());
+		let test = TestState::new();
+		test.add_extension(extension.clone());
+		let instance = test.add_instance("fake_game", "Survival");
+		test.load_game("fake_game");
+
+		test.launch(instance);
+		test.launch(Uuid::new_v4());
+		assert_eq!(game.launches(), vec![instance]);
+		assert_eq!(extension.events()[3..], [format!("before_launch {}", instance), format!("after_launch {}", instance)]);
+	}
+}
\ No newline at end of file
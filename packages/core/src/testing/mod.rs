@@ -0,0 +1,26 @@
+//! Helpers for testing extensions without touching the user's HERO directory, enabled by the `testing` feature.
+//!
+//! ```ignore
+//! let game = FakeGame::new("my_game");
+//! let test = TestState::new();
+//! test.add_extension(FakeExtension::new("my_extension").game(game.clone()));
+//! let instance = test.add_instance("my_game", "Survival");
+//!
+//! test.load_game("my_game");
+//! test.launch(instance);
+//! assert_eq!(game.launches(), vec![instance]);
+//! ```
+use std::path::PathBuf;
+
+#[cfg(feature = "testing")]
+mod fakes;
+
+#[cfg(feature = "testing")]
+pub use fakes::*;
+
+/// Creates an empty directory under the system's temporary directory, its name starting with `hero-{name}-` so leftovers can be traced back.
+pub fn temp_dir(name: &str) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("hero-{}-{}", name, uuid::Uuid::new_v4()));
+	std::fs::create_dir_all(&path).unwrap();
+	path
+}
\ No newline at end of file
@@ -17,8 +17,10 @@ pub struct Container {
 	pub render: Box<dyn Fn() -> Vec<Element> + Send + Sync>
 }
 
+pub type InstancePageFilter = Box<dyn Fn(&Instance, Arc<Box<dyn Game>>) -> bool + Send + Sync>;
+
 pub enum ContainerKind {
-	InstancePage(Option<Box<dyn Fn(&Instance, Arc<Box<dyn Game>>) -> bool + Send + Sync>>)
+	InstancePage(Option<InstancePageFilter>)
 }
 
 pub enum Element {
@@ -26,6 +28,23 @@ pub enum Element {
 	PathSelect {
 		kind: PathSelectKind,
 		value: Value<String>
+	},
+	TextInput {
+		value: Value<String>
+	},
+
+	/// Reads and writes `true` or `false`.
+	Checkbox {
+		value: Value<String>
+	},
+	Dropdown {
+		options: Vec<String>,
+		value: Value<String>
+	},
+	NumberInput {
+		min: Option<f64>,
+		max: Option<f64>,
+		value: Value<String>
 	}
 }
 
@@ -46,26 +65,45 @@ impl<T: From<String> + Clone> Value<T> {
 		match self.clone() {
 			Value::Static(value) => Some(value),
 			Value::Link(link) => match link {
-				ValueLink::InstanceGameMeta(key) => instance.and_then(|x| x.game_meta.get::<T>(key.clone()))
+				ValueLink::InstanceGameMeta(key) => instance.and_then(|x| x.game_meta.get::<T>(key.clone())),
+				ValueLink::ExtensionSetting { extension, key } => State::get()
+					.extension_setting(&extension, &key)
+					.map(|x| x.to_string().into())
 			}
 		}
 	}
 
+	/// Stores a new value right away, so that it is read back on the next frame and writes keep their order.
+	/// This locks the instances, or the extension settings, so don't call it while holding a guard on them.
 	pub fn write(self, value: impl Into<String>, instance_id: Option<Uuid>) {
-		match self {
-			Value::Link(link) => match link {
+		if let Value::Link(link) = self {
+			let value: String = value.into();
+			let state = State::get();
+			match link {
 				ValueLink::InstanceGameMeta(key) => {
-					let value: String = value.into();
-					std::thread::spawn(move || {
-						let state = State::get();
-						let instances = &mut state.instances.write().items;
-						if let Some(instance) = instance_id.and_then(|x| instances.get_mut(&x)) {
-							instance.game_meta.set(key, value);
-						}
-					});
+					let instances = &mut state.instances.write().items;
+					if let Some(instance) = instance_id.and_then(|x| instances.get_mut(&x)) {
+						instance.game_meta.set(key, value);
+					}
+				},
+				ValueLink::ExtensionSetting { extension, key } => {
+					let parsed = state.extension_settings.read()
+						.get(&extension)
+						.and_then(|x| x.definition(&key))
+						.and_then(|x| x.kind.parse(&value));
+					let Some(value) = parsed else {
+						return println!("invalid value for setting {} of {}: {}", key, extension, value);
+					};
+
+					match state.set_extension_setting(&extension, &key, value.clone()) {
+						Ok(_) => {
+							let hooks = state.extensions.read().hooks();
+							hooks.dispatch_to(&extension, "on_setting_changed", |x| x.on_setting_changed(&key, &value));
+						},
+						Err(err) => println!("failed to save setting {} of {}! {}", key, extension, err)
+					}
 				}
-			},
-			_ => {}
+			}
 		}
 	}
 }
@@ -78,5 +116,34 @@ impl<T: From<String> + Clone> From<T> for Value<T> {
 
 #[derive(Clone)]
 pub enum ValueLink {
-	InstanceGameMeta(String)
+	InstanceGameMeta(String),
+	ExtensionSetting {
+		extension: String,
+		key: String
+	}
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+	use super::*;
+	use crate::extension::{ SettingDefinition, SettingKind };
+	use crate::testing::{ FakeExtension, TestState };
+
+	#[test]
+	fn writes_settings_in_order() {
+		let extension = FakeExtension::new("fake_extension").setting(SettingDefinition {
+			key: "name".into(),
+			name: "Name".into(),
+			kind: SettingKind::String { default: String::new() }
+		});
+		let test = TestState::new();
+		test.add_extension(extension.clone());
+
+		let value: Value<String> = Value::Link(ValueLink::ExtensionSetting { extension: "fake_extension".into(), key: "name".into() });
+		for text in ["a", "ab", "abc"] {
+			value.clone().write(text, None);
+			assert_eq!(value.read(None).as_deref(), Some(text));
+		}
+		assert_eq!(extension.events()[1..], ["setting_changed name a", "setting_changed name ab", "setting_changed name abc"]);
+	}
 }
\ No newline at end of file